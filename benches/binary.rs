@@ -0,0 +1,43 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rust2048::game::{Grid, Position};
+
+/// 4x4 board with every square taken
+fn full_board() -> Grid {
+    let tiles: Vec<(Position, u32)> = (0..16u16)
+        .map(|i| (Position::new(i % 4, i / 4), 2 << i))
+        .collect();
+    Grid::new_from_positions(6, 4, &tiles)
+}
+
+fn serialize(c: &mut Criterion) {
+    let grid = full_board();
+    let mut group = c.benchmark_group("serialize 4x4");
+    group.bench_function("binary", |b| b.iter(|| black_box(&grid).serialize_binary()));
+    group.bench_function("serde_json", |b| {
+        b.iter(|| serde_json::to_string(&black_box(&grid).to_matrix()).unwrap())
+    });
+    group.finish();
+}
+
+fn deserialize(c: &mut Criterion) {
+    let grid = full_board();
+    let binary = grid.serialize_binary();
+    let json = serde_json::to_string(&grid.to_matrix()).unwrap();
+    let mut group = c.benchmark_group("deserialize 4x4");
+    group.bench_function("binary", |b| {
+        b.iter(|| Grid::deserialize_binary(black_box(&binary)).unwrap())
+    });
+    group.bench_function("serde_json", |b| {
+        b.iter(|| {
+            let matrix: Vec<Vec<u32>> = serde_json::from_str(black_box(&json)).unwrap();
+            let mut grid = Grid::new_from_positions(6, 4, &[]);
+            let board: Vec<u32> = matrix.into_iter().flatten().collect();
+            grid.import_board(&board).unwrap();
+            grid
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, serialize, deserialize);
+criterion_main!(benches);
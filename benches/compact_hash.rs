@@ -0,0 +1,37 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rust2048::game::{Grid, Position};
+
+/// full 4x4 board of distinct tiles with `last` on the bottom right square
+fn full_board(last: u32) -> Grid {
+    let mut tiles: Vec<(Position, u32)> = (0..15u16)
+        .map(|i| (Position::new(i % 4, i / 4), 2 << i))
+        .collect();
+    tiles.push((Position::new(3, 3), last));
+    Grid::new_from_positions(6, 4, &tiles)
+}
+
+fn equality(c: &mut Criterion) {
+    let grid = full_board(2);
+    let same = full_board(2);
+    // the boards differ on the last square only, the map has to be walked to tell
+    let changed = full_board(4);
+    // the hashes are cached after the first check, like they are for a board
+    // checked against several others in the solver
+    grid.compact_hash();
+    same.compact_hash();
+    changed.compact_hash();
+
+    let mut group = c.benchmark_group("equality 4x4");
+    for (name, other) in [("equal", &same), ("different", &changed)] {
+        group.bench_function(format!("compact_hash {}", name), |b| {
+            b.iter(|| black_box(&grid).same_tiles(black_box(other)))
+        });
+        group.bench_function(format!("HashMap::eq {}", name), |b| {
+            b.iter(|| black_box(&grid).tiles == black_box(other).tiles)
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, equality);
+criterion_main!(benches);
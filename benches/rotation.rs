@@ -0,0 +1,42 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rust2048::game::{Grid, Position, Tile};
+
+/// full 8x8 board of distinct tiles
+fn full_board() -> Grid {
+    let tiles: Vec<(Position, u32)> = (0..64u16)
+        .map(|i| (Position::new(i % 8, i / 8), 2 << (i % 16)))
+        .collect();
+    Grid::new_from_positions(6, 8, &tiles)
+}
+
+/// the rotation the board used to do, collecting the tiles into a new map every time
+fn rotate_allocating(grid: &mut Grid) {
+    let s = grid.size - 1;
+    grid.tiles = grid
+        .tiles
+        .iter()
+        .map(|(pos, tile)| {
+            let tile = Tile {
+                spawn_position: Position::new(tile.spawn_position.y, s - tile.spawn_position.x),
+                ..*tile
+            };
+            (Position::new(pos.y, s - pos.x), tile)
+        })
+        .collect();
+}
+
+fn rotation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rotate 8x8");
+    let mut grid = full_board();
+    group.bench_function("in place", |b| {
+        b.iter(|| black_box(&mut grid).rotate_90_in_place(true))
+    });
+    let mut grid = full_board();
+    group.bench_function("allocating", |b| {
+        b.iter(|| rotate_allocating(black_box(&mut grid)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, rotation);
+criterion_main!(benches);
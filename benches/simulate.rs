@@ -0,0 +1,64 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rust2048::game::{Grid, Move, Position};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// system allocator counting the allocations, to compare the heap pressure of the two
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// full 4x4 board where every row merges to the left
+fn full_board() -> Grid {
+    let tiles: Vec<(Position, u32)> = (0..16u16)
+        .map(|i| (Position::new(i % 4, i / 4), 2 << (i / 2)))
+        .collect();
+    Grid::new_from_positions(6, 4, &tiles)
+}
+
+/// allocations made by `f` on average over `calls` calls
+fn allocations_per_call(calls: usize, mut f: impl FnMut()) -> f64 {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..calls {
+        f();
+    }
+    (ALLOCATIONS.load(Ordering::Relaxed) - before) as f64 / calls as f64
+}
+
+fn simulate(c: &mut Criterion) {
+    let grid = full_board();
+    let mut out = grid.clone();
+    grid.simulate_into(Move::Left, &mut out);
+
+    println!(
+        "allocations per call: simulate_into {}, simulate {}",
+        allocations_per_call(1000, || grid.simulate_into(Move::Left, &mut out)),
+        allocations_per_call(1000, || drop(grid.simulate(Move::Left))),
+    );
+
+    let mut group = c.benchmark_group("simulate full 4x4");
+    group.bench_function("simulate_into", |b| {
+        b.iter(|| black_box(&grid).simulate_into(Move::Left, black_box(&mut out)))
+    });
+    group.bench_function("simulate", |b| {
+        b.iter(|| black_box(&grid).simulate(Move::Left))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, simulate);
+criterion_main!(benches);
@@ -0,0 +1,200 @@
+use crate::game::{GameEvent, Grid};
+use crate::stats::Stats;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+/// Badge unlocked once its condition holds for a finished game, the condition gets
+/// the game that just ended and the stats with that game already recorded
+#[derive(Debug, Clone, Copy)]
+pub struct Achievement {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub unlocked: bool,
+    pub unlock_condition: fn(&Grid, &Stats) -> bool,
+}
+
+impl Achievement {
+    const fn new(
+        id: &'static str,
+        name: &'static str,
+        description: &'static str,
+        unlock_condition: fn(&Grid, &Stats) -> bool,
+    ) -> Self {
+        Self {
+            id,
+            name,
+            description,
+            unlocked: false,
+            unlock_condition,
+        }
+    }
+}
+
+fn won(game: &Grid) -> bool {
+    game.event_log
+        .iter()
+        .any(|event| matches!(event, GameEvent::Won(_)))
+}
+
+/// time from the first to the last event of the game
+fn duration(game: &Grid) -> Duration {
+    match (game.event_log.first(), game.event_log.last()) {
+        (Some(first), Some(last)) => last.instant().duration_since(first.instant()),
+        _ => Duration::ZERO,
+    }
+}
+
+fn max_tile(game: &Grid) -> u32 {
+    game.tiles.values().map(|tile| tile.n).max().unwrap_or(0)
+}
+
+/// every achievement there is, all of them locked
+pub fn all() -> Vec<Achievement> {
+    vec![
+        Achievement::new(
+            "first_merge",
+            "First Merge",
+            "Merge two tiles",
+            |game, _| game.event_summary().total_merges > 0,
+        ),
+        Achievement::new("centurion", "Centurion", "Score 100 points", |game, _| {
+            game.score >= 100
+        }),
+        Achievement::new(
+            "explorer",
+            "Explorer",
+            "Play a game on a 6x6 board or bigger",
+            |game, _| game.size >= 6,
+        ),
+        Achievement::new(
+            "speedster",
+            "Speedster",
+            "Win in under 2 minutes",
+            |game, _| won(game) && duration(game) < Duration::from_secs(120),
+        ),
+        Achievement::new(
+            "minimalist",
+            "Minimalist",
+            "Win in under 200 moves",
+            |game, _| won(game) && game.event_summary().total_moves < 200,
+        ),
+        Achievement::new("2048", "2048", "Make a 2048 tile", |game, _| {
+            max_tile(game) >= 2048
+        }),
+        Achievement::new("4096", "4096", "Make a 4096 tile", |game, _| {
+            max_tile(game) >= 4096
+        }),
+        Achievement::new("marathon", "Marathon", "Play 100 games", |_, stats| {
+            stats.games.len() >= 100
+        }),
+        Achievement::new(
+            "patient",
+            "Patient",
+            "Undo 10 times in one game",
+            |game, _| game.undos >= 10,
+        ),
+        Achievement::new(
+            "purist",
+            "Purist",
+            "Win without undoing a move",
+            |game, _| won(game) && game.undos == 0,
+        ),
+    ]
+}
+
+/// Achievements keeps which of the achievements were unlocked, saved between the
+/// sessions as a json list of the ids of the unlocked ones
+#[derive(Debug)]
+pub struct Achievements {
+    pub list: Vec<Achievement>,
+}
+
+impl Default for Achievements {
+    fn default() -> Self {
+        Self { list: all() }
+    }
+}
+
+impl Achievements {
+    /// achievements unlocked in the sessions before, all locked when there's no file,
+    /// ids of achievements that no longer exist are skipped
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut achievements = Self::default();
+        let json = match fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(achievements),
+            Err(err) => return Err(err),
+        };
+        let ids: Vec<String> = serde_json::from_str(&json)?;
+        for achievement in achievements.list.iter_mut() {
+            achievement.unlocked = ids.iter().any(|id| id == achievement.id);
+        }
+        Ok(achievements)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let ids: Vec<&str> = self
+            .list
+            .iter()
+            .filter(|a| a.unlocked)
+            .map(|a| a.id)
+            .collect();
+        fs::write(path, serde_json::to_string(&ids)?)
+    }
+
+    /// unlock the achievements the finished game earned and return the ones that
+    /// weren't unlocked before
+    pub fn check(&mut self, game: &Grid, stats: &Stats) -> Vec<Achievement> {
+        let mut unlocked = vec![];
+        for achievement in self.list.iter_mut() {
+            if !achievement.unlocked && (achievement.unlock_condition)(game, stats) {
+                achievement.unlocked = true;
+                unlocked.push(*achievement);
+            }
+        }
+        unlocked
+    }
+
+    pub fn unlocked_count(&self) -> usize {
+        self.list.iter().filter(|a| a.unlocked).count()
+    }
+
+    /// lock every achievement again
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unlocked_achievements_load_back() {
+        let path =
+            std::env::temp_dir().join(format!("rust2048_{}_achievements.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        assert_eq!(Achievements::load(&path).unwrap().unlocked_count(), 0);
+
+        let mut game = Grid::new(6, 6);
+        game.score = 150;
+        let mut achievements = Achievements::default();
+        let unlocked = achievements.check(&game, &Stats::default());
+        assert!(unlocked.iter().any(|a| a.id == "centurion"));
+        assert!(unlocked.iter().any(|a| a.id == "explorer"));
+        assert!(achievements.check(&game, &Stats::default()).is_empty());
+
+        achievements.save(&path).unwrap();
+        let loaded = Achievements::load(&path).unwrap();
+        assert_eq!(loaded.unlocked_count(), achievements.unlocked_count());
+        fs::write(&path, "[\"centurion\", \"gone\"]").unwrap();
+        assert_eq!(Achievements::load(&path).unwrap().unlocked_count(), 1);
+        fs::remove_file(&path).unwrap();
+    }
+}
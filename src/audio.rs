@@ -0,0 +1,120 @@
+use crate::game::GameEvent;
+#[cfg(feature = "audio")]
+use rodio::{source::SineWave, OutputStream, OutputStreamHandle, Sink, Source};
+#[cfg(feature = "audio")]
+use std::time::Duration;
+
+/// pitch of the tone of the lowest tile, a merge into a 2
+const BASE_PITCH: f32 = 220.0;
+/// semitones the tone goes up every time the value doubles
+const SEMITONES_PER_DOUBLING: f32 = 2.0;
+#[cfg(feature = "audio")]
+const TONE_MS: u64 = 80;
+
+/// frequency in hertz of the tone played for a merge into `n`, a whole tone higher for
+/// every doubling so 2048 still sounds different from 1024, values that aren't a power
+/// of two land between the tones of their neighbours
+pub fn pitch_for_value(n: u32) -> f32 {
+    let doublings = (n.max(2) as f32).log2() - 1.0;
+    BASE_PITCH * 2f32.powf(doublings * SEMITONES_PER_DOUBLING / 12.0)
+}
+
+/// MergeSounds plays a tone for every merge added to the game since the last call,
+/// without the audio feature it keeps track of the merges but stays silent
+#[derive(Default)]
+pub struct MergeSounds {
+    /// first event of the game being heard, when it changes a new game has started
+    first: Option<GameEvent>,
+    heard: usize,
+    /// opened with the first merge so nothing is opened while the sounds are off
+    #[cfg(feature = "audio")]
+    output: Option<(OutputStream, OutputStreamHandle)>,
+}
+
+impl MergeSounds {
+    /// play the merges added to `events` since the last call, one after the other
+    pub fn play_new(&mut self, events: &[GameEvent]) {
+        if events.first() != self.first.as_ref() {
+            self.first = events.first().copied();
+            self.heard = 0;
+        }
+        if events.len() <= self.heard {
+            return;
+        }
+
+        let values: Vec<u32> = events[self.heard..]
+            .iter()
+            .filter_map(|event| match event {
+                GameEvent::Merged { value, .. } => Some(*value),
+                _ => None,
+            })
+            .collect();
+        self.heard = events.len();
+        self.play(&values);
+    }
+
+    /// skip the merges made so far, e.g. while the sounds are turned off
+    pub fn mute(&mut self, events: &[GameEvent]) {
+        self.first = events.first().copied();
+        self.heard = events.len();
+    }
+
+    #[cfg(feature = "audio")]
+    fn play(&mut self, values: &[u32]) {
+        if values.is_empty() {
+            return;
+        }
+        if self.output.is_none() {
+            match OutputStream::try_default() {
+                Ok(output) => self.output = Some(output),
+                Err(err) => {
+                    debug_log!(warn, "no audio output: {}", err);
+                    return;
+                }
+            }
+        }
+        let handle = match &self.output {
+            Some((_, handle)) => handle,
+            None => return,
+        };
+        let sink = match Sink::try_new(handle) {
+            Ok(sink) => sink,
+            Err(err) => {
+                debug_log!(warn, "merge sounds failed: {}", err);
+                return;
+            }
+        };
+        for n in values {
+            let tone = SineWave::new(pitch_for_value(*n))
+                .take_duration(Duration::from_millis(TONE_MS))
+                .amplify(0.2);
+            sink.append(tone);
+        }
+        // the tones keep playing on the output stream after the sink is dropped
+        sink.detach();
+    }
+
+    #[cfg(not(feature = "audio"))]
+    fn play(&mut self, _values: &[u32]) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn higher_merges_sound_higher() {
+        assert_eq!(pitch_for_value(2), BASE_PITCH);
+        let powers: Vec<f32> = (1..=17).map(|power| pitch_for_value(1 << power)).collect();
+        assert!(
+            powers.windows(2).all(|pair| pair[0] < pair[1]),
+            "{:?}",
+            powers
+        );
+        // a doubling is a whole tone, six of them an octave
+        assert!((pitch_for_value(128) - 2.0 * BASE_PITCH).abs() < 0.01);
+        // the values of the other rules land between the powers of two
+        assert!(pitch_for_value(3) > pitch_for_value(2) && pitch_for_value(3) < pitch_for_value(4));
+        assert!(pitch_for_value(0) == pitch_for_value(2));
+    }
+}
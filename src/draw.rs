@@ -1,4 +1,61 @@
-use tui::{style::Color, widgets::canvas::Line};
+use tui::{
+    style::{Color, Style},
+    text::Span,
+    widgets::canvas::{Context, Line},
+};
+
+/// contrast WCAG asks for between normal text and its background
+const MIN_CONTRAST_RATIO: f64 = 4.5;
+
+/// Colors of the large text board drawn instead of the canvas, for low vision players
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HighContrast {
+    Off,
+    /// the usual tile colors, with the text switched to black or white where it's hard to read
+    TileColors,
+    WhiteOnBlack,
+    BlackOnWhite,
+}
+
+impl HighContrast {
+    pub fn next(&self) -> Self {
+        match self {
+            HighContrast::Off => HighContrast::TileColors,
+            HighContrast::TileColors => HighContrast::WhiteOnBlack,
+            HighContrast::WhiteOnBlack => HighContrast::BlackOnWhite,
+            HighContrast::BlackOnWhite => HighContrast::Off,
+        }
+    }
+
+    /// text and background color of the tile
+    pub fn colors_for_n(&self, n: u32) -> (Color, Color) {
+        match self {
+            HighContrast::Off | HighContrast::TileColors => get_accessible_colors_for_n(n),
+            HighContrast::WhiteOnBlack => (Color::White, Color::Black),
+            HighContrast::BlackOnWhite => (Color::Black, Color::White),
+        }
+    }
+}
+
+/// Feedback played on the board when the game is lost, before the info screen shows
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LossAnimation {
+    Off,
+    /// the board jumps from side to side
+    Shake,
+    /// a red frame blinks around the board
+    Flash,
+}
+
+impl LossAnimation {
+    pub fn next(&self) -> Self {
+        match self {
+            LossAnimation::Off => LossAnimation::Shake,
+            LossAnimation::Shake => LossAnimation::Flash,
+            LossAnimation::Flash => LossAnimation::Off,
+        }
+    }
+}
 
 pub enum Direction {
     Up(f64),
@@ -7,9 +64,23 @@ pub enum Direction {
     Right(f64),
 }
 
+/// what `draw_shape` draws its lines on, the canvas context when the tiles are rendered
+pub trait LineCanvas {
+    fn draw_line(&mut self, line: &Line);
+}
+
+impl LineCanvas for Context<'_> {
+    fn draw_line(&mut self, line: &Line) {
+        self.draw(line);
+    }
+}
+
+/// draws the strokes one after another starting at `x`, `y`, strokes thicker than 1
+/// are drawn as several lines half a braille dot apart
 pub fn draw_shape(
-    ctx: &mut tui::widgets::canvas::Context,
+    ctx: &mut impl LineCanvas,
     color: Color,
+    thickness: f64,
     directions: &[Direction],
     x: f64,
     y: f64,
@@ -22,17 +93,33 @@ pub fn draw_shape(
             Direction::Left(v) => (x - v, y),
             Direction::Right(v) => (x + v, y),
         };
-        ctx.draw(&Line {
-            x1: x,
-            y1: y,
-            x2,
-            y2,
-            color,
-        });
+        for offset in stroke_offsets(thickness) {
+            // shift the copies across the stroke, not along it
+            let (dx, dy) = match direction {
+                Direction::Up(_) | Direction::Down(_) => (offset, 0.0),
+                Direction::Left(_) | Direction::Right(_) => (0.0, offset),
+            };
+            ctx.draw_line(&Line {
+                x1: x + dx,
+                y1: y + dy,
+                x2: x2 + dx,
+                y2: y2 + dy,
+                color,
+            });
+        }
         (x, y) = (x2, y2);
     }
 }
 
+/// offsets of the lines making up a stroke, centered around 0,
+/// a thickness of 1 is a single line and 2 is three lines at -0.5, 0 and 0.5
+fn stroke_offsets(thickness: f64) -> Vec<f64> {
+    let lines = ((thickness.max(1.0) - 1.0) * 2.0).round() as usize + 1;
+    (0..lines)
+        .map(|i| (i as f64 - (lines - 1) as f64 / 2.0) * 0.5)
+        .collect()
+}
+
 pub fn get_bg_color_for_n(n: u32) -> Color {
     match n {
         2 => Color::Rgb(238, 228, 218),
@@ -46,10 +133,94 @@ pub fn get_bg_color_for_n(n: u32) -> Color {
         512 => Color::Rgb(237, 200, 80),
         1024 => Color::Rgb(237, 197, 63),
         2048 => Color::Rgb(237, 194, 46),
-        _ => Color::Gray,
+        4096 => Color::Rgb(181, 225, 90),
+        8192 => Color::Rgb(99, 190, 182),
+        16384 => Color::Rgb(94, 140, 230),
+        32768 => Color::Rgb(150, 110, 220),
+        65536 => Color::Rgb(220, 100, 170),
+        // anything else gets its hue rotated by the size of the tile so
+        // even the biggest tiles are never left without a color
+        n => hue_color((n.max(1) as f64).log2() * 37.0),
+    }
+}
+
+/// bright color with the given hue in degrees
+fn hue_color(hue: f64) -> Color {
+    let (saturation, value) = (0.55, 0.85);
+    let hue = hue.rem_euclid(360.0) / 60.0;
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = value - chroma;
+    let channel = |c: f64| ((c + m) * 255.0).round() as u8;
+    Color::Rgb(channel(r), channel(g), channel(b))
+}
+
+/// short text version of the tile for numbers without their own shape,
+/// anything from ten thousand up is shown in k and from a million in M
+pub fn abbreviate(n: u32) -> String {
+    match n {
+        n if n >= 1_000_000 => format!("{}M", n / 1_000_000),
+        n if n >= 10_000 => format!("{}k", n / 1000),
+        n => n.to_string(),
+    }
+}
+
+/// text and background color of the tile, the text becomes black or white
+/// when the usual color doesn't stand out enough from the background
+/// and the background turns black when neither of them does
+pub fn get_accessible_colors_for_n(n: u32) -> (Color, Color) {
+    let bg = get_bg_color_for_n(n);
+    let fg = get_color_for_n(n);
+    if contrast_ratio(fg, bg) >= MIN_CONTRAST_RATIO {
+        return (fg, bg);
+    }
+    let fg = if contrast_ratio(Color::Black, bg) > contrast_ratio(Color::White, bg) {
+        Color::Black
+    } else {
+        Color::White
+    };
+    if contrast_ratio(fg, bg) >= MIN_CONTRAST_RATIO {
+        (fg, bg)
+    } else {
+        (Color::White, Color::Black)
     }
 }
 
+/// WCAG contrast ratio of two colors, from 1 for the same colors to 21 for black and white
+pub fn contrast_ratio(a: Color, b: Color) -> f64 {
+    let (a, b) = (relative_luminance(a), relative_luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// WCAG relative luminance, the named colors are taken as their usual xterm values
+fn relative_luminance(color: Color) -> f64 {
+    let (r, g, b) = match color {
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Black => (0, 0, 0),
+        Color::White => (255, 255, 255),
+        Color::Gray => (229, 229, 229),
+        Color::DarkGray => (127, 127, 127),
+        _ => (127, 127, 127),
+    };
+    let linear = |c: u8| {
+        let c = c as f64 / 255.0;
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+}
+
 pub fn get_color_for_n(n: u32) -> Color {
     match n {
         n if n > 4 => Color::White,
@@ -57,12 +228,13 @@ pub fn get_color_for_n(n: u32) -> Color {
     }
 }
 
-pub fn draw_number(ctx: &mut tui::widgets::canvas::Context, n: u32) {
+pub fn draw_number(ctx: &mut tui::widgets::canvas::Context, n: u32, thickness: f64) {
     let color = get_color_for_n(n);
     match n {
         2 => draw_shape(
             ctx,
             color,
+            thickness,
             &[
                 Direction::Right(4.0),
                 Direction::Down(3.0),
@@ -76,6 +248,7 @@ pub fn draw_number(ctx: &mut tui::widgets::canvas::Context, n: u32) {
         4 => draw_shape(
             ctx,
             color,
+            thickness,
             &[
                 Direction::Down(3.0),
                 Direction::Right(4.0),
@@ -88,6 +261,7 @@ pub fn draw_number(ctx: &mut tui::widgets::canvas::Context, n: u32) {
         8 => draw_shape(
             ctx,
             color,
+            thickness,
             &[
                 Direction::Right(4.0),
                 Direction::Down(3.0),
@@ -101,10 +275,11 @@ pub fn draw_number(ctx: &mut tui::widgets::canvas::Context, n: u32) {
             8.0,
         ),
         16 => {
-            draw_shape(ctx, color, &[Direction::Down(6.0)], 3.0, 8.0);
+            draw_shape(ctx, color, thickness, &[Direction::Down(6.0)], 3.0, 8.0);
             draw_shape(
                 ctx,
                 color,
+                thickness,
                 &[
                     Direction::Left(3.0),
                     Direction::Down(6.0),
@@ -120,6 +295,7 @@ pub fn draw_number(ctx: &mut tui::widgets::canvas::Context, n: u32) {
             draw_shape(
                 ctx,
                 color,
+                thickness,
                 &[
                     Direction::Right(2.5),
                     Direction::Down(3.0),
@@ -134,6 +310,7 @@ pub fn draw_number(ctx: &mut tui::widgets::canvas::Context, n: u32) {
             draw_shape(
                 ctx,
                 color,
+                thickness,
                 &[
                     Direction::Right(2.5),
                     Direction::Down(3.0),
@@ -149,6 +326,7 @@ pub fn draw_number(ctx: &mut tui::widgets::canvas::Context, n: u32) {
             draw_shape(
                 ctx,
                 color,
+                thickness,
                 &[
                     Direction::Right(2.5),
                     Direction::Left(2.5),
@@ -164,6 +342,7 @@ pub fn draw_number(ctx: &mut tui::widgets::canvas::Context, n: u32) {
             draw_shape(
                 ctx,
                 color,
+                thickness,
                 &[
                     Direction::Down(3.0),
                     Direction::Right(2.5),
@@ -175,10 +354,11 @@ pub fn draw_number(ctx: &mut tui::widgets::canvas::Context, n: u32) {
             )
         }
         128 => {
-            draw_shape(ctx, color, &[Direction::Down(6.0)], 1.5, 8.0);
+            draw_shape(ctx, color, thickness, &[Direction::Down(6.0)], 1.5, 8.0);
             draw_shape(
                 ctx,
                 color,
+                thickness,
                 &[
                     Direction::Right(2.5),
                     Direction::Down(3.0),
@@ -192,6 +372,7 @@ pub fn draw_number(ctx: &mut tui::widgets::canvas::Context, n: u32) {
             draw_shape(
                 ctx,
                 color,
+                thickness,
                 &[
                     Direction::Right(2.5),
                     Direction::Down(3.0),
@@ -209,6 +390,7 @@ pub fn draw_number(ctx: &mut tui::widgets::canvas::Context, n: u32) {
             draw_shape(
                 ctx,
                 color,
+                thickness,
                 &[
                     Direction::Right(2.0),
                     Direction::Down(3.0),
@@ -222,6 +404,7 @@ pub fn draw_number(ctx: &mut tui::widgets::canvas::Context, n: u32) {
             draw_shape(
                 ctx,
                 color,
+                thickness,
                 &[
                     Direction::Right(2.0),
                     Direction::Left(2.0),
@@ -236,6 +419,7 @@ pub fn draw_number(ctx: &mut tui::widgets::canvas::Context, n: u32) {
             draw_shape(
                 ctx,
                 color,
+                thickness,
                 &[
                     Direction::Right(2.0),
                     Direction::Left(2.0),
@@ -252,6 +436,7 @@ pub fn draw_number(ctx: &mut tui::widgets::canvas::Context, n: u32) {
             draw_shape(
                 ctx,
                 color,
+                thickness,
                 &[
                     Direction::Right(2.0),
                     Direction::Left(2.0),
@@ -263,10 +448,11 @@ pub fn draw_number(ctx: &mut tui::widgets::canvas::Context, n: u32) {
                 1.0,
                 8.0,
             );
-            draw_shape(ctx, color, &[Direction::Down(6.0)], 5.5, 8.0);
+            draw_shape(ctx, color, thickness, &[Direction::Down(6.0)], 5.5, 8.0);
             draw_shape(
                 ctx,
                 color,
+                thickness,
                 &[
                     Direction::Right(2.0),
                     Direction::Down(3.0),
@@ -279,10 +465,11 @@ pub fn draw_number(ctx: &mut tui::widgets::canvas::Context, n: u32) {
             )
         }
         1024 => {
-            draw_shape(ctx, color, &[Direction::Down(6.0)], 1.0, 8.0);
+            draw_shape(ctx, color, thickness, &[Direction::Down(6.0)], 1.0, 8.0);
             draw_shape(
                 ctx,
                 color,
+                thickness,
                 &[
                     Direction::Down(6.0),
                     Direction::Right(1.8),
@@ -295,6 +482,7 @@ pub fn draw_number(ctx: &mut tui::widgets::canvas::Context, n: u32) {
             draw_shape(
                 ctx,
                 color,
+                thickness,
                 &[
                     Direction::Right(1.8),
                     Direction::Down(3.0),
@@ -308,6 +496,7 @@ pub fn draw_number(ctx: &mut tui::widgets::canvas::Context, n: u32) {
             draw_shape(
                 ctx,
                 color,
+                thickness,
                 &[
                     Direction::Down(3.0),
                     Direction::Right(1.8),
@@ -322,6 +511,7 @@ pub fn draw_number(ctx: &mut tui::widgets::canvas::Context, n: u32) {
             draw_shape(
                 ctx,
                 color,
+                thickness,
                 &[
                     Direction::Right(1.5),
                     Direction::Down(3.0),
@@ -335,6 +525,7 @@ pub fn draw_number(ctx: &mut tui::widgets::canvas::Context, n: u32) {
             draw_shape(
                 ctx,
                 color,
+                thickness,
                 &[
                     Direction::Down(6.0),
                     Direction::Right(1.5),
@@ -347,6 +538,7 @@ pub fn draw_number(ctx: &mut tui::widgets::canvas::Context, n: u32) {
             draw_shape(
                 ctx,
                 color,
+                thickness,
                 &[
                     Direction::Down(3.0),
                     Direction::Right(1.5),
@@ -359,6 +551,7 @@ pub fn draw_number(ctx: &mut tui::widgets::canvas::Context, n: u32) {
             draw_shape(
                 ctx,
                 color,
+                thickness,
                 &[
                     Direction::Right(1.5),
                     Direction::Down(3.0),
@@ -372,6 +565,53 @@ pub fn draw_number(ctx: &mut tui::widgets::canvas::Context, n: u32) {
                 8.0,
             )
         }
-        _ => {}
+        // the merge factor can make numbers that aren't a power of two
+        n => ctx.print(
+            2.0,
+            5.0,
+            Span::styled(abbreviate(n), Style::default().fg(color)),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_power_of_two_has_a_background() {
+        for power in 1..=20 {
+            let bg = get_bg_color_for_n(1 << power);
+            assert_ne!(bg, Color::Black, "2^{}", power);
+            assert_ne!(bg, Color::Rgb(0, 0, 0), "2^{}", power);
+        }
+    }
+
+    /// counts the lines instead of drawing them
+    #[derive(Default)]
+    struct LineCount(usize);
+
+    impl LineCanvas for LineCount {
+        fn draw_line(&mut self, _: &Line) {
+            self.0 += 1;
+        }
+    }
+
+    #[test]
+    fn double_strokes_draw_three_lines_for_every_one() {
+        let strokes = [
+            Direction::Right(1.5),
+            Direction::Down(3.0),
+            Direction::Left(1.5),
+            Direction::Up(3.0),
+        ];
+        let lines = |thickness| {
+            let mut count = LineCount::default();
+            draw_shape(&mut count, Color::White, thickness, &strokes, 7.8, 8.0);
+            count.0
+        };
+        assert_eq!(lines(1.0), strokes.len());
+        assert_eq!(lines(2.0), 3 * lines(1.0));
+        assert_eq!(lines(0.5), lines(1.0));
     }
 }
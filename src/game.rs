@@ -1,42 +1,328 @@
-use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
-    execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
-};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::draw::get_bg_color_for_n;
+use crate::save::{SavedEvent, SavedGame};
 use itertools::Itertools;
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
-use rand::Rng;
-use std::cell::RefCell;
-use std::collections::HashMap;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::{DefaultHasher, Entry};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::hash::{BuildHasherDefault, Hash, Hasher};
 use std::mem;
-use std::{
-    error::Error,
-    io,
-    time::{Duration, Instant},
-};
-use tui::{
-    backend::{Backend, CrosstermBackend},
-    layout::{Alignment, Constraint, Layout, Margin, Rect},
-    style::{Color, Modifier, Style},
-    symbols,
-    text::{Span, Spans},
-    widgets::{
-        canvas::{Canvas, Label, Line, Map, MapResolution, Rectangle},
-        Block, BorderType, Borders, Cell, LineGauge, Paragraph, Row, Table, Wrap,
-    },
-    Frame, Terminal,
-};
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+#[cfg(not(target_arch = "wasm32"))]
+use tui::style::Color;
 
 pub const MARGINX: u16 = 2;
 pub const MARGINY: u16 = 1;
 
+/// Rules the game is played by
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum GameMode {
+    Classic,
+    /// the game ends when the time runs out, merges made early score more
+    Blitz,
+    /// a move that doesn't change the board costs a life, the game is lost without lives
+    Hardcore,
+    /// a merge that makes the winning tile turns it back into the smallest tile,
+    /// the game can't be won and goes on for as long as there are moves
+    Endless,
+    /// new tiles only spawn on the edge the last move left behind, the right
+    /// column after a move to the left
+    Trailing,
+    /// the game is won by making a tile of exactly the target value, a tile
+    /// that skips past it doesn't count
+    Target,
+}
+
+impl GameMode {
+    pub fn next(&self) -> Self {
+        match self {
+            GameMode::Classic => GameMode::Blitz,
+            GameMode::Blitz => GameMode::Hardcore,
+            GameMode::Hardcore => GameMode::Endless,
+            GameMode::Endless => GameMode::Trailing,
+            GameMode::Trailing => GameMode::Target,
+            GameMode::Target => GameMode::Classic,
+        }
+    }
+}
+
+/// What two neighbouring tiles merge into, the rest of a move works the same for every rule
+pub trait MergeRule: Send + Sync {
+    fn can_merge(&self, a: u32, b: u32) -> bool;
+    fn merged_value(&self, a: u32, b: u32) -> u32;
+    /// values a new tile can have, each with how many times out of the total it spawns
+    fn spawn_weights(&self) -> Vec<(u32, u32)>;
+    /// true if a tile of value `n` can be on the board
+    fn is_tile_value(&self, n: u32) -> bool;
+    /// smallest value of at least `n` a tile can have, none if it doesn't fit a u32
+    fn next_tile_value(&self, n: u32) -> Option<u32>;
+}
+
+/// Equal tiles merge into one `factor` times bigger, the usual 2048
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StandardMerge {
+    pub factor: u32,
+}
+
+impl MergeRule for StandardMerge {
+    fn can_merge(&self, a: u32, b: u32) -> bool {
+        a == b
+    }
+
+    fn merged_value(&self, a: u32, _: u32) -> u32 {
+        a * self.factor
+    }
+
+    fn spawn_weights(&self) -> Vec<(u32, u32)> {
+        vec![(self.factor, 9), (self.factor * self.factor, 2)]
+    }
+
+    fn is_tile_value(&self, n: u32) -> bool {
+        self.next_tile_value(n) == Some(n)
+    }
+
+    fn next_tile_value(&self, n: u32) -> Option<u32> {
+        let mut value = self.factor;
+        while value < n {
+            value = value.checked_mul(self.factor)?;
+        }
+        Some(value)
+    }
+}
+
+/// Any two tiles adding up to a multiple of 3 merge into their sum,
+/// ones and twos spawn and only make a 3 together
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThreesRule;
+
+impl MergeRule for ThreesRule {
+    fn can_merge(&self, a: u32, b: u32) -> bool {
+        (a + b).is_multiple_of(3)
+    }
+
+    fn merged_value(&self, a: u32, b: u32) -> u32 {
+        a + b
+    }
+
+    fn spawn_weights(&self) -> Vec<(u32, u32)> {
+        vec![(1, 5), (2, 5), (3, 1)]
+    }
+
+    fn is_tile_value(&self, n: u32) -> bool {
+        n == 1 || n == 2 || (n > 0 && n.is_multiple_of(3))
+    }
+
+    fn next_tile_value(&self, n: u32) -> Option<u32> {
+        match n {
+            0 | 1 => Some(1),
+            2 => Some(2),
+            n => n.div_ceil(3).checked_mul(3),
+        }
+    }
+}
+
+/// Neighbouring numbers of the Fibonacci sequence merge into the next one, 1 and 1 make 2,
+/// 2 and 3 make 5 but 2 and 5 don't merge
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FibonacciMerge;
+
+impl MergeRule for FibonacciMerge {
+    fn can_merge(&self, a: u32, b: u32) -> bool {
+        let (low, high) = (std::cmp::min(a, b), std::cmp::max(a, b));
+        let (mut previous, mut current) = (1_u32, 1_u32);
+        while current < low {
+            (previous, current) = (current, previous.saturating_add(current));
+        }
+        // low is a fibonacci number and high is either the one before it or after it,
+        // 1 is the only number there twice so it's the only one merging with itself
+        current == low && (high == previous.saturating_add(current) || (high == low && low == 1))
+    }
+
+    fn merged_value(&self, a: u32, b: u32) -> u32 {
+        a + b
+    }
+
+    fn spawn_weights(&self) -> Vec<(u32, u32)> {
+        vec![(1, 9), (2, 2)]
+    }
+
+    fn is_tile_value(&self, n: u32) -> bool {
+        self.next_tile_value(n) == Some(n)
+    }
+
+    fn next_tile_value(&self, n: u32) -> Option<u32> {
+        let (mut previous, mut current) = (1_u32, 1_u32);
+        while current < n {
+            (previous, current) = (current, previous.checked_add(current)?);
+        }
+        Some(current)
+    }
+}
+
+/// Set of rules the tiles merge by, see `MergeRule`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum GameVariant {
+    Standard,
+    Threes,
+    Fibonacci,
+}
+
+impl GameVariant {
+    pub fn next(&self) -> Self {
+        match self {
+            GameVariant::Standard => GameVariant::Threes,
+            GameVariant::Threes => GameVariant::Fibonacci,
+            GameVariant::Fibonacci => GameVariant::Standard,
+        }
+    }
+
+    /// rule of the variant, only the standard one uses the merge factor
+    pub fn rule(&self, merge_factor: u32) -> Arc<dyn MergeRule> {
+        match self {
+            GameVariant::Standard => Arc::new(StandardMerge {
+                factor: merge_factor,
+            }),
+            GameVariant::Threes => Arc::new(ThreesRule),
+            GameVariant::Fibonacci => Arc::new(FibonacciMerge),
+        }
+    }
+}
+
+/// Which pair merges when a line has three equal tiles in a row,
+/// `[2, 2, 2]` moved left is `[4, 2]` towards the move and `[2, 4]` away from it
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum MergePreference {
+    TowardMove,
+    AwayFromMove,
+}
+
+impl MergePreference {
+    pub fn next(&self) -> Self {
+        match self {
+            MergePreference::TowardMove => MergePreference::AwayFromMove,
+            MergePreference::AwayFromMove => MergePreference::TowardMove,
+        }
+    }
+}
+
+/// Preset spaces between the tiles
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TileSpacing {
+    Compact,
+    Normal,
+    Spacious,
+}
+
+impl TileSpacing {
+    pub fn margins(&self) -> (u16, u16) {
+        match self {
+            TileSpacing::Compact => (1, 1),
+            TileSpacing::Normal => (MARGINX, MARGINY),
+            TileSpacing::Spacious => (4, 2),
+        }
+    }
+
+    pub fn from_margins(margins: (u16, u16)) -> Option<Self> {
+        [
+            TileSpacing::Compact,
+            TileSpacing::Normal,
+            TileSpacing::Spacious,
+        ]
+        .into_iter()
+        .find(|spacing| spacing.margins() == margins)
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            TileSpacing::Compact => TileSpacing::Normal,
+            TileSpacing::Normal => TileSpacing::Spacious,
+            TileSpacing::Spacious => TileSpacing::Compact,
+        }
+    }
+}
+
+/// Maximum number of snapshots kept in the move history. A snapshot only holds
+/// the position and value of every tile (8 bytes per tile), so even a full 16x16
+/// board, the biggest the settings offer, takes 2KB and the whole history stays around 1MB
+pub const HISTORY_LIMIT: usize = 500;
+
+/// Default tile width used when the grid is built from saved data, the real
+/// width is picked by `LayoutEngine::fit_board` once the grid gets rendered
+pub const DEFAULT_TILE_WIDTH: u16 = 6;
+
+/// Tile widths the board is drawn with, from the one it prefers to the smallest that
+/// still fits the numbers, the tiles are half as tall as they are wide
+pub const TILE_SIZES: [u16; 2] = [10, 6];
+
+/// Smallest supported board, smaller sizes are clamped to it
+pub const MIN_SIZE: u16 = 2;
+/// biggest board the settings offer, bigger saved boards aren't loaded
+pub const MAX_SIZE: u16 = 16;
+/// first byte of `Grid::serialize_binary` for squares holding log2 of the tile,
+/// buffers written before the format byte start with the board size and are rejected
+pub const BINARY_LOG2: u8 = 0;
+/// first byte of `Grid::serialize_binary` for squares holding the tile value as a varint
+pub const BINARY_VALUES: u8 = 1;
+/// lives a hardcore game starts with
+pub const HARDCORE_LIVES: u8 = 3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameError {
+    InvalidData(String),
+    /// there is no empty square left to spawn a tile on
+    BoardFull,
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameError::InvalidData(msg) => write!(f, "Invalid data: {}", msg),
+            GameError::BoardFull => write!(f, "Board is full"),
+        }
+    }
+}
+
+impl std::error::Error for GameError {}
+
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rows = self
+            .to_matrix()
+            .iter()
+            .map(|row| row.iter().join(" "))
+            .join("\n");
+        write!(f, "{}", rows)
+    }
+}
+
 pub enum Flip {
     Horizontal,
     Clock,
     CounterClock,
+    HalfTurn,
+}
+
+impl Flip {
+    /// flip that turns the board clockwise by 90 degrees `turns` times,
+    /// none for a multiple of four turns
+    pub fn clockwise(turns: u8) -> Option<Self> {
+        match turns % 4 {
+            1 => Some(Self::Clock),
+            2 => Some(Self::HalfTurn),
+            3 => Some(Self::CounterClock),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum Move {
     Up,
     Down,
@@ -45,8 +331,10 @@ pub enum Move {
 }
 
 /// Position on the Grid, the square a tile is currently in
-/// {x: 0, y: 0} would be top left square
-#[derive(Debug, Clone, Copy, PartialEq, Default, Eq, Hash)]
+/// {x: 0, y: 0} would be top left square, positions are ordered by x and then by y
+#[derive(
+    Debug, Clone, Copy, PartialEq, Default, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
 pub struct Position {
     pub x: u16,
     pub y: u16,
@@ -76,13 +364,28 @@ impl Coordinates {
 /// it has the coordinates which are the coordinates in the terminal
 #[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub struct Tile {
+    /// unique number of the tile, given to it when it's spawned and kept when it moves,
+    /// when two tiles merge the tile that was already in place keeps its id
+    pub id: u64,
     pub coordinates: Coordinates,
     pub n: u32,
+    /// position the tile was spawned at, it stays the same when the tile moves
+    pub spawn_position: Position,
+    /// tick of the grid at which the tile randomly spawned, None for tiles
+    /// that were put on the board some other way
+    pub spawned_at: Option<u64>,
+    /// generations of merges behind the tile, 0 for a tile that was never merged
+    /// and one more than the older of the two tiles when they merge
+    pub merge_count: u8,
 }
 
 impl Tile {
     pub fn new(coordinates: Coordinates, n: u32) -> Self {
-        Tile { coordinates, n }
+        Tile {
+            coordinates,
+            n,
+            ..Default::default()
+        }
     }
 
     pub fn mv(&mut self, coordinates: Coordinates) {
@@ -93,212 +396,1976 @@ impl Tile {
     }
 }
 
-/// Grid represents the base for the 2048, it holds the tiles with
-/// their positions on the Grid. It also holds the tiles that are
-/// currently in motion and their desired positions
-#[derive(Debug, PartialEq)]
-pub struct Grid {
-    pub tiles: HashMap<Position, Tile>,
-    pub moving_tiles: Vec<(Position, Position)>,
-    pub size: u16,
-    pub tile_width: u16,
-    pub tile_height: u16,
-    pub coordinates: Coordinates,
+/// Something that happened during the game and when it happened
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameEvent {
+    Moved(Move, Instant),
+    Merged {
+        from: Position,
+        to: Position,
+        value: u32,
+        at: Instant,
+    },
+    Spawned {
+        at: Position,
+        value: u32,
+        instant: Instant,
+    },
+    Won(Instant),
+    Lost(Instant),
 }
 
-impl Grid {
-    pub fn new(tile_size: u16, size: u16) -> Self {
-        let tile_width = tile_size;
-        let tile_height = tile_size / 2;
+impl GameEvent {
+    /// when the event happened
+    pub fn instant(&self) -> Instant {
+        match self {
+            GameEvent::Moved(_, instant)
+            | GameEvent::Merged { at: instant, .. }
+            | GameEvent::Spawned { instant, .. }
+            | GameEvent::Won(instant)
+            | GameEvent::Lost(instant) => *instant,
+        }
+    }
+}
 
-        let mut new_grid = Self {
-            tiles: HashMap::new(),
-            moving_tiles: vec![],
-            size,
-            tile_width,
-            tile_height,
-            coordinates: Coordinates::new(0, 0),
-        };
-        new_grid.insert_tile(Position::new(1, 1), 2);
-        new_grid
+/// Overview of the event log shown on the statistics screen
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct EventSummary {
+    pub total_moves: usize,
+    pub total_merges: usize,
+    pub biggest_merge: u32,
+    /// square where the most tiles were merged or spawned
+    pub most_active_cell: Option<Position>,
+}
+
+/// Ancestry of a tile, the children are the two tiles that merged into it
+/// and a tile that was never merged has no children
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MergeNode {
+    pub value: u32,
+    pub children: Vec<MergeNode>,
+}
+
+impl MergeNode {
+    pub fn leaf(value: u32) -> Self {
+        Self {
+            value,
+            children: vec![],
+        }
     }
 
-    pub fn mv(&mut self, new_coordinates: Coordinates) {
-        self.coordinates = new_coordinates
+    /// number of merges on the longest path down to a tile that was never merged
+    pub fn depth(&self) -> usize {
+        self.children
+            .iter()
+            .map(|child| child.depth() + 1)
+            .max()
+            .unwrap_or(0)
     }
+}
 
-    pub fn change_tile_size(&mut self, new_size: u16) {
-        if new_size == self.tile_width {
-            return;
+/// Tile sliding from one square to another while a move is animated
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileAnimation {
+    pub from: Position,
+    pub to: Position,
+}
+
+impl TileAnimation {
+    /// squares the tile travels
+    pub fn distance(&self) -> u16 {
+        self.from.x.abs_diff(self.to.x) + self.from.y.abs_diff(self.to.y)
+    }
+}
+
+/// What a move does to the board, see `Grid::check_with_merge_positions`
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MoveResult {
+    pub movements: Vec<TileAnimation>,
+    /// squares two tiles merge on with the value of the tile they make, top row first
+    pub merges: Vec<(Position, u32)>,
+    pub score_delta: u32,
+}
+
+/// Hash a grid remembers for its tiles and whether the tiles are packed into it,
+/// atomic because the solver shares grids between threads, a grid equals another one
+/// no matter what either remembers
+#[derive(Debug)]
+struct HashCache {
+    hash: AtomicU64,
+    packed: AtomicBool,
+}
+
+impl HashCache {
+    /// stands for no hash, a board hashing to it is simply hashed again every time
+    const EMPTY: u64 = u64::MAX;
+
+    fn new() -> Self {
+        Self {
+            hash: AtomicU64::new(Self::EMPTY),
+            packed: AtomicBool::new(false),
         }
-        self.tile_width = new_size;
-        self.tile_height = new_size / 2;
-        for (pos, tile) in self.tiles.clone().iter() {
-            self.tiles
-                .insert(*pos, Tile::new(self.get_coordinates_at(*pos), tile.n));
+    }
+
+    fn get(&self) -> Option<(u64, bool)> {
+        // the flag is stored before the hash is released
+        match self.hash.load(Ordering::Acquire) {
+            Self::EMPTY => None,
+            hash => Some((hash, self.packed.load(Ordering::Relaxed))),
         }
     }
 
-    pub fn change_size(&mut self, new_size: u16) {
-        if new_size == self.size {
-            return;
+    fn set(&self, hash: Option<(u64, bool)>) {
+        match hash {
+            Some((hash, packed)) => {
+                self.packed.store(packed, Ordering::Relaxed);
+                self.hash.store(hash, Ordering::Release);
+            }
+            None => self.hash.store(Self::EMPTY, Ordering::Relaxed),
         }
-        self.size = new_size;
     }
+}
 
-    pub fn width(&self) -> u16 {
-        2 + self.tile_width * self.size + MARGINX * self.size
+impl Clone for HashCache {
+    fn clone(&self) -> Self {
+        let cache = Self::new();
+        cache.set(self.get());
+        cache
     }
+}
 
-    pub fn height(&self) -> u16 {
-        self.width() / 2
+impl PartialEq for HashCache {
+    fn eq(&self, _: &Self) -> bool {
+        true
     }
+}
 
-    pub fn simulate_size(&self, tile_size: u16) -> (u16, u16) {
-        let width = 2 + tile_size * self.size + MARGINX * self.size;
-        (width + self.coordinates.x, width / 2 + self.coordinates.y)
-    }
-
-    /// try to adjust the size of the game to fit the terminal, if it's not possible return an error
-    pub fn adjust_size(&mut self, terminal_width: u16, terminal_height: u16) -> Result<(), String> {
-        let tile_sizes: [u16; 2] = [10, 6];
-        let mut final_size: u16 = 0;
-        for size in tile_sizes {
-            let (width, height) = self.simulate_size(size);
-            if width <= terminal_width && height <= terminal_height {
-                final_size = size;
-                break;
-            }
-        }
+/// Merge rule a grid plays by, shared rather than copied with the grid, two grids
+/// compare by their variant and merge factor instead
+#[derive(Clone)]
+struct Rule(Arc<dyn MergeRule>);
 
-        if final_size == 0 {
-            return Err("The size of your terminal is too small and can't fit the game! Try to make it larger.".to_string());
-        }
+impl fmt::Debug for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Rule")
+    }
+}
 
-        if self.tile_width != final_size {
-            self.change_tile_size(final_size);
-        }
+impl PartialEq for Rule {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+/// Source of the time the events of the game are stamped with, tests step their own
+/// clock instead of waiting for the real one
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+/// Clock of the system, the one a game uses unless it's given another with `set_clock`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
 
-        return Ok(());
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
     }
+}
 
-    pub fn check_if_game_can_continue(&mut self) -> Result<(), String> {
-        if self.tiles.iter().any(|(_, tile)| tile.n == 2048) {
-            return Err("Game Won".to_string());
+/// Clock a grid stamps its events with, shared like the rule with the grids made from it
+#[derive(Clone)]
+struct GameClock(Arc<dyn Clock>);
+
+impl fmt::Debug for GameClock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "GameClock")
+    }
+}
+
+impl PartialEq for GameClock {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+/// Snapshot of the board before a move was made, it only stores
+/// the values of the tiles because the coordinates can be recomputed
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Snapshot {
+    pub tiles: Vec<(Position, u32)>,
+    pub score: u32,
+    /// tiles spawned from the seed so far, an undone move spawns the same tile again
+    pub spawned: u64,
+}
+
+/// Hasher of the squares of the board, a position is two small numbers so it's hashed
+/// by spreading them over the bits with a multiplication instead of the default
+/// SipHash, which makes looking tiles up a lot cheaper
+#[derive(Default)]
+pub struct PositionHasher(u64);
+
+impl Hasher for PositionHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.write_u8(*byte);
         }
+    }
 
-        if self.tiles.len() == (self.size * self.size) as usize {
-            if !vec![Move::Up, Move::Down, Move::Left, Move::Right]
-                .iter()
-                .any(|mv| self.check(*mv) != self.moving_tiles)
-            {
-                return Err("Game Lost".to_string());
+    fn write_u8(&mut self, n: u8) {
+        self.0 = (self.0 << 8) | n as u64;
+    }
+
+    fn write_u16(&mut self, n: u16) {
+        self.0 = (self.0 << 16) | n as u64;
+    }
+
+    fn finish(&self) -> u64 {
+        let h = self.0.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        h ^ (h >> 32)
+    }
+}
+
+/// the tiles of a board by the square they are on
+pub type TileMap = HashMap<Position, Tile, BuildHasherDefault<PositionHasher>>;
+
+/// Grid represents the base for the 2048, it holds the tiles with
+/// their positions on the Grid. It also holds the tiles that are
+/// currently in motion and their desired positions
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grid {
+    pub tiles: TileMap,
+    pub moving_tiles: Vec<(Position, Position)>,
+    pub size: u16,
+    pub tile_width: u16,
+    pub tile_height: u16,
+    /// terminal coordinates of the top left corner of the board, moved with `mv`
+    /// so the coordinates cached for the squares are recomputed
+    coordinates: Coordinates,
+    /// first square shown when the board is bigger than the terminal
+    pub scroll: Position,
+    /// number of columns and rows of squares shown, the whole board when it fits
+    visible: (u16, u16),
+    /// horizontal space between the tiles
+    pub margin_x: u16,
+    /// vertical space between the tiles
+    pub margin_y: u16,
+    pub mode: GameMode,
+    /// sum of the values of all merged tiles
+    pub score: u32,
+    /// every merge adds its value times the multiplier to the score
+    pub score_multiplier: u32,
+    /// number of times `on_tick` was called
+    pub ticks: u64,
+    /// values of the tiles the merges of the last move made
+    pub last_merges: Vec<u32>,
+    /// lives left in a hardcore game and the tick the last one was lost at
+    pub lives: u8,
+    pub life_lost_at: Option<u64>,
+    /// number of winning tiles turned back into the smallest tile in an endless game
+    pub conversions: u32,
+    /// number of times the game went back to an earlier point of its history
+    pub undos: u32,
+    /// a new tile appears after every move, puzzles turn it off so the board only merges
+    pub spawn_enabled: bool,
+    /// tile that wins the game instead of the usual 2048, see `set_target`
+    pub target: Option<u32>,
+    /// last move that changed the board
+    pub last_move: Option<Move>,
+    /// number of moves that changed the board, undoing one doesn't take it back
+    pub moves: u64,
+    /// square the last random tile spawned at
+    pub last_spawn: Option<Position>,
+    next_tile_id: u64,
+    /// the tiles `spawn_random_tile` spawns come from it, the same seed and moves
+    /// replay the same game
+    pub seed: u64,
+    /// random number telling the games apart, even two games played from the same seed
+    pub game_id: u64,
+    /// number of tiles spawned from the seed so far
+    spawned: u64,
+    history: VecDeque<Snapshot>,
+    pub event_log: Vec<GameEvent>,
+    /// number of ticks a merging tile stays on top of the other one before they combine
+    pub merge_linger: u16,
+    /// how many times bigger a tile gets when two of them merge
+    pub merge_factor: u32,
+    /// the merge rule comes from it, see `set_variant`
+    pub variant: GameVariant,
+    rule: Rule,
+    /// the events of the game are stamped with its time, see `set_clock`
+    clock: GameClock,
+    pub merge_preference: MergePreference,
+    /// most merges a single move can make, the other equal tiles just slide
+    pub merge_limit: Option<u32>,
+    lingering: HashMap<Position, u16>,
+    /// ancestry of the tiles that were made by merges, by the id of the tile, `Tile`
+    /// is copied around too much to carry the tree itself
+    merge_trees: HashMap<u64, MergeNode>,
+    /// terminal coordinates of the squares, filled when tiles are inserted and
+    /// cleared whenever the grid is moved or the tiles change their size
+    coord_cache: HashMap<Position, Coordinates>,
+    /// `compact_hash` of the tiles, forgotten whenever the tiles change
+    compact_hash: HashCache,
+}
+
+impl Grid {
+    /// new grid with one tile on it, sizes smaller than `MIN_SIZE` are clamped to it
+    pub fn new(tile_size: u16, size: u16) -> Self {
+        let size = std::cmp::max(size, MIN_SIZE);
+        let mut new_grid = Self::empty(tile_size, size);
+        new_grid.seed = rand::random();
+        new_grid.game_id = rand::random();
+        new_grid.insert_tile(Position::new(1, 1), 2);
+        new_grid
+    }
+
+    /// grid placed at `coords` that starts like the standard game,
+    /// with `n_tiles` tiles spawned on random squares
+    pub fn new_with_initial_tiles(
+        size: u16,
+        tile_size: u16,
+        coords: Coordinates,
+        n_tiles: u8,
+        rng: &mut impl Rng,
+    ) -> Self {
+        Self::new_with_seed(size, tile_size, coords, n_tiles, rng.gen())
+    }
+
+    /// like `new_with_initial_tiles` but every tile of the game comes from the seed,
+    /// including the first ones
+    pub fn new_with_seed(
+        size: u16,
+        tile_size: u16,
+        coords: Coordinates,
+        n_tiles: u8,
+        seed: u64,
+    ) -> Self {
+        let mut new_grid = Self::empty(tile_size, std::cmp::max(size, MIN_SIZE));
+        new_grid.seed = seed;
+        new_grid.game_id = rand::random();
+        new_grid.mv(coords);
+        for _ in 0..n_tiles {
+            if new_grid.spawn_random_tile().is_err() {
+                break;
             }
         }
+        new_grid
+    }
 
-        return Ok(());
+    /// grid that starts like the standard game with the biggest tiles that fit the available
+    /// space, an error if not even the smallest ones do. The board is always square so
+    /// `cols` and `rows` have to be the same
+    pub fn new_auto_sized(
+        cols: u16,
+        rows: u16,
+        available_width: u16,
+        available_height: u16,
+    ) -> Result<Grid, GameError> {
+        if cols != rows {
+            return Err(GameError::InvalidData(format!(
+                "the board has to be square, got {}x{}",
+                cols, rows
+            )));
+        }
+        let mut grid = Self::new_with_initial_tiles(
+            cols,
+            TILE_SIZES[TILE_SIZES.len() - 1],
+            Coordinates::new(0, 0),
+            2,
+            &mut rand::thread_rng(),
+        );
+        let tile_size = TILE_SIZES.into_iter().find(|size| {
+            let (width, height) = grid.simulate_size(*size);
+            width <= available_width && height <= available_height
+        });
+        match tile_size {
+            Some(size) => {
+                grid.change_tile_size(size);
+                Ok(grid)
+            }
+            None => Err(GameError::InvalidData(format!(
+                "a {}x{} board doesn't fit in {}x{}",
+                cols, rows, available_width, available_height
+            ))),
+        }
     }
 
-    pub fn get_tile_mut(&mut self, pos: Position) -> Option<&mut Tile> {
-        if let Some(_) = self.tiles.get(&pos) {
-            Some(self.tiles.get_mut(&pos).unwrap())
-        } else {
-            None
+    /// grid with the given tiles already on it, positions outside of the board are skipped
+    pub fn new_from_positions(tile_size: u16, size: u16, tiles: &[(Position, u32)]) -> Self {
+        let mut new_grid = Self::empty(tile_size, std::cmp::max(size, MIN_SIZE));
+        new_grid.seed = rand::random();
+        for (pos, n) in tiles {
+            if pos.x < new_grid.size && pos.y < new_grid.size {
+                new_grid.insert_tile(*pos, *n);
+            }
         }
+        new_grid
     }
 
-    pub fn get_tile(&mut self, pos: Position) -> Option<Tile> {
-        if let Some(tile) = self.tiles.get(&pos) {
-            Some(*tile)
-        } else {
-            None
+    fn empty(tile_size: u16, size: u16) -> Self {
+        Self {
+            tiles: TileMap::default(),
+            moving_tiles: vec![],
+            size,
+            tile_width: tile_size,
+            tile_height: tile_size / 2,
+            coordinates: Coordinates::new(0, 0),
+            scroll: Position::new(0, 0),
+            visible: (size, size),
+            margin_x: MARGINX,
+            margin_y: MARGINY,
+            mode: GameMode::Classic,
+            score: 0,
+            score_multiplier: 1,
+            ticks: 0,
+            last_merges: vec![],
+            lives: HARDCORE_LIVES,
+            life_lost_at: None,
+            conversions: 0,
+            undos: 0,
+            spawn_enabled: true,
+            target: None,
+            last_move: None,
+            moves: 0,
+            last_spawn: None,
+            next_tile_id: 0,
+            seed: 0,
+            game_id: 0,
+            spawned: 0,
+            history: VecDeque::new(),
+            event_log: vec![],
+            merge_linger: 1,
+            merge_factor: 2,
+            variant: GameVariant::Standard,
+            rule: Rule(GameVariant::Standard.rule(2)),
+            clock: GameClock(Arc::new(SystemClock)),
+            merge_preference: MergePreference::TowardMove,
+            merge_limit: None,
+            lingering: HashMap::new(),
+            merge_trees: HashMap::new(),
+            coord_cache: HashMap::new(),
+            compact_hash: HashCache::new(),
         }
     }
 
-    pub fn get_coordinates_at(&self, pos: Position) -> Coordinates {
-        Coordinates {
-            x: self.coordinates.x + MARGINX + pos.x * MARGINX + pos.x * self.tile_width,
-            y: self.coordinates.y + MARGINY + pos.y * MARGINY + pos.y * self.tile_height,
+    /// grid without any tiles but with the same size and placement on the screen
+    fn empty_like(&self) -> Self {
+        Self {
+            coordinates: self.coordinates,
+            scroll: self.scroll,
+            visible: self.visible,
+            margin_x: self.margin_x,
+            margin_y: self.margin_y,
+            mode: self.mode,
+            merge_linger: self.merge_linger,
+            merge_factor: self.merge_factor,
+            variant: self.variant,
+            rule: self.rule.clone(),
+            clock: self.clock.clone(),
+            merge_preference: self.merge_preference,
+            merge_limit: self.merge_limit,
+            spawn_enabled: self.spawn_enabled,
+            target: self.target,
+            seed: self.seed,
+            game_id: self.game_id,
+            spawned: self.spawned,
+            ..Self::empty(self.tile_width, self.size)
         }
     }
 
-    pub fn insert_tile(&mut self, pos: Position, n: u32) {
+    /// all the tiles ordered by their position, unlike iterating over `tiles`
+    /// the order is always the same
+    pub fn tiles_sorted(&self) -> Vec<(Position, Tile)> {
         self.tiles
-            .insert(pos, Tile::new(self.get_coordinates_at(pos), n));
+            .iter()
+            .map(|(pos, tile)| (*pos, *tile))
+            .sorted_by_key(|(pos, _)| *pos)
+            .collect()
     }
 
-    pub fn remove_tile(&mut self, pos: Position) {
-        self.tiles.remove(&pos);
+    /// estimate of the score from the tiles on the board, used when the score
+    /// is unknown: the values the rule spawns most often, 2s in the usual game,
+    /// add nothing and every other tile had to be made by a merge so it adds its value
+    pub fn compute_score_for_state(&self) -> u32 {
+        let weights = self.rule.0.spawn_weights();
+        let most = weights.iter().map(|(_, weight)| *weight).max().unwrap_or(0);
+        self.tiles
+            .values()
+            .filter(|tile| !weights.contains(&(tile.n, most)))
+            .map(|tile| tile.n)
+            .sum()
     }
 
-    pub fn remove_moving_tile(&mut self, pos: Position) {
-        let index = self
-            .moving_tiles
-            .iter()
-            .position(|(p, _)| p == &pos)
-            .unwrap();
-        self.moving_tiles.remove(index);
+    /// values of the tiles row by row, 0 for an empty square
+    pub fn to_matrix(&self) -> Vec<Vec<u32>> {
+        (0..self.size)
+            .map(|y| {
+                (0..self.size)
+                    .map(|x| match self.tiles.get(&Position::new(x, y)) {
+                        Some(tile) => tile.n,
+                        None => 0,
+                    })
+                    .collect()
+            })
+            .collect()
     }
 
-    pub fn spawn_random_tile(&mut self) {
-        let mut available = vec![];
-        for x in 0..self.size {
-            for y in 0..self.size {
-                if !self.tiles.contains_key(&Position::new(x, y)) {
-                    available.push((x, y));
-                }
+    /// the board as a table of ascii characters with the values right aligned in
+    /// columns as wide as the biggest value, for reading boards outside of the tui
+    pub fn to_ascii_string(&self) -> String {
+        let matrix = self.to_matrix();
+        let width = matrix
+            .iter()
+            .flatten()
+            .map(|n| n.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let border = format!(
+            "+{}\n",
+            format!("{}+", "-".repeat(width + 2)).repeat(matrix.len())
+        );
+        let mut ascii = border.clone();
+        for row in matrix.iter() {
+            ascii.push('|');
+            for n in row.iter() {
+                let value = match n {
+                    0 => String::new(),
+                    n => n.to_string(),
+                };
+                ascii.push_str(&format!(" {:>width$} |", value, width = width));
             }
+            ascii.push('\n');
+            ascii.push_str(&border);
         }
-        if available.len() < 1 {
-            return;
+        ascii
+    }
+
+    /// print `to_ascii_string` to stdout
+    pub fn print_ascii(&self) {
+        print!("{}", self.to_ascii_string());
+    }
+
+    /// the tiles as a single number to tell boards apart quickly, a board of up to 4x4
+    /// squares with the first 15 values of its rule is packed row by row with 4 bits per
+    /// square holding the place of the value in the rule, 0 for an empty square, so two
+    /// boards of the same rule hash equal exactly when their tiles are.
+    /// Other boards get a regular hash which two boards could share
+    pub fn compact_hash(&self) -> u64 {
+        self.cached_hash().0
+    }
+
+    /// `compact_hash` and whether it's the packed tiles, remembered until the tiles change
+    fn cached_hash(&self) -> (u64, bool) {
+        if let Some(cached) = self.compact_hash.get() {
+            return cached;
         }
+        let cached = match self.packed_tiles() {
+            Some(packed) => (packed, true),
+            None => {
+                let mut hasher = DefaultHasher::new();
+                self.to_matrix().hash(&mut hasher);
+                (hasher.finish(), false)
+            }
+        };
+        self.compact_hash.set(Some(cached));
+        cached
+    }
 
-        if let Some((x, y)) = available.choose(&mut rand::thread_rng()) {
-            let mut rng = rand::thread_rng();
-            let new_n = match rng.gen_range(0..=10) {
-                x if x < 9 => 2,
-                _ => 4,
-            };
-            self.insert_tile(Position::new(*x, *y), new_n);
+    fn packed_tiles(&self) -> Option<u64> {
+        if self.size > 4 {
+            return None;
         }
+        let mut packed = 0;
+        for (pos, tile) in self.tiles.iter() {
+            let rank = self.tile_rank(tile.n)?;
+            let index = pos.y * self.size + pos.x;
+            packed |= (rank as u64) << (index * 4);
+        }
+        Some(packed)
     }
 
-    pub fn flip(&mut self, flip: Flip) {
-        let s = self.size - 1;
-        self.moving_tiles = self
-            .moving_tiles
+    /// place of `n` among the values of the rule counting from 1 for the smallest,
+    /// none past the 15th value or for a value the rule can't make
+    fn tile_rank(&self, n: u32) -> Option<u32> {
+        let rule = self.rule.0.as_ref();
+        let mut value = rule.next_tile_value(1)?;
+        for rank in 1..16 {
+            if value >= n {
+                return (value == n).then_some(rank);
+            }
+            value = rule.next_tile_value(value.checked_add(1)?)?;
+        }
+        None
+    }
+
+    /// true if both boards have the same tiles on the same squares
+    pub fn same_tiles(&self, other: &Grid) -> bool {
+        // the hashes of boards of different rules pack different values
+        if self.variant == other.variant && self.merge_factor == other.merge_factor {
+            let (hash, packed) = self.cached_hash();
+            let (other_hash, other_packed) = other.cached_hash();
+            if hash != other_hash {
+                return false;
+            }
+            // only the packed hashes are equal for equal tiles alone
+            if packed && other_packed {
+                return true;
+            }
+        }
+        self.size == other.size
+            && self.tiles.len() == other.tiles.len()
+            && self
+                .tiles
+                .iter()
+                .all(|(pos, tile)| other.tiles.get(pos).map(|other| other.n) == Some(tile.n))
+    }
+
+    /// values of the squares row after row in a single flat vector, 0 for an empty
+    /// square, a compact form for frontends that can't use the terminal like a web page
+    pub fn export_board(&self) -> Vec<u32> {
+        self.to_matrix().into_iter().flatten().collect()
+    }
+
+    /// replace the tiles with the ones of a board from `export_board`, the board
+    /// has to be of the same size as the grid and hold only values its rule can make
+    pub fn import_board(&mut self, board: &[u32]) -> Result<(), GameError> {
+        let size = self.size as usize;
+        if board.len() != size * size {
+            return Err(GameError::InvalidData(format!(
+                "expected {} squares for a {}x{} board, got {}",
+                size * size,
+                size,
+                size,
+                board.len()
+            )));
+        }
+        if let Some(n) = board
             .iter()
-            .map(|(pos, new_pos)| match flip {
-                Flip::Horizontal => (
-                    Position::new(s - pos.x, pos.y),
-                    Position::new(s - new_pos.x, new_pos.y),
-                ),
-                Flip::CounterClock => (
-                    Position::new(s - pos.y, pos.x),
-                    Position::new(s - new_pos.y, new_pos.x),
-                ),
-                Flip::Clock => (
-                    Position::new(pos.y, s - pos.x),
-                    Position::new(new_pos.y, s - new_pos.x),
-                ),
+            .find(|n| **n != 0 && !self.rule.0.is_tile_value(**n))
+        {
+            return Err(GameError::InvalidData(format!(
+                "{} isn't a tile of the {:?} variant",
+                n, self.variant
+            )));
+        }
+        self.clear();
+        for (i, n) in board.iter().enumerate() {
+            if *n != 0 {
+                let pos = Position::new((i % size) as u16, (i / size) as u16);
+                self.insert_tile(pos, *n);
+            }
+        }
+        Ok(())
+    }
+
+    /// the board, the rules it's played by and the event log, to be picked up again
+    /// with `from_saved`
+    pub fn to_saved(&self) -> SavedGame {
+        let start = self.event_log.first().map(|event| event.instant());
+        let ms = |at: &Instant| match start {
+            Some(start) => at.saturating_duration_since(start).as_millis() as u64,
+            None => 0,
+        };
+        let events = self
+            .event_log
+            .iter()
+            .map(|event| match event {
+                GameEvent::Moved(direction, at) => SavedEvent::Moved {
+                    ms: ms(at),
+                    direction: *direction,
+                },
+                GameEvent::Merged {
+                    from,
+                    to,
+                    value,
+                    at,
+                } => SavedEvent::Merged {
+                    ms: ms(at),
+                    from: *from,
+                    to: *to,
+                    value: *value,
+                },
+                GameEvent::Spawned { at, value, instant } => SavedEvent::Spawned {
+                    ms: ms(instant),
+                    at: *at,
+                    value: *value,
+                },
+                GameEvent::Won(at) => SavedEvent::Won { ms: ms(at) },
+                GameEvent::Lost(at) => SavedEvent::Lost { ms: ms(at) },
             })
             .collect();
-        self.tiles = self
-            .tiles
+        SavedGame {
+            board: self.to_string(),
+            events,
+            score: Some(self.score),
+            moves: self.moves,
+            mode: self.mode,
+            variant: self.variant,
+            merge_factor: self.merge_factor,
+            target: self.target,
+            seed: self.seed,
+            spawned: self.spawned,
+            game_id: self.game_id,
+            merge_preference: self.merge_preference,
+            merge_limit: self.merge_limit,
+            merge_linger: self.merge_linger,
+            margin_x: self.margin_x,
+            margin_y: self.margin_y,
+        }
+    }
+
+    /// grid of a saved game at the top left corner of the terminal, the history of the
+    /// game isn't saved so it starts empty while the event log goes on as if the last
+    /// event just happened. A board of an unsupported size or with tiles its rule can't
+    /// make is an error
+    pub fn from_saved(saved: &SavedGame, tile_size: u16) -> Result<Grid, GameError> {
+        let board = Grid::from_string(&saved.board)?;
+        if board.size > MAX_SIZE {
+            return Err(GameError::InvalidData(format!(
+                "unsupported board size {}x{}",
+                board.size, board.size
+            )));
+        }
+        let mut grid = Self::empty(tile_size, board.size);
+        grid.mode = saved.mode;
+        grid.merge_factor = std::cmp::max(saved.merge_factor, 2);
+        grid.set_variant(saved.variant);
+        if let Some(target) = saved.target {
+            grid.set_target(target)?;
+        }
+        grid.import_board(&board.export_board())?;
+        grid.score = match saved.score {
+            Some(score) => score,
+            None => grid.compute_score_for_state(),
+        };
+        grid.moves = saved.moves;
+        grid.seed = saved.seed;
+        grid.game_id = saved.game_id;
+        grid.spawned = saved.spawned;
+        grid.merge_preference = saved.merge_preference;
+        grid.merge_limit = saved.merge_limit.map(|limit| std::cmp::max(limit, 1));
+        grid.merge_linger = saved.merge_linger;
+        grid.change_margins(saved.margin_x, saved.margin_y);
+
+        let now = grid.clock.0.now();
+        let last = saved.events.last().map(|event| event.ms()).unwrap_or(0);
+        let at = |ms: u64| {
+            now.checked_sub(Duration::from_millis(last.saturating_sub(ms)))
+                .unwrap_or(now)
+        };
+        grid.event_log = saved
+            .events
             .iter()
-            .map(|(pos, tile)| match flip {
-                Flip::Horizontal => (Position::new(s - pos.x, pos.y), *tile),
-                Flip::CounterClock => (Position::new(s - pos.y, pos.x), *tile),
-                Flip::Clock => (Position::new(pos.y, s - pos.x), *tile),
+            .map(|event| match *event {
+                SavedEvent::Moved { ms, direction } => GameEvent::Moved(direction, at(ms)),
+                SavedEvent::Merged {
+                    ms,
+                    from,
+                    to,
+                    value,
+                } => GameEvent::Merged {
+                    from,
+                    to,
+                    value,
+                    at: at(ms),
+                },
+                SavedEvent::Spawned { ms, at: pos, value } => GameEvent::Spawned {
+                    at: pos,
+                    value,
+                    instant: at(ms),
+                },
+                SavedEvent::Won { ms } => GameEvent::Won(at(ms)),
+                SavedEvent::Lost { ms } => GameEvent::Lost(at(ms)),
             })
             .collect();
+        Ok(grid)
+    }
+
+    /// highest sum of the tiles in a single row, a high sum means the big
+    /// tiles are gathered together where they can be merged
+    pub fn best_row_sum(&self) -> u32 {
+        (0..self.size)
+            .map(|y| self.line_sum(|i| Position::new(i, y)))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// like `best_row_sum` but for the columns
+    pub fn best_column_sum(&self) -> u32 {
+        (0..self.size)
+            .map(|x| self.line_sum(|i| Position::new(x, i)))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// sum of the tiles at the positions `at` gives for every index of a line
+    fn line_sum(&self, at: impl Fn(u16) -> Position) -> u32 {
+        (0..self.size)
+            .filter_map(|i| self.tiles.get(&at(i)))
+            .map(|tile| tile.n)
+            .sum()
+    }
+
+    /// highest number on the board, 0 for an empty board
+    pub fn max_tile_value(&self) -> u32 {
+        self.tiles.values().map(|tile| tile.n).max().unwrap_or(0)
+    }
+
+    /// how many tiles of each value are on the board, lowest value first
+    pub fn tile_value_distribution(&self) -> BTreeMap<u32, u32> {
+        let mut distribution = BTreeMap::new();
+        for tile in self.tiles.values() {
+            *distribution.entry(tile.n).or_insert(0) += 1;
+        }
+        distribution
+    }
+
+    /// value most of the tiles have, on a tie the lowest of them, none on an empty board
+    pub fn dominant_value(&self) -> Option<u32> {
+        let mut dominant: Option<(u32, u32)> = None;
+        for (n, count) in self.tile_value_distribution() {
+            if dominant.is_none_or(|(_, most)| count > most) {
+                dominant = Some((n, count));
+            }
+        }
+        dominant.map(|(n, _)| n)
+    }
+
+    /// values of the tiles row by row divided by the highest value,
+    /// so every square is between 0.0 and 1.0 and empty squares are 0.0
+    pub fn density_map(&self) -> Vec<Vec<f64>> {
+        let max = self.max_tile_value();
+        self.to_matrix()
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|n| match max {
+                        0 => 0.0,
+                        max => *n as f64 / max as f64,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// like `density_map` but with log2 of the values, which spreads the small
+    /// tiles more evenly between 0.0 and 1.0
+    pub fn log2_map(&self) -> Vec<Vec<f64>> {
+        let max = (self.max_tile_value() as f64).log2();
+        self.to_matrix()
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|n| match n {
+                        0 => 0.0,
+                        n if max > 0.0 => (*n as f64).log2() / max,
+                        _ => 0.0,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// compact binary form of the board: one byte with the format, one byte with the
+    /// number of columns, one byte with the number of rows and then every square in
+    /// row-major order. Boards of powers of two up to 65536 use `BINARY_LOG2`, one byte
+    /// per square holding log2 of the tile (0 for an empty square, 1 for 2, up to 16 for
+    /// 65536), any other board uses `BINARY_VALUES`, the value of the square (0 for an
+    /// empty one) as a LEB128 varint, 7 bits a byte with the high bit set on all but the last
+    pub fn serialize_binary(&self) -> Vec<u8> {
+        let cells = self.export_board();
+        let log2 = cells
+            .iter()
+            .all(|n| *n == 0 || (n.is_power_of_two() && (1..=16).contains(&n.trailing_zeros())));
+        let mut data = Vec::with_capacity(3 + cells.len());
+        data.push(if log2 { BINARY_LOG2 } else { BINARY_VALUES });
+        data.push(self.size as u8);
+        data.push(self.size as u8);
+        for n in cells {
+            if log2 {
+                data.push(if n == 0 { 0 } else { n.trailing_zeros() as u8 });
+                continue;
+            }
+            let mut rest = n;
+            while rest >= 0x80 {
+                data.push((rest & 0x7f) as u8 | 0x80);
+                rest >>= 7;
+            }
+            data.push(rest as u8);
+        }
+        data
+    }
+
+    /// inverse of `serialize_binary`
+    pub fn deserialize_binary(data: &[u8]) -> Result<Grid, GameError> {
+        if data.len() < 3 {
+            return Err(GameError::InvalidData("missing board size".to_string()));
+        }
+        let (cols, rows) = (data[1] as u16, data[2] as u16);
+        if cols != rows || !(MIN_SIZE..=MAX_SIZE).contains(&cols) {
+            return Err(GameError::InvalidData(format!(
+                "unsupported board size {}x{}",
+                cols, rows
+            )));
+        }
+        let cells = match data[0] {
+            BINARY_LOG2 => Self::log2_cells(&data[3..])?,
+            BINARY_VALUES => Self::varint_cells(&data[3..])?,
+            format => {
+                return Err(GameError::InvalidData(format!(
+                    "unknown binary format {}",
+                    format
+                )))
+            }
+        };
+        if cells.len() != (cols * rows) as usize {
+            return Err(GameError::InvalidData(format!(
+                "expected {} squares, got {}",
+                cols * rows,
+                cells.len()
+            )));
+        }
+
+        // any value is taken as is since the format doesn't store the rule
+        let mut grid = Grid::empty(DEFAULT_TILE_WIDTH, cols);
+        for (i, n) in cells.iter().enumerate() {
+            if *n != 0 {
+                grid.insert_tile(Position::new(i as u16 % cols, i as u16 / cols), *n);
+            }
+        }
+        // the binary format doesn't store the score
+        grid.score = grid.compute_score_for_state();
+        Ok(grid)
+    }
+
+    /// squares of the `BINARY_LOG2` format
+    fn log2_cells(data: &[u8]) -> Result<Vec<u32>, GameError> {
+        data.iter()
+            .map(|exponent| match exponent {
+                0 => Ok(0),
+                1..=16 => Ok(1 << exponent),
+                _ => Err(GameError::InvalidData(format!(
+                    "tile 2^{} is too large",
+                    exponent
+                ))),
+            })
+            .collect()
+    }
+
+    /// squares of the `BINARY_VALUES` format
+    fn varint_cells(data: &[u8]) -> Result<Vec<u32>, GameError> {
+        let mut cells = Vec::with_capacity(data.len());
+        let (mut value, mut shift) = (0_u64, 0);
+        for byte in data.iter() {
+            value |= ((byte & 0x7f) as u64) << shift;
+            if value > u32::MAX as u64 || shift > 28 {
+                return Err(GameError::InvalidData(format!(
+                    "tile of square {} is too large",
+                    cells.len() + 1
+                )));
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                cells.push(value as u32);
+                (value, shift) = (0, 0);
+            }
+        }
+        if shift != 0 {
+            return Err(GameError::InvalidData(format!(
+                "square {} is cut short",
+                cells.len() + 1
+            )));
+        }
+        Ok(cells)
+    }
+
+    /// parses a board written as rows of space separated values, `0` being an empty square,
+    /// inverse of the `Display` impl
+    pub fn from_string(s: &str) -> Result<Grid, GameError> {
+        let rows = s
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                line.split_whitespace()
+                    .map(|token| {
+                        token.parse::<u32>().map_err(|_| {
+                            GameError::InvalidData(format!("invalid tile value '{}'", token))
+                        })
+                    })
+                    .collect::<Result<Vec<u32>, GameError>>()
+            })
+            .collect::<Result<Vec<Vec<u32>>, GameError>>()?;
+
+        let size = rows.len();
+        if size < MIN_SIZE as usize {
+            return Err(GameError::InvalidData(format!(
+                "unsupported board size {}x{}",
+                size, size
+            )));
+        }
+        let mut grid = Grid::empty(DEFAULT_TILE_WIDTH, size as u16);
+        for (y, row) in rows.iter().enumerate() {
+            if row.len() != size {
+                return Err(GameError::InvalidData(format!(
+                    "row {} has {} squares, expected {}",
+                    y + 1,
+                    row.len(),
+                    size
+                )));
+            }
+            for (x, n) in row.iter().enumerate() {
+                if *n != 0 {
+                    grid.insert_tile(Position::new(x as u16, y as u16), *n);
+                }
+            }
+        }
+        grid.score = grid.compute_score_for_state();
+        Ok(grid)
+    }
+
+    /// deterministic puzzle: the board from `layout` (see `from_string`) that never
+    /// spawns new tiles and is won by making a `target` tile
+    pub fn puzzle(layout: &str, target: u32) -> Result<Grid, GameError> {
+        let mut grid = Grid::from_string(layout)?;
+        grid.spawn_enabled = false;
+        grid.set_target(target)?;
+        Ok(grid)
+    }
+
+    pub fn event_summary(&self) -> EventSummary {
+        let mut summary = EventSummary::default();
+        let mut activity: HashMap<Position, usize> = HashMap::new();
+        for event in self.event_log.iter() {
+            match event {
+                GameEvent::Moved(..) => summary.total_moves += 1,
+                GameEvent::Merged { to, value, .. } => {
+                    summary.total_merges += 1;
+                    summary.biggest_merge = std::cmp::max(summary.biggest_merge, *value);
+                    *activity.entry(*to).or_insert(0) += 1;
+                }
+                GameEvent::Spawned { at, .. } => *activity.entry(*at).or_insert(0) += 1,
+                _ => (),
+            }
+        }
+        summary.most_active_cell = activity
+            .iter()
+            .sorted_by_key(|(p, _)| **p)
+            .rev()
+            .max_by_key(|(_, count)| **count)
+            .map(|(p, _)| *p);
+        summary
+    }
+
+    /// snapshots of the board before each move, oldest first
+    pub fn history(&self) -> &VecDeque<Snapshot> {
+        &self.history
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            tiles: self
+                .tiles_sorted()
+                .iter()
+                .map(|(pos, tile)| (*pos, tile.n))
+                .collect(),
+            score: self.score,
+            spawned: self.spawned,
+        }
+    }
+
+    fn record_snapshot(&mut self) {
+        if self.history.len() == HISTORY_LIMIT {
+            self.history.pop_front();
+        }
+        self.history.push_back(self.snapshot());
+    }
+
+    /// board as it was at the given point of the history, index equal to
+    /// the length of the history is the current board
+    pub fn board_at(&self, index: usize) -> Grid {
+        let mut board = self.empty_like();
+        board.ticks = self.ticks;
+        match self.history.get(index) {
+            Some(snapshot) => {
+                for (pos, n) in snapshot.tiles.iter() {
+                    board.insert_tile(*pos, *n);
+                }
+                board.score = snapshot.score;
+                board.spawned = snapshot.spawned;
+            }
+            None => {
+                board.tiles = self.tiles.clone();
+                board.score = self.score;
+            }
+        }
+        board
+    }
+
+    /// go back to the given point of the history, everything that
+    /// happened after it is forgotten
+    pub fn restore(&mut self, index: usize) {
+        if index >= self.history.len() {
+            return;
+        }
+        let board = self.board_at(index);
+        self.tiles = board.tiles;
+        self.compact_hash.set(None);
+        self.score = board.score;
+        self.spawned = board.spawned;
+        self.moving_tiles = vec![];
+        self.lingering.clear();
+        self.last_spawn = None;
+        // the history doesn't keep the ancestry and the restored tiles have new ids
+        self.merge_trees.clear();
+        self.history.truncate(index);
+        self.undos += 1;
+        debug_log!(debug, "restored index={} undos={}", index, self.undos);
+    }
+
+    pub fn coordinates(&self) -> Coordinates {
+        self.coordinates
+    }
+
+    pub fn mv(&mut self, new_coordinates: Coordinates) {
+        self.coordinates = new_coordinates;
+        self.coord_cache.clear();
+    }
+
+    pub fn change_tile_size(&mut self, new_size: u16) {
+        if new_size == self.tile_width {
+            return;
+        }
+        self.tile_width = new_size;
+        self.tile_height = new_size / 2;
+        self.coord_cache.clear();
+        self.refresh_coordinates();
+    }
+
+    /// put every tile at the coordinates matching its position
+    pub fn refresh_coordinates(&mut self) {
+        for (pos, tile) in self.tiles.clone().iter() {
+            self.place_tile(*pos, *tile);
+        }
+    }
+
+    pub fn change_size(&mut self, new_size: u16) {
+        let new_size = std::cmp::max(new_size, MIN_SIZE);
+        if new_size == self.size {
+            return;
+        }
+        self.size = new_size;
+        self.visible = (new_size, new_size);
+        self.scroll = Position::new(0, 0);
+        self.coord_cache.clear();
+    }
+
+    /// number of columns and rows of squares that fit in `width` x `height`
+    /// cells of the terminal, always at least one
+    pub fn visible_squares(&self, width: u16, height: u16) -> (u16, u16) {
+        let columns = width.saturating_sub(2) / (self.tile_width + self.margin_x);
+        let rows = height.saturating_sub(1) / (self.tile_height + self.margin_y);
+        (columns.clamp(1, self.size), rows.clamp(1, self.size))
+    }
+
+    /// show only the squares that fit in `width` x `height` cells of the terminal,
+    /// a board that fits is shown whole and never scrolls
+    pub fn fit_viewport(&mut self, width: u16, height: u16) {
+        let visible = self.visible_squares(width, height);
+        if visible == self.visible {
+            return;
+        }
+        self.visible = visible;
+        self.scroll_to(self.scroll);
+    }
+
+    /// columns and rows of the squares currently shown
+    pub fn visible_range(&self) -> (Range<u16>, Range<u16>) {
+        let Position { x, y } = self.scroll;
+        (x..x + self.visible.0, y..y + self.visible.1)
+    }
+
+    pub fn is_visible(&self, pos: Position) -> bool {
+        let (columns, rows) = self.visible_range();
+        columns.contains(&pos.x) && rows.contains(&pos.y)
+    }
+
+    /// pan the viewport one square in the direction, it stops at the edges of the board
+    pub fn scroll(&mut self, mv: Move) {
+        let Position { x, y } = self.scroll;
+        let pos = match mv {
+            Move::Up => Position::new(x, y.saturating_sub(1)),
+            Move::Down => Position::new(x, y + 1),
+            Move::Left => Position::new(x.saturating_sub(1), y),
+            Move::Right => Position::new(x + 1, y),
+        };
+        self.scroll_to(pos);
+    }
+
+    fn scroll_to(&mut self, pos: Position) {
+        let pos = Position::new(
+            std::cmp::min(pos.x, self.size - self.visible.0),
+            std::cmp::min(pos.y, self.size - self.visible.1),
+        );
+        if pos == self.scroll {
+            return;
+        }
+        self.scroll = pos;
+        self.coord_cache.clear();
+        self.refresh_coordinates();
+    }
+
+    /// switch to a different merge factor, the tiles on the board keep
+    /// their rank so a 4 becomes a 9 when going from 2 to 3
+    pub fn change_merge_factor(&mut self, factor: u32) {
+        let factor = std::cmp::max(factor, 2);
+        if factor == self.merge_factor {
+            return;
+        }
+        self.rule = Rule(self.variant.rule(factor));
+        if self.variant != GameVariant::Standard {
+            self.merge_factor = factor;
+            return;
+        }
+        for tile in self.tiles.values_mut() {
+            let mut rank = 0;
+            let mut n = tile.n;
+            while n > 1 {
+                n /= self.merge_factor;
+                rank += 1;
+            }
+            tile.n = factor.pow(rank);
+        }
+        self.compact_hash.set(None);
+        self.merge_factor = factor;
+    }
+
+    /// stamp the events from now on with the time of `clock`, e.g. one a test steps by hand
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = GameClock(clock);
+    }
+
+    /// switch to the merge rule of a different variant, meant for a new game
+    /// before its first tiles spawn since the tiles on the board are left alone
+    pub fn set_variant(&mut self, variant: GameVariant) {
+        self.variant = variant;
+        self.rule = Rule(variant.rule(self.merge_factor));
+    }
+
+    /// the first power of the merge factor to reach 2048, which is 2048 itself
+    /// when tiles double and 2187 when they triple, unless a puzzle sets its own target.
+    /// The other variants play to the first tile of theirs past 2048, a threes game
+    /// can also make a bigger tile without ever making that one
+    pub fn winning_value(&self) -> u32 {
+        if let Some(target) = self.target {
+            return target;
+        }
+        let (mut previous, mut n) = match self.variant {
+            GameVariant::Standard => (1, self.merge_factor),
+            GameVariant::Threes => (3, 3),
+            GameVariant::Fibonacci => (1, 1),
+        };
+        while n < 2048 {
+            (previous, n) = match self.variant {
+                GameVariant::Standard => (n, n * self.merge_factor),
+                GameVariant::Threes => (n, n * 2),
+                GameVariant::Fibonacci => (n, previous + n),
+            };
+        }
+        n
+    }
+
+    /// play to make `target`, a value the rule can't make is rounded up to the next one
+    /// it can so the game can still be won, set it after the variant and merge factor
+    pub fn set_target(&mut self, target: u32) -> Result<(), GameError> {
+        match self.rule.0.next_tile_value(target) {
+            Some(n) => {
+                self.target = Some(n);
+                Ok(())
+            }
+            None => Err(GameError::InvalidData(format!(
+                "no tile of the {:?} variant reaches {}",
+                self.variant, target
+            ))),
+        }
+    }
+
+    /// true for a tile that wins the game: a tile of the winning value, in a threes game
+    /// any bigger tile too since its merges can skip past the winning value, while a
+    /// target game needs exactly the target
+    pub fn is_winning_tile(&self, n: u32) -> bool {
+        let target = self.winning_value();
+        match (self.mode, self.variant) {
+            (GameMode::Target, _) => n == target,
+            (_, GameVariant::Threes) => n >= target,
+            _ => n == target,
+        }
+    }
+
+    /// values new tiles spawn with and how many times out of the total each of them does
+    pub fn spawn_weights(&self) -> Vec<(u32, u32)> {
+        self.rule.0.spawn_weights()
+    }
+
+    /// value of the tile a merge producing `n` leaves on the board, in an endless
+    /// game the winning tile respawns as the smallest tile
+    fn merged_tile_value(&self, n: u32) -> u32 {
+        if self.mode == GameMode::Endless && self.is_winning_tile(n) {
+            return self.rule.0.spawn_weights()[0].0;
+        }
+        n
+    }
+
+    pub fn change_margins(&mut self, margin_x: u16, margin_y: u16) {
+        if (margin_x, margin_y) == (self.margin_x, self.margin_y) {
+            return;
+        }
+        self.margin_x = margin_x;
+        self.margin_y = margin_y;
+        self.coord_cache.clear();
+        self.refresh_coordinates();
+    }
+
+    /// width of the board on the screen, only the visible columns count
+    pub fn width(&self) -> u16 {
+        2 + self.tile_width * self.visible.0 + self.margin_x * self.visible.0
+    }
+
+    /// height of the board on the screen, only the visible rows count
+    pub fn height(&self) -> u16 {
+        1 + self.tile_height * self.visible.1 + self.margin_y * self.visible.1
+    }
+
+    pub fn simulate_size(&self, tile_size: u16) -> (u16, u16) {
+        let width = 2 + tile_size * self.size + self.margin_x * self.size;
+        let height = 1 + tile_size / 2 * self.size + self.margin_y * self.size;
+        (width + self.coordinates.x, height + self.coordinates.y)
+    }
+
+    /// true once a tile reached the winning value, see `is_winning_tile`
+    pub fn reached_target(&self) -> bool {
+        self.tiles.values().any(|tile| self.is_winning_tile(tile.n))
+    }
+
+    pub fn check_if_game_can_continue(&mut self) -> Result<(), String> {
+        if self.reached_target() {
+            debug_log!(
+                info,
+                "game won score={} moves={}",
+                self.score,
+                self.history.len()
+            );
+            self.event_log.push(GameEvent::Won(self.clock.0.now()));
+            return Err("Game Won".to_string());
+        }
+
+        if !self.has_moves() {
+            debug_log!(
+                info,
+                "game lost score={} moves={}",
+                self.score,
+                self.history.len()
+            );
+            self.event_log.push(GameEvent::Lost(self.clock.0.now()));
+            return Err("Game Lost".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// every square of the board is taken, the game can still go on as long as
+    /// there are tiles that can be merged
+    pub fn board_full(&self) -> bool {
+        self.tiles.len() == (self.size * self.size) as usize
+    }
+
+    /// part of the board that is taken by tiles, 0.0 for an empty board and 1.0 for a full one
+    pub fn occupied_fraction(&self) -> f64 {
+        self.tiles.len() as f64 / (self.size * self.size) as f64
+    }
+
+    /// check if the move would change anything on the board
+    pub fn can_move(&self, mv: Move) -> bool {
+        !self.check(mv).is_empty()
+    }
+
+    /// check if there is at least one move that would change the board
+    pub fn has_moves(&self) -> bool {
+        !self.board_full()
+            || [Move::Up, Move::Down, Move::Left, Move::Right]
+                .iter()
+                .any(|mv| self.can_move(*mv))
+    }
+
+    /// position of the tile with the highest number, if there are more of
+    /// them the one with the lowest position is returned
+    pub fn max_tile_position(&self) -> Option<Position> {
+        self.tiles_sorted()
+            .into_iter()
+            .rev()
+            .max_by_key(|(_, tile)| tile.n)
+            .map(|(p, _)| p)
+    }
+
+    /// minus the mean manhattan distance of the tiles to the highest one divided by the
+    /// size of the board, 0 when the tiles huddle around it and more negative the more
+    /// they are spread out
+    pub fn compactness_score(&self) -> f64 {
+        let max = match self.max_tile_position() {
+            Some(pos) => pos,
+            None => return 0.0,
+        };
+        if self.tiles.len() < 2 {
+            return 0.0;
+        }
+        let total: u32 = self
+            .tiles
+            .keys()
+            .filter(|pos| **pos != max)
+            .map(|pos| {
+                (pos.x as i32 - max.x as i32).unsigned_abs()
+                    + (pos.y as i32 - max.y as i32).unsigned_abs()
+            })
+            .sum();
+        let mean = total as f64 / (self.tiles.len() - 1) as f64;
+        -mean / self.size as f64
+    }
+
+    /// average position of the tiles weighted by the log2 of their values,
+    /// the middle of the board when it's empty
+    pub fn centroid(&self) -> (f64, f64) {
+        let (mut x, mut y, mut weights) = (0.0, 0.0, 0.0);
+        for (pos, tile) in self.tiles.iter() {
+            let weight = (tile.n as f64).log2();
+            x += pos.x as f64 * weight;
+            y += pos.y as f64 * weight;
+            weights += weight;
+        }
+        if weights == 0.0 {
+            let middle = self.size.saturating_sub(1) as f64 / 2.0;
+            return (middle, middle);
+        }
+        (x / weights, y / weights)
+    }
+
+    /// true for the squares of the first and last rows and columns
+    pub fn is_perimeter(&self, pos: Position) -> bool {
+        let s = self.size.saturating_sub(1);
+        pos.x == 0 || pos.x == s || pos.y == 0 || pos.y == s
+    }
+
+    /// tiles on the border of the board, ordered by their position
+    pub fn perimeter_tiles(&self) -> Vec<(Position, &Tile)> {
+        self.tiles
+            .iter()
+            .filter(|(pos, _)| self.is_perimeter(**pos))
+            .map(|(pos, tile)| (*pos, tile))
+            .sorted_by_key(|(pos, _)| *pos)
+            .collect()
+    }
+
+    /// tiles off the border of the board, ordered by their position
+    pub fn interior_tiles(&self) -> Vec<(Position, &Tile)> {
+        self.tiles
+            .iter()
+            .filter(|(pos, _)| !self.is_perimeter(**pos))
+            .map(|(pos, tile)| (*pos, tile))
+            .sorted_by_key(|(pos, _)| *pos)
+            .collect()
+    }
+
+    pub fn is_corner(&self, pos: Position) -> bool {
+        let s = self.size.saturating_sub(1);
+        (pos.x == 0 || pos.x == s) && (pos.y == 0 || pos.y == s)
+    }
+
+    /// check if the move would take the highest tile out of the corner it's in,
+    /// which is usually a bad idea
+    pub fn leaves_corner(&self, mv: Move) -> bool {
+        match self.max_tile_position() {
+            Some(pos) if self.is_corner(pos) => {
+                let board = self.simulate(mv);
+                match board.max_tile_position() {
+                    Some(new_pos) => !board.is_corner(new_pos),
+                    None => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    pub fn get_tile_mut(&mut self, pos: Position) -> Option<&mut Tile> {
+        // the tile can be changed through the reference
+        self.compact_hash.set(None);
+        self.tiles.get_mut(&pos)
+    }
+
+    pub fn get_tile(&mut self, pos: Position) -> Option<Tile> {
+        self.tiles.get(&pos).copied()
+    }
+
+    pub fn get_coordinates_at(&self, pos: Position) -> Coordinates {
+        match self.coord_cache.get(&pos) {
+            Some(coordinates) => *coordinates,
+            None => self.compute_coordinates_at(pos),
+        }
+    }
+
+    /// square of the board the terminal cell falls on, the inverse of `get_coordinates_at`,
+    /// None for the gaps between the squares and anything outside the board
+    pub fn position_at_coordinate(&self, c: Coordinates) -> Option<Position> {
+        let axis = |c: u16, origin: u16, margin: u16, tile: u16| {
+            let offset = c.checked_sub(origin + margin)?;
+            let (square, within) = (offset / (tile + margin), offset % (tile + margin));
+            if within >= tile {
+                return None;
+            }
+            Some(square)
+        };
+        let x = axis(c.x, self.coordinates.x, self.margin_x, self.tile_width)?;
+        let y = axis(c.y, self.coordinates.y, self.margin_y, self.tile_height)?;
+        if x >= self.visible.0 || y >= self.visible.1 {
+            return None;
+        }
+        Some(Position::new(self.scroll.x + x, self.scroll.y + y))
+    }
+
+    /// squares scrolled past end up left of or above the board
+    fn compute_coordinates_at(&self, pos: Position) -> Coordinates {
+        let (step_x, step_y) = (
+            self.margin_x + self.tile_width,
+            self.margin_y + self.tile_height,
+        );
+        Coordinates {
+            x: (self.coordinates.x + self.margin_x + pos.x * step_x)
+                .saturating_sub(self.scroll.x * step_x),
+            y: (self.coordinates.y + self.margin_y + pos.y * step_y)
+                .saturating_sub(self.scroll.y * step_y),
+        }
+    }
+
+    pub fn insert_tile(&mut self, pos: Position, n: u32) {
+        let coordinates = self.get_coordinates_at(pos);
+        self.coord_cache.insert(pos, coordinates);
+        let tile = Tile {
+            id: self.next_tile_id,
+            spawn_position: pos,
+            ..Tile::new(coordinates, n)
+        };
+        self.next_tile_id += 1;
+        self.tiles.insert(pos, tile);
+        self.compact_hash.set(None);
+    }
+
+    /// put an existing tile to the position, unlike `insert_tile` it keeps
+    /// everything the tile remembers about its past
+    pub fn place_tile(&mut self, pos: Position, tile: Tile) {
+        let coordinates = self.get_coordinates_at(pos);
+        self.coord_cache.insert(pos, coordinates);
+        self.tiles.insert(
+            pos,
+            Tile {
+                coordinates,
+                ..tile
+            },
+        );
+        self.compact_hash.set(None);
+    }
+
+    /// manhattan distance between the tile at the position and the square it spawned at
+    pub fn tile_displacement(&self, pos: Position) -> u32 {
+        match self.tiles.get(&pos) {
+            Some(tile) => {
+                (pos.x.abs_diff(tile.spawn_position.x) + pos.y.abs_diff(tile.spawn_position.y))
+                    as u32
+            }
+            None => 0,
+        }
+    }
+
+    /// fewest steps the tile at `p1` has to take through empty squares to end up next to
+    /// the tile at `p2`, 0 if they are already neighbours, None if the tiles aren't equal
+    /// or the other tiles are in the way
+    pub fn manhattan_distance_to_merge(&self, p1: Position, p2: Position) -> Option<u32> {
+        let n1 = self.tiles.get(&p1)?.n;
+        let n2 = self.tiles.get(&p2)?.n;
+        if n1 != n2 || p1 == p2 {
+            return None;
+        }
+
+        let adjacent = |a: Position, b: Position| {
+            (a.x as i32 - b.x as i32).abs() + (a.y as i32 - b.y as i32).abs() == 1
+        };
+        let mut visited = HashSet::from([p1]);
+        let mut queue = VecDeque::from([(p1, 0)]);
+        while let Some((pos, steps)) = queue.pop_front() {
+            if adjacent(pos, p2) {
+                return Some(steps);
+            }
+            let Position { x, y } = pos;
+            let neighbours = [
+                (x.checked_sub(1), Some(y)),
+                (Some(x + 1), Some(y)),
+                (Some(x), y.checked_sub(1)),
+                (Some(x), Some(y + 1)),
+            ];
+            for (nx, ny) in neighbours {
+                let next = match (nx, ny) {
+                    (Some(nx), Some(ny)) if nx < self.size && ny < self.size => {
+                        Position::new(nx, ny)
+                    }
+                    _ => continue,
+                };
+                if self.tiles.contains_key(&next) || !visited.insert(next) {
+                    continue;
+                }
+                queue.push_back((next, steps + 1));
+            }
+        }
+        None
+    }
+
+    /// size of the largest group of empty squares connected side by side, the room
+    /// there is to maneuver, 0 on a full board
+    pub fn find_largest_empty_region(&self) -> usize {
+        self.empty_regions().into_iter().max().unwrap_or(0)
+    }
+
+    /// number of separate groups of connected empty squares
+    pub fn empty_region_count(&self) -> usize {
+        self.empty_regions().len()
+    }
+
+    /// sizes of the groups of connected empty squares, flood filled one after another.
+    /// The squares taken by a tile or already filled are one bit each of a mask,
+    /// row after row
+    fn empty_regions(&self) -> Vec<usize> {
+        let mut blocked = vec![0u64; (self.size as usize * self.size as usize).div_ceil(64)];
+        let mut block = |pos: Position| {
+            let bit = pos.y as usize * self.size as usize + pos.x as usize;
+            let was_blocked = blocked[bit / 64] & (1 << (bit % 64)) != 0;
+            blocked[bit / 64] |= 1 << (bit % 64);
+            !was_blocked
+        };
+        for pos in self.tiles.keys() {
+            block(*pos);
+        }
+        let mut regions = vec![];
+        for (x, y) in (0..self.size).cartesian_product(0..self.size) {
+            let start = Position::new(x, y);
+            if !block(start) {
+                continue;
+            }
+            let mut size = 0;
+            let mut stack = vec![start];
+            while let Some(Position { x, y }) = stack.pop() {
+                size += 1;
+                let neighbours = [
+                    (x.checked_sub(1), Some(y)),
+                    (Some(x + 1), Some(y)),
+                    (Some(x), y.checked_sub(1)),
+                    (Some(x), Some(y + 1)),
+                ];
+                for (nx, ny) in neighbours {
+                    let next = match (nx, ny) {
+                        (Some(nx), Some(ny)) if nx < self.size && ny < self.size => {
+                            Position::new(nx, ny)
+                        }
+                        _ => continue,
+                    };
+                    if block(next) {
+                        stack.push(next);
+                    }
+                }
+            }
+            regions.push(size);
+        }
+        regions
+    }
+
+    /// average distance the tiles on the board travelled since they spawned
+    pub fn mean_displacement(&self) -> f64 {
+        if self.tiles.is_empty() {
+            return 0.0;
+        }
+        let total: u32 = self
+            .tiles
+            .keys()
+            .map(|pos| self.tile_displacement(*pos))
+            .sum();
+        total as f64 / self.tiles.len() as f64
+    }
+
+    /// ancestry of the tile at `pos`, None if the square is empty
+    pub fn trace_merge_tree(&self, pos: Position) -> Option<MergeNode> {
+        let tile = self.tiles.get(&pos)?;
+        Some(match self.merge_trees.get(&tile.id) {
+            Some(node) => node.clone(),
+            None => MergeNode::leaf(tile.n),
+        })
+    }
+
+    /// the merge trees of every tile on the board as a Graphviz graph, every tile points to
+    /// the two tiles that merged into it, `dot -Tsvg` draws it
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn serialize_to_dot(&self) -> String {
+        let mut dot = String::from("digraph merges {\n    node [shape=box, style=filled];\n");
+        let mut next_id = 0;
+        for (pos, _) in self.tiles_sorted() {
+            if let Some(tree) = self.trace_merge_tree(pos) {
+                Self::write_dot_node(&mut dot, &tree, Some(pos), &mut next_id);
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// write the node and everything under it to `dot` and return the name of the node,
+    /// only the tiles still on the board know their position
+    #[cfg(not(target_arch = "wasm32"))]
+    fn write_dot_node(
+        dot: &mut String,
+        node: &MergeNode,
+        pos: Option<Position>,
+        next_id: &mut usize,
+    ) -> String {
+        let name = format!("n{}", next_id);
+        *next_id += 1;
+        let label = match pos {
+            Some(pos) => format!("{} at ({}, {})", node.value, pos.x, pos.y),
+            None => node.value.to_string(),
+        };
+        let (r, g, b) = match get_bg_color_for_n(node.value) {
+            Color::Rgb(r, g, b) => (r, g, b),
+            _ => (255, 255, 255),
+        };
+        dot.push_str(&format!(
+            "    {} [label=\"{}\", fillcolor=\"#{:02x}{:02x}{:02x}\"];\n",
+            name, label, r, g, b
+        ));
+        for child in node.children.iter() {
+            let child_name = Self::write_dot_node(dot, child, None, next_id);
+            dot.push_str(&format!("    {} -> {};\n", name, child_name));
+        }
+        name
+    }
+
+    /// most generations of merges behind any tile on the board
+    pub fn deepest_merge_count(&self) -> u8 {
+        self.tiles
+            .values()
+            .map(|tile| tile.merge_count)
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn take_merge_tree(&mut self, tile: &Tile) -> MergeNode {
+        match self.merge_trees.remove(&tile.id) {
+            Some(node) => node,
+            None => MergeNode::leaf(tile.n),
+        }
+    }
+
+    pub fn remove_tile(&mut self, pos: Position) {
+        self.tiles.remove(&pos);
+        self.compact_hash.set(None);
+    }
+
+    pub fn remove_moving_tile(&mut self, pos: Position) {
+        let index = self
+            .moving_tiles
+            .iter()
+            .position(|(p, _)| p == &pos)
+            .unwrap();
+        self.moving_tiles.remove(index);
+    }
+
+    /// spawn a 2 or sometimes a 4 on a random empty square and return the square,
+    /// the nth spawn of a game always draws from the same generator made from the seed
+    pub fn spawn_random_tile(&mut self) -> Result<Position, GameError> {
+        // the spawn count is spread over the bits so neighbouring seeds don't share spawns
+        let mut rng =
+            StdRng::seed_from_u64(self.seed ^ self.spawned.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        self.spawned += 1;
+        self.spawn_random_tile_with(&mut rng)
+    }
+
+    /// empty squares a new tile can spawn on, in a trailing game only the ones on the
+    /// edge opposite to the last move unless that edge is full
+    pub fn spawn_squares(&self) -> Vec<Position> {
+        let s = self.size.saturating_sub(1);
+        let empty = (0..self.size)
+            .cartesian_product(0..self.size)
+            .map(|(x, y)| Position::new(x, y))
+            .filter(|pos| !self.tiles.contains_key(pos));
+        let trailing = match (self.mode, self.last_move) {
+            (GameMode::Trailing, Some(mv)) => mv,
+            _ => return empty.collect(),
+        };
+        let (edge, rest): (Vec<Position>, Vec<Position>) = empty.partition(|pos| match trailing {
+            Move::Left => pos.x == s,
+            Move::Right => pos.x == 0,
+            Move::Up => pos.y == s,
+            Move::Down => pos.y == 0,
+        });
+        if edge.is_empty() {
+            rest
+        } else {
+            edge
+        }
+    }
+
+    /// like `spawn_random_tile` but both the square and the value come from `rng`
+    pub fn spawn_random_tile_with(&mut self, rng: &mut impl Rng) -> Result<Position, GameError> {
+        let pos = match self.spawn_squares().choose(rng) {
+            Some(square) => *square,
+            None => return Err(GameError::BoardFull),
+        };
+
+        let weights = self.rule.0.spawn_weights();
+        let mut roll = rng.gen_range(0..weights.iter().map(|(_, weight)| weight).sum::<u32>());
+        let mut new_n = weights[0].0;
+        for (n, weight) in weights {
+            if roll < weight {
+                new_n = n;
+                break;
+            }
+            roll -= weight;
+        }
+        debug_log!(trace, "spawned n={} at={:?}", new_n, pos);
+        self.insert_tile(pos, new_n);
+        let ticks = self.ticks;
+        if let Some(tile) = self.get_tile_mut(pos) {
+            tile.spawned_at = Some(ticks);
+        }
+        self.last_spawn = Some(pos);
+        self.event_log.push(GameEvent::Spawned {
+            at: pos,
+            value: new_n,
+            instant: self.clock.0.now(),
+        });
+        Ok(pos)
+    }
+
+    fn flipped_position(size: u16, flip: &Flip, pos: Position) -> Position {
+        let s = size.saturating_sub(1);
+        match flip {
+            Flip::Horizontal => Position::new(s - pos.x, pos.y),
+            Flip::CounterClock => Position::new(s - pos.y, pos.x),
+            Flip::Clock => Position::new(pos.y, s - pos.x),
+            Flip::HalfTurn => Position::new(s - pos.x, s - pos.y),
+        }
+    }
+
+    /// move every tile to its flipped square without a second map: the squares form
+    /// cycles, e.g. the four corners of a rotation or two squares across a mirror,
+    /// and the tiles of each cycle move one square along it
+    pub fn flip(&mut self, flip: Flip) {
+        let size = self.size;
+        let flipped = |pos| Self::flipped_position(size, &flip, pos);
+        for (pos, new_pos) in self.moving_tiles.iter_mut() {
+            *pos = flipped(*pos);
+            *new_pos = flipped(*new_pos);
+        }
+
+        let half = size / 2;
+        match flip {
+            // the rings of the board from the outside in, every square of the top row
+            // of a ring but its last one starts a cycle around the four sides
+            Flip::Clock | Flip::CounterClock => {
+                for ring in 0..half {
+                    for x in ring..size - 1 - ring {
+                        let first = Position::new(x, ring);
+                        let second = flipped(first);
+                        let third = flipped(second);
+                        let cycle = [first, second, third, flipped(third)];
+                        Self::shift_cycle(&mut self.tiles, cycle);
+                    }
+                }
+            }
+            // the squares of the top half swap with the ones of the bottom half,
+            // the middle row of an odd board swaps within itself
+            Flip::HalfTurn => {
+                let middle = (0..size % 2 * half).map(|x| Position::new(x, half));
+                for first in (0..half)
+                    .cartesian_product(0..size)
+                    .map(|(y, x)| Position::new(x, y))
+                    .chain(middle)
+                {
+                    Self::shift_cycle(&mut self.tiles, [first, flipped(first)]);
+                }
+            }
+            Flip::Horizontal => {
+                for (x, y) in (0..half).cartesian_product(0..size) {
+                    let first = Position::new(x, y);
+                    Self::shift_cycle(&mut self.tiles, [first, flipped(first)]);
+                }
+            }
+        }
+        for tile in self.tiles.values_mut() {
+            tile.spawn_position = flipped(tile.spawn_position);
+        }
+        self.compact_hash.set(None);
+    }
+
+    /// move the tile of every square of the cycle to the next square, the last one's to
+    /// the first. With every square taken, as on a crowded board, the tiles are only
+    /// swapped, otherwise the tiles are carried along and the squares emptied or filled
+    fn shift_cycle<const N: usize>(tiles: &mut TileMap, cycle: [Position; N]) {
+        let squares = tiles.get_disjoint_mut(cycle.each_ref());
+        if squares.iter().all(Option::is_some) {
+            let mut carried = squares[N - 1].as_deref().copied();
+            for square in squares.into_iter().flatten() {
+                carried = carried.map(|tile| mem::replace(square, tile));
+            }
+            return;
+        }
+
+        let mut carried = tiles.get(&cycle[N - 1]).copied();
+        for pos in cycle {
+            carried = match (tiles.entry(pos), carried) {
+                (Entry::Occupied(mut entry), Some(tile)) => {
+                    Some(mem::replace(entry.get_mut(), tile))
+                }
+                (Entry::Occupied(entry), None) => Some(entry.remove()),
+                (Entry::Vacant(entry), Some(tile)) => {
+                    entry.insert(tile);
+                    None
+                }
+                (Entry::Vacant(_), None) => None,
+            };
+        }
+    }
+
+    /// rotate the board by 90 degrees, moving the tiles within the map they are in
+    pub fn rotate_90_in_place(&mut self, clockwise: bool) {
+        match clockwise {
+            true => self.flip(Flip::Clock),
+            false => self.flip(Flip::CounterClock),
+        }
+    }
+
+    /// rotate the board clockwise by 90 degrees `n` times, the tiles are moved only once
+    pub fn rotate_clockwise_n_in_place(&mut self, n: u8) {
+        if let Some(flip) = Flip::clockwise(n) {
+            self.flip(flip);
+        }
+    }
+
+    /// copy of the board rotated clockwise by 90 degrees `n` times, 0 or 4 keep it as it is
+    pub fn rotate_tiles_clockwise_n(&self, n: u8) -> Grid {
+        let mut rotated = self.clone();
+        rotated.rotate_clockwise_n_in_place(n);
+        rotated
     }
 
     fn get_desired_position(
         &mut self,
         pos: Position,
         n: u32,
-        unavailable: &Vec<Position>,
+        unavailable: &[Position],
+        rule: &dyn MergeRule,
     ) -> (Position, u32) {
         let Position { x, y } = pos;
         if x == 0_u16 {
@@ -313,8 +2380,9 @@ impl Grid {
             }
 
             if let Some(checking_tile) = self.get_tile(new_pos) {
-                if checking_tile.n == n {
-                    return (Position::new(checking_x, y), n * 2);
+                if rule.can_merge(checking_tile.n, n) {
+                    let merged = rule.merged_value(checking_tile.n, n);
+                    return (Position::new(checking_x, y), merged);
                 } else {
                     break;
                 }
@@ -325,70 +2393,354 @@ impl Grid {
         (Position::new(new_x, y), n)
     }
 
-    /// try to move the tiles in the direction specified by "mv", by first flipping
-    /// the board always to the same position, solving for this position and then
-    /// flipping it back to its original position
+    /// try to move the tiles in the direction specified by "mv", by first rotating
+    /// the board so the move goes to the left, solving for the move to the left and
+    /// then rotating it back to its original position
     ///
     /// For example if we want to move the tiles down we can instead rotate the board
-    /// clockwise then solve for tiles moving to the left and then rotate the board
-    /// back to it's original position (counterclockwise)
-    pub fn check(&mut self, mv: Move) -> Vec<(Position, Position)> {
-        let mut new_grid = Grid {
-            tiles: HashMap::new(),
-            moving_tiles: vec![],
-            ..*self
-        };
+    /// three times clockwise then solve for tiles moving to the left and then rotate
+    /// the board once more to get back to it's original position
+    pub fn check(&self, mv: Move) -> Vec<(Position, Position)> {
+        self.check_with_merge_positions(mv)
+            .movements
+            .into_iter()
+            .map(|movement| (movement.from, movement.to))
+            .collect()
+    }
 
-        match mv {
-            Move::Right => {
-                self.flip(Flip::Horizontal);
+    /// `check` together with the squares the move merges tiles on, e.g. to flash the
+    /// merged tiles or pop up the score where they are
+    pub fn check_with_merge_positions(&self, mv: Move) -> MoveResult {
+        self.move_result(&self.resolve(mv))
+    }
+
+    /// compare the board with the board `resolved` from it, a square two tiles end up
+    /// on is a merge, both of them moving there or one of them staying put
+    fn move_result(&self, resolved: &Grid) -> MoveResult {
+        let movements: Vec<TileAnimation> = resolved
+            .moving_tiles
+            .iter()
+            .map(|(from, to)| TileAnimation {
+                from: *from,
+                to: *to,
+            })
+            .collect();
+        let merges = movements
+            .iter()
+            .map(|movement| movement.to)
+            .sorted_by_key(|to| (to.y, to.x))
+            .dedup()
+            .filter(|to| {
+                let arriving = movements.iter().filter(|m| m.to == *to).count();
+                let staying =
+                    self.tiles.contains_key(to) && movements.iter().all(|m| m.from != *to);
+                arriving + staying as usize == 2
+            })
+            .filter_map(|to| resolved.tiles.get(&to).map(|tile| (to, tile.n)))
+            .collect();
+        MoveResult {
+            movements,
+            merges,
+            score_delta: resolved.score.saturating_sub(self.score),
+        }
+    }
+
+    /// `check` with the tiles that travel the fewest squares first, so a tile landing
+    /// on a square another tile is leaving is animated after that tile got going
+    pub fn manhattan_gravity_sort(&self, mv: Move) -> Vec<(Position, Position)> {
+        Self::sorted_by_travel(self.check(mv))
+    }
+
+    /// movements ordered by the manhattan distance between their squares, ties keep
+    /// the order they came in
+    fn sorted_by_travel(moving_tiles: Vec<(Position, Position)>) -> Vec<(Position, Position)> {
+        moving_tiles
+            .into_iter()
+            .sorted_by_key(|(from, to)| {
+                TileAnimation {
+                    from: *from,
+                    to: *to,
+                }
+                .distance()
+            })
+            .collect()
+    }
+
+    /// board after the move without animating the tiles and without spawning
+    /// a new tile, the original board is left untouched
+    pub fn simulate(&self, mv: Move) -> Grid {
+        let mut new_board = self.resolve(mv);
+        new_board.moving_tiles = vec![];
+        new_board.refresh_coordinates();
+        new_board
+    }
+
+    /// board after the move together with the score the move earns,
+    /// none if the move doesn't change the board
+    pub fn apply_move_scored(&self, mv: Move) -> Option<(Grid, u32)> {
+        let new_board = self.simulate(mv);
+        if new_board.same_tiles(self) {
+            return None;
+        }
+        let score = new_board.score - self.score;
+        Some((new_board, score))
+    }
+
+    /// highest tile the merges of the move would make, 0 if the move merges nothing
+    /// or doesn't change the board, two 8s made by the same move count as an 8
+    pub fn largest_merge_in_move(&self, mv: Move) -> u32 {
+        match self.apply_move_scored(mv) {
+            Some((board, _)) => board.last_merges.iter().copied().max().unwrap_or(0),
+            None => 0,
+        }
+    }
+
+    /// number of merges the move would make, 0 if it doesn't change the board
+    pub fn count_merges_after(&self, mv: Move) -> usize {
+        match self.apply_move_scored(mv) {
+            Some((board, _)) => board.last_merges.len(),
+            None => 0,
+        }
+    }
+
+    /// `count_merges_after` for every direction, in the order up, down, left and right
+    pub fn merges_per_direction(&self) -> [usize; 4] {
+        [Move::Up, Move::Down, Move::Left, Move::Right].map(|mv| self.count_merges_after(mv))
+    }
+
+    /// highest tile a single merge could make right now in any direction, the neighbours
+    /// of a row or column are the tiles a move would slide together so the empty squares
+    /// between them are skipped, none if no two tiles can merge
+    pub fn largest_potential_merge(&self) -> Option<u32> {
+        let rule = self.rule.0.as_ref();
+        let mut largest = None;
+        for i in 0..self.size {
+            let row: Vec<u32> = (0..self.size)
+                .filter_map(|x| self.tiles.get(&Position::new(x, i)))
+                .map(|tile| tile.n)
+                .collect();
+            let column: Vec<u32> = (0..self.size)
+                .filter_map(|y| self.tiles.get(&Position::new(i, y)))
+                .map(|tile| tile.n)
+                .collect();
+            for pair in row.windows(2).chain(column.windows(2)) {
+                if rule.can_merge(pair[0], pair[1]) {
+                    largest = largest.max(Some(rule.merged_value(pair[0], pair[1])));
+                }
             }
-            Move::Up => {
-                self.flip(Flip::Clock);
+        }
+        largest
+    }
+
+    /// values of the tiles merges could make within `depth` moves, after each move but
+    /// the last every possible spawn is tried, the search grows quickly so keep it small
+    pub fn reachable_values(&self, depth: u8) -> HashSet<u32> {
+        let mut reachable = HashSet::new();
+        let mut seen: HashSet<Vec<Vec<u32>>> = HashSet::new();
+        let mut frontier = vec![self.clone()];
+        for level in 0..depth {
+            let mut next = vec![];
+            for board in frontier.iter() {
+                for mv in [Move::Up, Move::Down, Move::Left, Move::Right] {
+                    let (moved, _) = match board.apply_move_scored(mv) {
+                        Some(moved) => moved,
+                        None => continue,
+                    };
+                    reachable.extend(moved.last_merges.iter().copied());
+                    if level + 1 == depth {
+                        continue;
+                    }
+                    for spawned in moved.possible_spawns() {
+                        if seen.insert(spawned.to_matrix()) {
+                            next.push(spawned);
+                        }
+                    }
+                }
             }
-            Move::Down => {
-                self.flip(Flip::CounterClock);
+            frontier = next;
+        }
+        reachable
+    }
+
+    /// every board a spawn could leave behind, the board itself if nothing spawns
+    fn possible_spawns(&self) -> Vec<Grid> {
+        if !self.spawn_enabled {
+            return vec![self.clone()];
+        }
+        let mut boards = vec![];
+        for pos in self.spawn_squares() {
+            for (n, _) in self.rule.0.spawn_weights() {
+                let mut board = self.clone();
+                board.insert_tile(pos, n);
+                boards.push(board);
             }
-            _ => (),
-        };
+        }
+        boards
+    }
+
+    /// like `simulate` but the board is written into `out`, whose maps are cleared
+    /// and reused instead of allocating a new grid, the coordinates of its tiles
+    /// aren't refreshed so it's meant for searching rather than drawing
+    pub fn simulate_into(&self, mv: Move, out: &mut Grid) {
+        out.clear();
+        out.size = self.size;
+        out.merge_factor = self.merge_factor;
+        out.variant = self.variant;
+        out.rule = self.rule.clone();
+        out.merge_preference = self.merge_preference;
+        out.merge_limit = self.merge_limit;
+        out.mode = self.mode;
+        self.resolve_into(mv, out);
+        out.moving_tiles.clear();
+    }
+
+    /// remove all the tiles from the grid but keep the memory of the maps holding them
+    pub fn clear(&mut self) {
+        self.tiles.clear();
+        self.compact_hash.set(None);
+        self.moving_tiles.clear();
+        self.lingering.clear();
+        self.merge_trees.clear();
+        self.coord_cache.clear();
+        self.last_merges.clear();
+        self.last_spawn = None;
+    }
+
+    /// solve the move and return the grid with tiles at their desired positions,
+    /// moving tiles of the returned grid are the movements needed to get there
+    fn resolve(&self, mv: Move) -> Grid {
+        let mut new_grid = self.empty_like();
+        self.resolve_into(mv, &mut new_grid);
+        new_grid
+    }
+
+    /// solve the move into `new_grid`, which is expected to be empty
+    fn resolve_into(&self, mv: Move, new_grid: &mut Grid) {
+        self.resolve_into_with(mv, new_grid, self.rule.0.as_ref());
+    }
+
+    /// board moved to the left with `rule` deciding which tiles merge instead of the
+    /// rule of the grid, handy for trying out a rule on a board
+    pub fn apply_rule(&self, rule: &dyn MergeRule) -> Grid {
+        let mut new_grid = self.empty_like();
+        self.resolve_into_with(Move::Left, &mut new_grid, rule);
+        new_grid.moving_tiles = vec![];
+        new_grid.refresh_coordinates();
+        new_grid
+    }
 
-        // thanks to flipping the grid, now we can move all the tiles to the left and then
-        // flip the grid back to it's original position but this time with tiles moved to
-        // their desired position
+    fn resolve_into_with(&self, mv: Move, new_grid: &mut Grid, rule: &dyn MergeRule) {
+        // thanks to rotating the positions, now we can move all the tiles to the left and
+        // then rotate the new grid back to it's original position but this time with tiles
+        // moved to their desired position
+        // the tiles keep their id and past like they do when the move is animated,
+        // rotating them back flips their spawn position back so it's flipped along
+        let flip = Flip::clockwise(Self::turns_for_left(mv));
+        let tiles: Vec<(Position, Tile)> = self
+            .tiles
+            .iter()
+            .map(|(pos, tile)| match &flip {
+                Some(flip) => (
+                    Self::flipped_position(self.size, flip, *pos),
+                    Tile {
+                        spawn_position: Self::flipped_position(
+                            self.size,
+                            flip,
+                            tile.spawn_position,
+                        ),
+                        ..*tile
+                    },
+                ),
+                None => (*pos, *tile),
+            })
+            .sorted_by_key(|(pos, _)| *pos)
+            .collect();
+        let values: Vec<(Position, u32)> = tiles.iter().map(|(pos, tile)| (*pos, tile.n)).collect();
+        let targets = self.merge_targets(&values, rule);
         let mut unavailable = vec![];
-        for (pos, tile) in self.tiles.iter().sorted_by_key(|(p, _)| p.x) {
-            let (new_pos, n) =
-                new_grid.get_desired_position(Position::new(pos.x, pos.y), tile.n, &unavailable);
+        new_grid.score = self.score;
+        new_grid.conversions = self.conversions;
+        new_grid.next_tile_id = self.next_tile_id;
+        for (pos, tile) in tiles {
+            let (new_pos, n) = new_grid.get_desired_position(pos, tile.n, &unavailable, rule);
+            let mut placed = tile;
             if n > tile.n {
+                new_grid.score += n * self.score_multiplier;
+                new_grid.last_merges.push(n);
+                // the tile already in place keeps its id, see `Tile::id`
+                let target = new_grid.get_tile(new_pos).unwrap_or(tile);
+                placed = Tile {
+                    n: self.merged_tile_value(n),
+                    merge_count: std::cmp::max(target.merge_count, tile.merge_count)
+                        .saturating_add(1),
+                    ..target
+                };
+                if placed.n != n {
+                    new_grid.conversions += 1;
+                    placed.merge_count = 0;
+                }
+            }
+            if n > tile.n || !targets.contains(&pos) {
                 unavailable.push(new_pos);
             }
-            new_grid.insert_tile(new_pos, n);
-            if pos != &new_pos {
-                new_grid.moving_tiles.push((*pos, new_pos));
+            new_grid.place_tile(new_pos, placed);
+            if pos != new_pos {
+                new_grid.moving_tiles.push((pos, new_pos));
             }
         }
 
-        match mv {
-            Move::Right => {
-                self.flip(Flip::Horizontal);
-                new_grid.flip(Flip::Horizontal);
-            }
-            Move::Up => {
-                self.flip(Flip::CounterClock);
-                new_grid.flip(Flip::CounterClock);
+        new_grid.rotate_from_left(mv);
+        new_grid.last_move = Some(mv);
+    }
+
+    /// tiles that another tile merges into when the board moves to the left,
+    /// neighbours that merge are paired from the side the merge preference favours,
+    /// with a merge limit only the first pairs from the top row down are
+    fn merge_targets(&self, tiles: &[(Position, u32)], rule: &dyn MergeRule) -> HashSet<Position> {
+        let mut targets = HashSet::new();
+        let limit = self.merge_limit.unwrap_or(u32::MAX) as usize;
+        for y in 0..self.size {
+            let mut line: Vec<(Position, u32)> = tiles
+                .iter()
+                .filter(|(pos, _)| pos.y == y)
+                .copied()
+                .sorted_by_key(|(pos, _)| pos.x)
+                .collect();
+            if self.merge_preference == MergePreference::AwayFromMove {
+                line.reverse();
             }
-            Move::Down => {
-                self.flip(Flip::Clock);
-                new_grid.flip(Flip::Clock);
+            let mut i = 0;
+            while i + 1 < line.len() && targets.len() < limit {
+                if rule.can_merge(line[i].1, line[i + 1].1) {
+                    // the tile closer to the left is the one that stays in place
+                    targets.insert(std::cmp::min(line[i].0, line[i + 1].0));
+                    i += 2;
+                } else {
+                    i += 1;
+                }
             }
-            _ => (),
-        };
+        }
+        targets
+    }
+
+    /// clockwise turns that make the move a move to the left
+    fn turns_for_left(mv: Move) -> u8 {
+        match mv {
+            Move::Left => 0,
+            Move::Up => 1,
+            Move::Right => 2,
+            Move::Down => 3,
+        }
+    }
 
-        new_grid.moving_tiles
+    /// rotate a grid resolved as a move to the left back to the direction of the move
+    fn rotate_from_left(&mut self, mv: Move) {
+        self.rotate_clockwise_n_in_place(4 - Self::turns_for_left(mv));
     }
 
     pub fn on_tick(&mut self, mv: Option<Move>) -> Result<(), String> {
-        if self.moving_tiles.len() > 0 {
+        self.ticks += 1;
+        if !self.moving_tiles.is_empty() {
             // if tiles are still moving, move them closer to the desired position
             for (pos, new_pos) in self.moving_tiles.clone().iter() {
                 let desired = self.get_coordinates_at(*new_pos);
@@ -398,20 +2750,71 @@ impl Grid {
                 let mut x = current.x;
                 let mut y = current.y;
 
+                // steps are clamped so the tiles don't overshoot when the distance
+                // between the squares isn't a multiple of the step
                 match desired {
-                    _ if desired.x > current.x => x += 4,
-                    _ if desired.x < current.x => x -= 4,
-                    _ if desired.y > current.y => y += 2,
-                    _ if desired.y < current.y => y -= 2,
+                    _ if desired.x > current.x => x = std::cmp::min(x + 4, desired.x),
+                    _ if desired.x < current.x => x = std::cmp::max(x.saturating_sub(4), desired.x),
+                    _ if desired.y > current.y => y = std::cmp::min(y + 2, desired.y),
+                    _ if desired.y < current.y => y = std::cmp::max(y.saturating_sub(2), desired.y),
                     _ => {}
                 }
 
                 if desired == Coordinates::new(x, y) {
+                    if self.moving_tiles.iter().any(|(p, _)| p == new_pos) {
+                        // the tile on the square hasn't left it yet, it's still lingering
+                        // on top of the tile it merges with, wait for it to go
+                        self.get_tile_mut(*pos).unwrap().mv(desired);
+                        continue;
+                    }
                     if let Some(tile) = self.get_tile(*new_pos) {
-                        self.insert_tile(*new_pos, tile.n * 2);
+                        let ticks = self.lingering.entry(*pos).or_insert(0);
+                        if *ticks < self.merge_linger {
+                            // keep the tile on top of the one it merges with for
+                            // a while so it's visible that they are stacking
+                            *ticks += 1;
+                            self.get_tile_mut(*pos).unwrap().mv(desired);
+                            continue;
+                        }
+                        self.lingering.remove(pos);
+                        let moved = self.get_tile(*pos).unwrap();
+                        let merged = self.rule.0.merged_value(tile.n, moved.n);
+                        let n = self.merged_tile_value(merged);
+                        let mut merge_count =
+                            std::cmp::max(tile.merge_count, moved.merge_count).saturating_add(1);
+                        if n != merged {
+                            // the respawned tile starts a new tree
+                            self.conversions += 1;
+                            self.merge_trees.remove(&tile.id);
+                            merge_count = 0;
+                        } else {
+                            let node = MergeNode {
+                                value: n,
+                                children: vec![
+                                    self.take_merge_tree(&tile),
+                                    self.take_merge_tree(&moved),
+                                ],
+                            };
+                            self.merge_trees.insert(tile.id, node);
+                        }
+                        self.place_tile(
+                            *new_pos,
+                            Tile {
+                                n,
+                                merge_count,
+                                ..tile
+                            },
+                        );
+                        self.score += merged * self.score_multiplier;
+                        self.event_log.push(GameEvent::Merged {
+                            from: *pos,
+                            to: *new_pos,
+                            value: merged,
+                            at: self.clock.0.now(),
+                        });
                     } else {
-                        let n = self.get_tile(*pos).unwrap().n;
-                        self.insert_tile(*new_pos, n);
+                        let tile = self.get_tile(*pos).unwrap();
+                        self.place_tile(*new_pos, tile);
                     }
                     self.remove_tile(*pos);
                     self.remove_moving_tile(*pos);
@@ -421,22 +2824,1312 @@ impl Grid {
                 }
             }
 
-            if self.moving_tiles.len() == 0 {
+            if self.moving_tiles.is_empty() {
                 // if there is no more tiles moving it means that all
                 // the tiles achieved their desired position and we can
                 // spawn a new tile and check if game can continue
-                self.spawn_random_tile();
+                if self.spawn_enabled {
+                    match self.spawn_random_tile() {
+                        Ok(_) => (),
+                        // a move always frees a square so this shouldn't happen, but if it
+                        // does the full board decides the game rather than going unnoticed
+                        Err(GameError::BoardFull) if !self.has_moves() => {
+                            debug_log!(warn, "spawn failed on a full board without moves");
+                            self.event_log.push(GameEvent::Lost(self.clock.0.now()));
+                            return Err("Game Lost".to_string());
+                        }
+                        Err(err) => {
+                            debug_log!(warn, "spawn failed after a move: {:?}", err);
+                        }
+                    }
+                }
                 self.check_if_game_can_continue()?;
             }
 
             return Ok(());
         }
 
-        match mv {
-            Some(mv) => self.moving_tiles = self.check(mv),
-            _ => (),
+        if let Some(mv) = mv {
+            let resolved = self.resolve(mv);
+            let result = self.move_result(&resolved);
+            if !result.movements.is_empty() {
+                debug_log!(
+                    debug,
+                    "move resolved mv={:?} moving={} merges={:?} score_delta={}",
+                    mv,
+                    result.movements.len(),
+                    result.merges,
+                    result.score_delta
+                );
+                self.record_snapshot();
+                self.event_log
+                    .push(GameEvent::Moved(mv, self.clock.0.now()));
+                self.moves += 1;
+                self.last_merges = resolved.last_merges;
+                self.last_move = Some(mv);
+            } else if self.mode == GameMode::Hardcore {
+                self.lives = self.lives.saturating_sub(1);
+                self.life_lost_at = Some(self.ticks);
+                debug_log!(info, "life lost mv={:?} lives={}", mv, self.lives);
+                if self.lives == 0 {
+                    self.event_log.push(GameEvent::Lost(self.clock.0.now()));
+                    return Err("Game Lost".to_string());
+                }
+            }
+            let moving_tiles = result.movements.iter().map(|m| (m.from, m.to)).collect();
+            self.moving_tiles = Self::sorted_by_travel(moving_tiles);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+
+    /// board from rows of space separated values, see `Grid::from_string`
+    fn board(rows: &str) -> Grid {
+        Grid::from_string(rows).unwrap()
+    }
+
+    fn board_of(rows: &str, variant: GameVariant) -> Grid {
+        let mut grid = board(rows);
+        grid.set_variant(variant);
+        grid
+    }
+
+    /// board of tiles merging `factor` times bigger, the values are taken as they are
+    fn board_with_factor(rows: &str, factor: u32) -> Grid {
+        let tiles = board(rows);
+        let mut grid = Grid::empty(DEFAULT_TILE_WIDTH, tiles.size);
+        grid.change_merge_factor(factor);
+        grid.import_board(&tiles.export_board()).unwrap();
+        grid
+    }
+
+    /// clock that only moves when the test steps it
+    struct ManualClock(std::sync::Mutex<Instant>);
+
+    impl ManualClock {
+        fn step(&self, ms: u64) {
+            *self.0.lock().unwrap() += Duration::from_millis(ms);
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now(&self) -> Instant {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    /// tick the move until the tiles settled
+    fn play(grid: &mut Grid, mv: Move) {
+        let _ = grid.on_tick(Some(mv));
+        while !grid.moving_tiles.is_empty() {
+            let _ = grid.on_tick(None);
+        }
+    }
+
+    /// fails showing both boards as ascii tables when their tiles differ
+    fn assert_board(grid: &Grid, rows: &str) {
+        let expected = board(rows);
+        assert!(
+            grid.same_tiles(&expected),
+            "expected\n{}got\n{}",
+            expected.to_ascii_string(),
+            grid.to_ascii_string()
+        );
+    }
+
+    #[test]
+    fn standard_rule_merges_equal_tiles() {
+        let grid = board("2 2 4 0\n2 4 8 16\n0 0 0 0\n0 0 0 0");
+        assert_board(
+            &grid.simulate(Move::Left),
+            "4 4 0 0\n2 4 8 16\n0 0 0 0\n0 0 0 0",
+        );
+    }
+
+    #[test]
+    fn threes_rule_merges_pairs_adding_up_to_a_multiple_of_three() {
+        let grid = board_of("1 2 3 3\n1 1 0 0\n2 2 0 0\n3 6 0 0", GameVariant::Threes);
+        assert_board(
+            &grid.simulate(Move::Left),
+            "3 6 0 0\n1 1 0 0\n2 2 0 0\n9 0 0 0",
+        );
+    }
+
+    #[test]
+    fn fibonacci_rule_merges_neighbouring_numbers() {
+        let grid = board_of("1 1 2 3\n2 5 0 0\n3 5 0 0\n8 8 0 0", GameVariant::Fibonacci);
+        assert_board(
+            &grid.simulate(Move::Left),
+            "2 5 0 0\n2 5 0 0\n8 0 0 0\n8 8 0 0",
+        );
+    }
+
+    #[test]
+    fn apply_rule_moves_left_by_the_given_rule() {
+        let grid = board("1 2 0 0\n0 0 0 0\n0 0 0 0\n0 0 0 0");
+        assert_board(
+            &grid.apply_rule(&ThreesRule),
+            "3 0 0 0\n0 0 0 0\n0 0 0 0\n0 0 0 0",
+        );
+        assert_board(
+            &grid.apply_rule(&StandardMerge { factor: 2 }),
+            "1 2 0 0\n0 0 0 0\n0 0 0 0\n0 0 0 0",
+        );
+    }
+
+    #[test]
+    fn exported_board_imports_back() {
+        let grid = board("2 0 4 0\n0 8 0 0\n0 0 0 2048\n16 0 0 0");
+        let exported = grid.export_board();
+        assert_eq!(exported.len(), 16);
+        assert_eq!(exported[..4], [2, 0, 4, 0]);
+
+        let mut imported = board("0 0 0 0\n0 0 0 0\n0 0 0 0\n0 0 0 0");
+        imported.import_board(&exported).unwrap();
+        assert!(imported.same_tiles(&grid));
+    }
+
+    #[test]
+    fn import_rejects_boards_the_rule_cant_make() {
+        let mut grid = board("0 0\n0 0");
+        assert!(grid.import_board(&[2, 4, 0]).is_err());
+        assert!(grid.import_board(&[2, 3, 0, 0]).is_err());
+        grid.set_variant(GameVariant::Threes);
+        grid.import_board(&[1, 2, 3, 0]).unwrap();
+        assert!(grid.import_board(&[4, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn tripled_tiles_merge_and_score_by_the_factor() {
+        let grid = board_with_factor("3 3 0 0\n9 9 27 0\n0 0 0 0\n0 0 0 0", 3);
+        assert_board(
+            &grid.simulate(Move::Left),
+            "9 0 0 0\n27 27 0 0\n0 0 0 0\n0 0 0 0",
+        );
+        // the 3s are spawned, the 9s and the 27 were merged
+        assert_eq!(grid.compute_score_for_state(), 9 + 9 + 27);
+    }
+
+    #[test]
+    fn binary_form_keeps_any_tile_value() {
+        let grid = board("3 0 6 0\n0 70000 0 0\n0 0 0 1\n5 0 0 2");
+        let data = grid.serialize_binary();
+        let loaded = Grid::deserialize_binary(&data).unwrap();
+        assert_eq!(data[0], BINARY_VALUES);
+        assert!(loaded.same_tiles(&grid));
+        assert!(Grid::deserialize_binary(&data[..data.len() - 1]).is_err());
+        let too_large = [BINARY_VALUES, 2, 2, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01];
+        assert!(Grid::deserialize_binary(&too_large).is_err());
+    }
+
+    #[test]
+    fn binary_form_of_a_board_bigger_than_the_maximum_is_rejected() {
+        let biggest = Grid::empty(DEFAULT_TILE_WIDTH, MAX_SIZE).serialize_binary();
+        assert!(Grid::deserialize_binary(&biggest).is_ok());
+        let size = MAX_SIZE + 1;
+        let mut too_big = vec![BINARY_LOG2, size as u8, size as u8];
+        too_big.resize(3 + (size * size) as usize, 0);
+        assert!(Grid::deserialize_binary(&too_big).is_err());
+    }
+
+    #[test]
+    fn binary_form_without_the_format_byte_is_rejected() {
+        let data = board("2 0 0 0\n0 4 0 0\n0 0 0 0\n0 0 0 2").serialize_binary();
+        assert!(Grid::deserialize_binary(&data[1..]).is_err());
+        let mut unknown = data.clone();
+        unknown[0] = 7;
+        assert!(Grid::deserialize_binary(&unknown).is_err());
+    }
+
+    #[test]
+    fn binary_form_round_trips_empty_partial_and_full_boards() {
+        for size in 3..=8u16 {
+            let squares = size * size;
+            let full: Vec<(Position, u32)> = (0..squares)
+                .map(|i| (Position::new(i % size, i / size), 2 << (i % 16)))
+                .collect();
+            let partial: Vec<(Position, u32)> = full.iter().copied().step_by(3).collect();
+            for tiles in [&[][..], &partial, &full] {
+                let grid = Grid::new_from_positions(DEFAULT_TILE_WIDTH, size, tiles);
+                let data = grid.serialize_binary();
+                assert_eq!(data[..3], [BINARY_LOG2, size as u8, size as u8]);
+                assert_eq!(data.len(), 3 + squares as usize);
+                let loaded = Grid::deserialize_binary(&data).unwrap();
+                assert_eq!(loaded.size, size);
+                assert!(loaded.same_tiles(&grid), "{}x{} board", size, size);
+            }
+        }
+    }
+
+    #[test]
+    fn boards_of_any_rule_hash_equal_exactly_for_equal_tiles() {
+        let tripled = board_with_factor("3 9 0 0\n0 0 0 0\n0 0 0 0\n0 0 0 27", 3);
+        let moved = board_with_factor("3 9 0 0\n0 0 0 0\n0 0 0 0\n0 0 27 0", 3);
+        assert!(tripled.packed_tiles().is_some());
+        assert_ne!(tripled.compact_hash(), moved.compact_hash());
+
+        let threes = board_of("1 2 3 6\n0 0 0 0\n0 0 0 0\n0 0 0 12", GameVariant::Threes);
+        assert!(threes.packed_tiles().is_some());
+        let same = board_of("1 2 3 6\n0 0 0 0\n0 0 0 0\n0 0 0 12", GameVariant::Threes);
+        assert!(threes.same_tiles(&same));
+        // a different rule packs other values into the same hash
+        assert!(!threes.same_tiles(&board("2 4 8 16\n0 0 0 0\n0 0 0 0\n0 0 0 32")));
+    }
+
+    #[test]
+    fn targets_round_up_to_a_tile_of_the_rule() {
+        let mut grid = board("2 0\n0 2");
+        grid.set_target(100).unwrap();
+        assert_eq!(grid.winning_value(), 128);
+        grid.set_variant(GameVariant::Fibonacci);
+        grid.set_target(100).unwrap();
+        assert_eq!(grid.winning_value(), 144);
+        assert!(grid.set_target(u32::MAX).is_err());
+
+        grid.mode = GameMode::Target;
+        grid.import_board(&[144, 0, 0, 0]).unwrap();
+        assert!(grid.reached_target());
+        assert!(Grid::puzzle("2 2\n0 0", 5).unwrap().winning_value() == 8);
+    }
+
+    #[test]
+    fn event_log_is_saved_with_the_time_between_the_events() {
+        let clock = Arc::new(ManualClock(std::sync::Mutex::new(Instant::now())));
+        let mut grid = board("2 2 0 0\n0 0 0 0\n0 0 0 0\n0 0 0 0");
+        grid.set_clock(clock.clone());
+        play(&mut grid, Move::Left);
+        clock.step(1500);
+        play(&mut grid, Move::Right);
+        let first = grid.event_log[0].instant();
+        let last = grid.event_log.last().unwrap().instant();
+        assert_eq!(last - first, Duration::from_millis(1500));
+
+        let saved = grid.to_saved();
+        assert_eq!(saved.events.len(), grid.event_log.len());
+        let loaded = Grid::from_saved(&saved, DEFAULT_TILE_WIDTH).unwrap();
+        assert_eq!(loaded.event_summary(), grid.event_summary());
+        let (first, last) = (loaded.event_log[0], *loaded.event_log.last().unwrap());
+        assert_eq!(
+            last.instant() - first.instant(),
+            Duration::from_millis(1500)
+        );
+    }
+
+    #[test]
+    fn undone_move_spawns_the_same_tile_again() {
+        let mut grid = board("2 0 0 0\n0 0 0 0\n0 0 0 0\n0 0 0 0");
+        grid.seed = 7;
+        play(&mut grid, Move::Right);
+        let moved = grid.clone();
+        grid.restore(0);
+        assert_board(&grid, "2 0 0 0\n0 0 0 0\n0 0 0 0\n0 0 0 0");
+        play(&mut grid, Move::Right);
+        assert!(grid.same_tiles(&moved), "{}", grid.to_ascii_string());
+    }
+
+    #[test]
+    fn ascii_board_right_aligns_the_values() {
+        let grid = board("2 0\n128 16");
+        assert_eq!(
+            grid.to_ascii_string(),
+            "+-----+-----+\n|   2 |     |\n+-----+-----+\n| 128 |  16 |\n+-----+-----+\n"
+        );
+    }
+
+    #[test]
+    fn simulated_move_keeps_the_tiles() {
+        let grid = board("2 2 4 0\n0 0 0 0\n0 0 0 8\n0 0 0 0");
+        let tile = |grid: &Grid, x, y| grid.tiles[&Position::new(x, y)];
+        let moved = grid.simulate(Move::Right);
+        assert_board(&moved, "0 0 4 4\n0 0 0 0\n0 0 0 8\n0 0 0 0");
+        // the 4 slid over and the 2 already in place took the other one in
+        assert_eq!(tile(&moved, 3, 0).id, tile(&grid, 2, 0).id);
+        assert_eq!(tile(&moved, 3, 0).spawn_position, Position::new(2, 0));
+        assert_eq!(tile(&moved, 2, 0).id, tile(&grid, 1, 0).id);
+        assert_eq!(tile(&moved, 2, 0).merge_count, 1);
+        assert_eq!(
+            tile(&moved, 3, 2),
+            Tile {
+                coordinates: tile(&moved, 3, 2).coordinates,
+                ..tile(&grid, 3, 2)
+            }
+        );
+
+        let up = grid.simulate(Move::Up);
+        assert_eq!(tile(&up, 3, 0).id, tile(&grid, 3, 2).id);
+        assert_eq!(tile(&up, 3, 0).spawn_position, Position::new(3, 2));
+    }
+
+    #[test]
+    fn tile_values_follow_the_rule() {
+        let standard = StandardMerge { factor: 3 };
+        assert!(standard.is_tile_value(27) && !standard.is_tile_value(18));
+        assert_eq!(standard.next_tile_value(10), Some(27));
+        assert!(ThreesRule.is_tile_value(2) && ThreesRule.is_tile_value(3072));
+        assert!(!ThreesRule.is_tile_value(4));
+        assert_eq!(ThreesRule.next_tile_value(100), Some(102));
+        assert!(FibonacciMerge.is_tile_value(2584) && !FibonacciMerge.is_tile_value(100));
+        assert_eq!(FibonacciMerge.next_tile_value(100), Some(144));
+        assert_eq!(StandardMerge { factor: 2 }.next_tile_value(u32::MAX), None);
+    }
+
+    #[test]
+    fn only_threes_wins_past_the_winning_value() {
+        let standard = board("2048 0\n0 4096");
+        assert!(standard.is_winning_tile(2048) && !standard.is_winning_tile(4096));
+        let threes = board_of("3 0\n0 3", GameVariant::Threes);
+        assert_eq!(threes.winning_value(), 3072);
+        assert!(threes.is_winning_tile(3072) && threes.is_winning_tile(3075));
+        assert!(!threes.is_winning_tile(3069));
+        let fibonacci = board_of("1 0\n0 1", GameVariant::Fibonacci);
+        assert!(fibonacci.is_winning_tile(2584) && !fibonacci.is_winning_tile(4181));
+    }
+
+    #[test]
+    fn tile_staying_put_merges_with_the_tile_moving_onto_it() {
+        let grid = board("2 2 0 0\n0 0 0 0\n0 0 0 0\n0 0 0 0");
+        let result = grid.check_with_merge_positions(Move::Left);
+        assert_eq!(
+            result.movements,
+            vec![TileAnimation {
+                from: Position::new(1, 0),
+                to: Position::new(0, 0),
+            }]
+        );
+        assert_eq!(result.merges, vec![(Position::new(0, 0), 4)]);
+        assert_eq!(result.score_delta, 4);
+        assert_eq!(
+            grid.check(Move::Left),
+            vec![(Position::new(1, 0), Position::new(0, 0))]
+        );
+    }
+
+    #[test]
+    fn empty_regions_are_counted_by_flood_fill() {
+        let empty = Grid::empty(DEFAULT_TILE_WIDTH, 4);
+        assert_eq!(empty.find_largest_empty_region(), 16);
+        assert_eq!(empty.empty_region_count(), 1);
+
+        let full = board("2 4 2 4\n4 2 4 2\n2 4 2 4\n4 2 4 2");
+        assert_eq!(full.find_largest_empty_region(), 0);
+        assert_eq!(full.empty_region_count(), 0);
+
+        // a cross of tiles cuts the board into four corners of different sizes
+        let cross = board("0 2 0 0\n2 2 2 2\n0 2 0 0\n0 2 0 0");
+        assert_eq!(cross.find_largest_empty_region(), 4);
+        assert_eq!(cross.empty_region_count(), 4);
+        assert_eq!(
+            cross.empty_regions().into_iter().sorted().collect_vec(),
+            vec![1, 2, 2, 4]
+        );
+    }
+
+    #[test]
+    fn empty_regions_cover_the_largest_board() {
+        let mut grid = Grid::empty(DEFAULT_TILE_WIDTH, MAX_SIZE);
+        grid.insert_tile(Position::new(MAX_SIZE - 1, MAX_SIZE - 1), 2);
+        assert_eq!(grid.find_largest_empty_region(), 255);
+    }
+
+    #[test]
+    fn hardcore_move_doing_nothing_costs_a_life() {
+        let mut grid = board("0 2 0 0\n0 0 0 0\n0 0 0 0\n0 0 0 0");
+        grid.mode = GameMode::Hardcore;
+        grid.spawn_enabled = false;
+        // a move that slides the tile costs nothing
+        play(&mut grid, Move::Left);
+        assert_eq!(grid.lives, HARDCORE_LIVES);
+        for lives in (1..HARDCORE_LIVES).rev() {
+            assert!(grid.on_tick(Some(Move::Left)).is_ok());
+            assert_eq!(grid.lives, lives);
+        }
+        assert!(grid.on_tick(Some(Move::Left)).is_err());
+        assert_eq!(grid.lives, 0);
+    }
+
+    #[test]
+    fn cached_coordinates_follow_the_board() {
+        let mut grid = board("0 0 0 0\n0 2 0 0\n0 0 0 0\n0 0 0 0");
+        let pos = Position::new(1, 1);
+        let before = grid.get_coordinates_at(pos);
+        assert_eq!(grid.coord_cache.get(&pos), Some(&before));
+
+        grid.mv(Coordinates::new(10, 5));
+        let moved = grid.get_coordinates_at(pos);
+        assert_eq!(moved, Coordinates::new(before.x + 10, before.y + 5));
+        grid.refresh_coordinates();
+        assert_eq!(grid.tiles[&pos].coordinates, moved);
+        assert_eq!(grid.coord_cache.get(&pos), Some(&moved));
+
+        grid.change_tile_size(TILE_SIZES[0]);
+        assert_eq!(
+            grid.get_coordinates_at(pos),
+            grid.compute_coordinates_at(pos)
+        );
+        assert_ne!(grid.get_coordinates_at(pos), moved);
+    }
+
+    #[test]
+    fn tiles_in_one_column_resolve_in_the_same_order_every_time() {
+        let grid = board("0 2 0 0\n0 2 0 0\n0 0 0 0\n0 0 0 0");
+        let first = grid.check(Move::Left);
+        assert_eq!(
+            first,
+            vec![
+                (Position::new(1, 0), Position::new(0, 0)),
+                (Position::new(1, 1), Position::new(0, 1)),
+            ]
+        );
+        let merging = board("2 2 2 0\n0 0 0 0\n0 0 0 0\n0 0 0 0");
+        let merged = merging.simulate(Move::Left);
+        for _ in 0..20 {
+            assert_eq!(board(&grid.to_string()).check(Move::Left), first);
+            assert_eq!(merging.clone().simulate(Move::Left).tiles, merged.tiles);
+        }
+        assert_board(&merged, "4 2 0 0\n0 0 0 0\n0 0 0 0\n0 0 0 0");
+    }
+
+    #[test]
+    fn moving_the_biggest_tile_out_of_its_corner_is_risky() {
+        let grid = board("64 2 0 0\n4 0 0 0\n0 0 0 0\n0 0 0 0");
+        assert!(grid.leaves_corner(Move::Right));
+        assert!(grid.leaves_corner(Move::Down));
+        assert!(!grid.leaves_corner(Move::Left));
+        assert!(!grid.leaves_corner(Move::Up));
+        // nothing to warn about when the biggest tile isn't in a corner to begin with
+        let centered = board("0 0 0 0\n0 64 0 0\n0 0 0 0\n0 0 0 0");
+        assert!(!centered.leaves_corner(Move::Right));
+    }
+
+    #[test]
+    fn full_board_is_lost_only_without_merges() {
+        let mut playable = board("2 4 2 4\n4 2 4 2\n2 4 2 4\n4 2 4 4");
+        assert!(playable.board_full());
+        assert!(playable.has_moves());
+        assert!(playable.check_if_game_can_continue().is_ok());
+
+        let mut stuck = board("2 4 2 4\n4 2 4 2\n2 4 2 4\n4 2 4 2");
+        assert!(stuck.board_full());
+        assert!(!stuck.has_moves());
+        assert_eq!(
+            stuck.check_if_game_can_continue(),
+            Err("Game Lost".to_string())
+        );
+    }
+
+    #[test]
+    fn lingering_tile_merges_into_the_sum_once_it_settled() {
+        let mut grid = board("0 0 0 0\n2 0 0 2\n0 0 0 0\n0 0 0 0");
+        grid.spawn_enabled = false;
+        grid.merge_linger = 3;
+        let expected = grid.simulate(Move::Left);
+        let _ = grid.on_tick(Some(Move::Left));
+        let mut stacked_ticks = 0;
+        while !grid.moving_tiles.is_empty() {
+            let _ = grid.on_tick(None);
+            let target = grid.get_coordinates_at(Position::new(0, 1));
+            let moving = grid
+                .tiles
+                .get(&Position::new(3, 1))
+                .map(|tile| tile.coordinates);
+            if moving == Some(target) {
+                stacked_ticks += 1;
+            }
+        }
+        assert!(stacked_ticks >= 3, "stacked for {} ticks", stacked_ticks);
+        assert_eq!(grid.tiles.len(), 1);
+        assert_eq!(grid.tiles[&Position::new(0, 1)].n, 4);
+        assert!(grid.same_tiles(&expected));
+    }
+
+    #[test]
+    fn tile_spacing_presets_place_the_squares() {
+        let mut grid = board("0 0 0 0\n0 0 2 0\n0 0 0 0\n0 0 0 0");
+        let pos = Position::new(2, 1);
+        // 6x3 tiles with the margin before every square
+        for (spacing, expected) in [
+            (TileSpacing::Compact, Coordinates::new(1 + 2 * 7, 1 + 4)),
+            (TileSpacing::Normal, Coordinates::new(2 + 2 * 8, 1 + 4)),
+            (TileSpacing::Spacious, Coordinates::new(4 + 2 * 10, 2 + 5)),
+        ] {
+            let (margin_x, margin_y) = spacing.margins();
+            grid.change_margins(margin_x, margin_y);
+            assert_eq!(grid.get_coordinates_at(pos), expected, "{:?}", spacing);
+            assert_eq!(grid.tiles[&pos].coordinates, expected, "{:?}", spacing);
+            assert_eq!(TileSpacing::from_margins(spacing.margins()), Some(spacing));
+        }
+    }
+
+    #[test]
+    fn sorted_tiles_come_in_the_same_order_however_they_were_added() {
+        let tiles = [
+            (Position::new(3, 0), 2),
+            (Position::new(0, 2), 4),
+            (Position::new(1, 1), 8),
+            (Position::new(0, 0), 16),
+        ];
+        let forward = Grid::new_from_positions(DEFAULT_TILE_WIDTH, 4, &tiles);
+        let mut reversed_tiles = tiles;
+        reversed_tiles.reverse();
+        let reversed = Grid::new_from_positions(DEFAULT_TILE_WIDTH, 4, &reversed_tiles);
+        let positions = |grid: &Grid| {
+            grid.tiles_sorted()
+                .into_iter()
+                .map(|(pos, _)| pos)
+                .collect_vec()
+        };
+        assert_eq!(positions(&forward), positions(&reversed));
+        assert_eq!(
+            positions(&forward),
+            vec![
+                Position::new(0, 0),
+                Position::new(0, 2),
+                Position::new(1, 1),
+                Position::new(3, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn occupied_fraction_counts_the_taken_squares() {
+        let values: Vec<u32> = (0..16).map(|i| 2 << (i % 4)).collect();
+        for taken in [0, 8, 15, 16] {
+            let grid = board_from_values(4, &[&values[..taken], &[0; 16][taken..]].concat());
+            let expected = taken as f64 / 16.0;
+            assert!(
+                (grid.occupied_fraction() - expected).abs() < f64::EPSILON,
+                "{} of 16 squares taken",
+                taken
+            );
+        }
+    }
+
+    #[test]
+    fn tiles_are_displaced_once_they_move_from_their_spawn() {
+        let mut grid = Grid::empty(DEFAULT_TILE_WIDTH, 4);
+        let pos = grid.spawn_random_tile().unwrap();
+        assert_eq!(grid.tile_displacement(pos), 0);
+        assert_eq!(grid.mean_displacement(), 0.0);
+
+        grid.spawn_enabled = false;
+        let mv = if pos.x == 0 { Move::Right } else { Move::Left };
+        play(&mut grid, mv);
+        let (moved, _) = grid.tiles_sorted()[0];
+        assert!(grid.tile_displacement(moved) > 0);
+        assert!(grid.mean_displacement() > 0.0);
+    }
+
+    #[test]
+    fn boards_smaller_than_the_minimum_are_clamped() {
+        for size in [0, 1] {
+            let mut grid = Grid::new(DEFAULT_TILE_WIDTH, size);
+            assert_eq!(grid.size, MIN_SIZE);
+            for mv in [Move::Left, Move::Right, Move::Up, Move::Down] {
+                grid.simulate(mv);
+            }
+            grid.rotate_90_in_place(true);
+            grid.change_size(size);
+            assert_eq!(grid.size, MIN_SIZE);
+
+            let seeded =
+                Grid::new_with_seed(size, DEFAULT_TILE_WIDTH, Coordinates::new(0, 0), 2, 7);
+            assert_eq!(seeded.size, MIN_SIZE);
+            assert_eq!(seeded.tiles.len(), 2);
+            assert!(Grid::deserialize_binary(&[BINARY_LOG2, size as u8, size as u8]).is_err());
+        }
+        assert!(Grid::from_string("2").is_err());
+    }
+
+    #[test]
+    fn score_is_estimated_from_the_merged_tiles() {
+        assert_eq!(board("2 2\n2 0").compute_score_for_state(), 0);
+        assert_eq!(
+            board("2 4 8 0\n0 0 0 0\n0 0 0 0\n0 0 0 0").compute_score_for_state(),
+            12
+        );
+        assert_eq!(board("2048 2\n0 16").compute_score_for_state(), 2048 + 16);
+        assert_eq!(
+            Grid::empty(DEFAULT_TILE_WIDTH, 4).compute_score_for_state(),
+            0
+        );
+    }
+
+    #[test]
+    fn heatmaps_cover_every_square_with_empty_ones_at_zero() {
+        for rows in ["0 0 0\n0 0 0\n0 0 0", "4 0 0\n0 16 0\n0 0 2"] {
+            let grid = board(rows);
+            for map in [grid.density_map(), grid.log2_map()] {
+                assert_eq!(map.len(), 3);
+                assert!(map.iter().all(|row| row.len() == 3));
+                for pos in (0..3)
+                    .cartesian_product(0..3)
+                    .map(|(x, y)| Position::new(x, y))
+                {
+                    if !grid.tiles.contains_key(&pos) {
+                        assert_eq!(map[pos.y as usize][pos.x as usize], 0.0);
+                    }
+                }
+            }
+        }
+        let grid = board("4 0 0\n0 16 0\n0 0 2");
+        assert_eq!(grid.density_map()[0][0], 0.25);
+        assert_eq!(grid.density_map()[1][1], 1.0);
+        assert_eq!(grid.log2_map()[0][0], 0.5);
+        assert_eq!(grid.log2_map()[2][2], 0.25);
+    }
+
+    #[test]
+    fn new_games_start_with_the_asked_number_of_tiles() {
+        let mut rng = rand::thread_rng();
+        for n_tiles in [0, 1, 2, 5] {
+            let grid = Grid::new_with_initial_tiles(
+                4,
+                DEFAULT_TILE_WIDTH,
+                Coordinates::new(0, 0),
+                n_tiles,
+                &mut rng,
+            );
+            assert_eq!(grid.tiles.len(), n_tiles as usize);
+        }
+        // a board can't take more tiles than it has squares
+        let grid = Grid::new_with_initial_tiles(
+            2,
+            DEFAULT_TILE_WIDTH,
+            Coordinates::new(0, 0),
+            9,
+            &mut rng,
+        );
+        assert_eq!(grid.tiles.len(), 4);
+    }
+
+    #[test]
+    fn merge_preference_picks_the_pair_of_three_equal_tiles() {
+        let mut grid = board("2 2 2\n0 0 0\n0 0 0");
+        assert_board(&grid.simulate(Move::Left), "4 2 0\n0 0 0\n0 0 0");
+        grid.merge_preference = MergePreference::AwayFromMove;
+        assert_board(&grid.simulate(Move::Left), "2 4 0\n0 0 0\n0 0 0");
+        assert_board(&grid.simulate(Move::Right), "0 4 2\n0 0 0\n0 0 0");
+    }
+
+    #[test]
+    fn spawn_filling_the_last_square_loses_the_game() {
+        let mut grid = board("8 16 8 16\n16 8 16 8\n8 16 8 16\n0 32 64 128");
+        let mut result = grid.on_tick(Some(Move::Left));
+        while result.is_ok() && !grid.moving_tiles.is_empty() {
+            result = grid.on_tick(None);
+        }
+        assert!(grid.board_full());
+        assert_eq!(result, Err("Game Lost".to_string()));
+        assert!(matches!(grid.event_log.last(), Some(GameEvent::Lost(_))));
+    }
+
+    #[test]
+    fn best_row_and_column_sums() {
+        let grid = board("2 4 8 0\n0 0 0 0\n0 0 0 0\n0 0 0 0");
+        assert_eq!(grid.best_row_sum(), 14);
+        assert_eq!(grid.best_column_sum(), 8);
+        let empty = Grid::empty(DEFAULT_TILE_WIDTH, 4);
+        assert_eq!((empty.best_row_sum(), empty.best_column_sum()), (0, 0));
+        let uniform = board("8 8 8 8\n8 8 8 8\n8 8 8 8\n8 8 8 8");
+        assert_eq!(
+            (uniform.best_row_sum(), uniform.best_column_sum()),
+            (4 * 8, 4 * 8)
+        );
+    }
+
+    #[test]
+    fn scored_move_is_none_when_nothing_moves() {
+        let grid = board("2 2 0 0\n4 0 0 0\n0 0 0 0\n0 0 0 0");
+        assert!(board("2 4 0 0\n0 0 0 0\n0 0 0 0\n0 0 0 0")
+            .apply_move_scored(Move::Left)
+            .is_none());
+        let (merged, score) = grid.apply_move_scored(Move::Left).unwrap();
+        assert_eq!(score, 4);
+        assert_board(&merged, "4 0 0 0\n4 0 0 0\n0 0 0 0\n0 0 0 0");
+        let (_, score) = grid.apply_move_scored(Move::Down).unwrap();
+        assert_eq!(score, 0);
+    }
+
+    #[test]
+    fn move_merging_two_pairs_reports_both() {
+        let grid = board("2 2 4 4\n0 0 0 0\n0 0 0 0\n0 0 0 0");
+        let result = grid.check_with_merge_positions(Move::Left);
+        assert_eq!(
+            result.merges,
+            vec![(Position::new(0, 0), 4), (Position::new(1, 0), 8)]
+        );
+        assert_eq!(result.score_delta, 12);
+        assert_eq!(grid.simulate(Move::Left).last_merges, vec![4, 8]);
+    }
+
+    #[test]
+    fn board_string_round_trips() {
+        let rows = "2 4 0 0\n0 0 0 0\n0 0 2 0\n0 0 0 2048";
+        let grid = board(rows);
+        assert_eq!(grid.size, 4);
+        assert_eq!(grid.tiles.len(), 4);
+        assert!(board(&grid.to_string()).same_tiles(&grid));
+        assert_eq!(grid.to_string().trim_end(), rows);
+    }
+
+    #[test]
+    fn malformed_board_strings_are_rejected() {
+        assert!(Grid::from_string("2 4 0\n0 0\n0 0 0").is_err());
+        assert!(Grid::from_string("2 4 0 0 0\n0 0 0 0\n0 0 0 0\n0 0 0 0").is_err());
+        assert!(Grid::from_string("2 x\n0 0").is_err());
+        assert!(Grid::from_string("2 -4\n0 0").is_err());
+        assert!(Grid::from_string("2.5 0\n0 0").is_err());
+        assert!(Grid::from_string("").is_err());
+    }
+
+    #[test]
+    fn endless_game_turns_the_winning_tile_into_a_two() {
+        let mut grid = board("1024 1024 0 0\n0 0 0 0\n0 0 0 0\n0 0 0 0");
+        grid.mode = GameMode::Endless;
+        grid.spawn_enabled = false;
+        let simulated = grid.simulate(Move::Left);
+        assert_board(&simulated, "2 0 0 0\n0 0 0 0\n0 0 0 0\n0 0 0 0");
+        assert_eq!(simulated.conversions, 1);
+
+        play(&mut grid, Move::Left);
+        assert_board(&grid, "2 0 0 0\n0 0 0 0\n0 0 0 0\n0 0 0 0");
+        assert_eq!(grid.conversions, 1);
+        assert!(!grid.reached_target());
+    }
+
+    #[test]
+    fn puzzle_never_adds_tiles() {
+        let mut grid = Grid::puzzle("2 2 4 0\n0 4 0 2\n2 0 0 0\n0 0 8 8", 2048).unwrap();
+        let mut count = grid.tiles.len();
+        for mv in [
+            Move::Left,
+            Move::Up,
+            Move::Right,
+            Move::Down,
+            Move::Left,
+            Move::Left,
+        ] {
+            play(&mut grid, mv);
+            assert!(grid.tiles.len() <= count, "{:?}", mv);
+            count = grid.tiles.len();
+        }
+    }
+
+    #[test]
+    fn merge_tree_keeps_three_generations() {
+        let mut grid = board("2 2 4 8\n0 0 0 0\n0 0 0 0\n0 0 0 0");
+        grid.spawn_enabled = false;
+        for _ in 0..3 {
+            play(&mut grid, Move::Left);
+        }
+        let four = MergeNode {
+            value: 4,
+            children: vec![MergeNode::leaf(2), MergeNode::leaf(2)],
+        };
+        let eight = MergeNode {
+            value: 8,
+            children: vec![four, MergeNode::leaf(4)],
+        };
+        let tree = grid.trace_merge_tree(Position::new(0, 0)).unwrap();
+        assert_eq!(
+            tree,
+            MergeNode {
+                value: 16,
+                children: vec![eight, MergeNode::leaf(8)],
+            }
+        );
+        assert_eq!(tree.depth(), 3);
+        assert_eq!(grid.trace_merge_tree(Position::new(1, 0)), None);
+    }
+
+    #[test]
+    fn second_1024_takes_two_moves_to_reach_2048() {
+        let alone = board("1024 0 0 0\n0 0 0 0\n0 0 0 0\n0 0 0 0");
+        assert!(!alone.reachable_values(1).contains(&2048));
+        assert!(!alone.reachable_values(2).contains(&2048));
+
+        // the two tiles first have to be lined up by one move and merged by the next
+        let apart = board("1024 0 0 0\n0 0 0 0\n0 0 0 0\n0 0 0 1024");
+        assert!(!apart.reachable_values(1).contains(&2048));
+        assert!(apart.reachable_values(2).contains(&2048));
+    }
+
+    #[test]
+    fn merge_limit_stops_after_the_first_pairs() {
+        let mut grid = board("2 2 2 2\n0 0 0 0\n0 0 0 0\n0 0 0 0");
+        grid.merge_limit = Some(1);
+        assert_board(
+            &grid.simulate(Move::Left),
+            "4 2 2 0\n0 0 0 0\n0 0 0 0\n0 0 0 0",
+        );
+        grid.merge_limit = None;
+        assert_board(
+            &grid.simulate(Move::Left),
+            "4 4 0 0\n0 0 0 0\n0 0 0 0\n0 0 0 0",
+        );
+    }
+
+    #[test]
+    fn distance_to_merge_goes_around_the_other_tiles() {
+        let p = Position::new;
+        let adjacent = board("2 2 0 0\n0 0 0 0\n0 0 0 0\n0 0 0 0");
+        assert_eq!(
+            adjacent.manhattan_distance_to_merge(p(0, 0), p(1, 0)),
+            Some(0)
+        );
+
+        let apart = board("2 0 0 2\n0 0 0 0\n0 0 0 0\n0 0 0 0");
+        assert_eq!(apart.manhattan_distance_to_merge(p(0, 0), p(3, 0)), Some(2));
+
+        // the 4 in between makes the 2 take the row below
+        let detour = board("2 4 0 2\n0 0 0 0\n0 0 0 0\n0 0 0 0");
+        assert_eq!(
+            detour.manhattan_distance_to_merge(p(0, 0), p(3, 0)),
+            Some(4)
+        );
+
+        let walled = board("2 4 0 2\n4 0 0 0\n0 0 0 0\n0 0 0 0");
+        assert_eq!(walled.manhattan_distance_to_merge(p(0, 0), p(3, 0)), None);
+        assert_eq!(detour.manhattan_distance_to_merge(p(0, 0), p(1, 0)), None);
+    }
+
+    #[test]
+    fn centroid_is_weighted_by_the_tiles() {
+        assert_eq!(board("4 4\n4 4").centroid(), (0.5, 0.5));
+        assert_eq!(board("8 0 0\n0 0 0\n0 0 0").centroid(), (0.0, 0.0));
+        // a 4 weighs twice as much as a 2
+        assert_eq!(board("2 0 4\n0 0 0\n0 0 0").centroid(), (4.0 / 3.0, 0.0));
+        assert_eq!(Grid::empty(DEFAULT_TILE_WIDTH, 4).centroid(), (1.5, 1.5));
+
+        assert_eq!(board("64 2\n0 0").compactness_score(), -0.5);
+        assert!(board("64 0\n0 2").compactness_score() < board("64 2\n0 0").compactness_score());
+    }
+
+    #[test]
+    fn terminal_cells_map_back_to_their_squares() {
+        let mut grid = Grid::empty(DEFAULT_TILE_WIDTH, 4);
+        grid.mv(Coordinates::new(5, 3));
+        for pos in [(0, 0), (1, 2), (3, 3), (3, 0)].map(|(x, y)| Position::new(x, y)) {
+            let c = grid.get_coordinates_at(pos);
+            assert_eq!(grid.position_at_coordinate(c), Some(pos));
+            // the last cell of the tile is still on the square
+            let inside = Coordinates::new(c.x + grid.tile_width - 1, c.y + grid.tile_height - 1);
+            assert_eq!(grid.position_at_coordinate(inside), Some(pos));
+            // the margin right after it is a gap
+            let gap = Coordinates::new(c.x + grid.tile_width, c.y);
+            assert_eq!(grid.position_at_coordinate(gap), None);
+        }
+        assert_eq!(grid.position_at_coordinate(Coordinates::new(0, 0)), None);
+        assert_eq!(
+            grid.position_at_coordinate(Coordinates::new(500, 500)),
+            None
+        );
+    }
+
+    #[test]
+    fn viewport_shows_the_squares_that_fit() {
+        let mut small = Grid::empty(DEFAULT_TILE_WIDTH, 4);
+        small.fit_viewport(200, 60);
+        assert_eq!(small.visible_range(), (0..4, 0..4));
+        small.scroll(Move::Right);
+        assert_eq!(small.visible_range(), (0..4, 0..4));
+
+        // 8 cells a column and 4 a row with the margins
+        let mut huge = Grid::empty(DEFAULT_TILE_WIDTH, 16);
+        huge.fit_viewport(2 + 10 * 8, 1 + 10 * 4);
+        assert_eq!(huge.visible_range(), (0..10, 0..10));
+        huge.scroll(Move::Right);
+        huge.scroll(Move::Down);
+        assert_eq!(huge.visible_range(), (1..11, 1..11));
+        assert!(huge.is_visible(Position::new(10, 10)));
+        assert!(!huge.is_visible(Position::new(0, 5)));
+        for _ in 0..10 {
+            huge.scroll(Move::Right);
+        }
+        assert_eq!(huge.visible_range().0, 6..16);
+        assert_eq!(huge.visible_squares(1, 1), (1, 1));
+    }
+
+    #[test]
+    fn previewed_moves_land_where_the_move_puts_the_tiles() {
+        let grid = board("2 2 0 4\n0 4 4 0\n8 0 8 2\n2 0 0 2");
+        for mv in [Move::Left, Move::Right, Move::Up, Move::Down] {
+            let moves = grid.check(mv);
+            let previewed: HashSet<Position> = grid
+                .tiles
+                .keys()
+                .map(|pos| match moves.iter().find(|(from, _)| from == pos) {
+                    Some((_, to)) => *to,
+                    None => *pos,
+                })
+                .collect();
+            let moved: HashSet<Position> = grid.simulate(mv).tiles.keys().copied().collect();
+            assert_eq!(previewed, moved, "{:?}", mv);
+        }
+    }
+
+    #[test]
+    fn merge_count_grows_with_every_generation() {
+        let mut grid = board("2 2 4 8\n0 0 0 0\n0 0 0 0\n0 0 0 0");
+        grid.spawn_enabled = false;
+        for generation in 1..=3 {
+            play(&mut grid, Move::Left);
+            assert_eq!(grid.tiles[&Position::new(0, 0)].merge_count, generation);
+            assert_eq!(grid.deepest_merge_count(), generation);
+        }
+        // a tile merging with a younger one takes the count of the older
+        let mut uneven = board("4 4 0 0\n0 0 0 0\n0 0 0 0\n0 0 0 0");
+        uneven.spawn_enabled = false;
+        uneven
+            .get_tile_mut(Position::new(0, 0))
+            .unwrap()
+            .merge_count = 2;
+        play(&mut uneven, Move::Left);
+        assert_eq!(uneven.deepest_merge_count(), 3);
+    }
+
+    #[test]
+    fn largest_merge_of_a_move() {
+        let row = |values: &str| board(&format!("{}\n0 0 0 0\n0 0 0 0\n0 0 0 0", values));
+        assert_eq!(row("4 4 0 0").largest_merge_in_move(Move::Left), 8);
+        assert_eq!(row("4 4 4 4").largest_merge_in_move(Move::Left), 8);
+        assert_eq!(row("2 4 0 0").largest_merge_in_move(Move::Left), 0);
+        assert_eq!(row("2 2 8 8").largest_merge_in_move(Move::Left), 16);
+    }
+
+    #[test]
+    fn trailing_spawns_land_on_the_edge_the_move_left_behind() {
+        for seed in 0..20 {
+            let mut grid = board("2 0 0 0\n0 4 0 0\n0 0 8 0\n0 0 0 0");
+            grid.mode = GameMode::Trailing;
+            grid.seed = seed;
+            play(&mut grid, Move::Left);
+            let spawn = grid.last_spawn.expect("the move spawns a tile");
+            assert_eq!(spawn.x, 3, "seed {} spawned at {:?}", seed, spawn);
+            assert!(grid.spawn_squares().iter().all(|pos| pos.x == 3));
+        }
+    }
+
+    #[test]
+    fn dot_of_a_single_merge_has_the_four_and_both_twos() {
+        let mut grid = board("2 2 0 0\n0 0 0 0\n0 0 0 0\n0 0 0 0");
+        grid.spawn_enabled = false;
+        play(&mut grid, Move::Left);
+        let dot = grid.serialize_to_dot();
+        let nodes: Vec<&str> = dot
+            .lines()
+            .filter(|line| line.contains("[label="))
+            .collect();
+        let edges: Vec<&str> = dot.lines().filter(|line| line.contains("->")).collect();
+        assert_eq!(nodes.len(), 3, "{}", dot);
+        assert_eq!(edges.len(), 2, "{}", dot);
+        assert!(nodes[0].contains("label=\"4 at (0, 0)\""), "{}", dot);
+        assert!(
+            nodes[1..].iter().all(|node| node.contains("label=\"2\"")),
+            "{}",
+            dot
+        );
+        assert!(
+            dot.contains("n0 -> n1;") && dot.contains("n0 -> n2;"),
+            "{}",
+            dot
+        );
+    }
+
+    #[test]
+    fn replayed_seed_spawns_the_same_tiles_for_the_same_moves() {
+        let new_game = || Grid::new_with_seed(4, DEFAULT_TILE_WIDTH, Coordinates::new(0, 0), 2, 42);
+        let (mut first, mut replay) = (new_game(), new_game());
+        assert!(replay.same_tiles(&first), "{}", replay.to_ascii_string());
+        for mv in [
+            Move::Left,
+            Move::Up,
+            Move::Right,
+            Move::Down,
+            Move::Left,
+            Move::Up,
+        ] {
+            play(&mut first, mv);
+            play(&mut replay, mv);
+            assert_eq!(replay.last_spawn, first.last_spawn, "after {:?}", mv);
+            assert!(
+                replay.same_tiles(&first),
+                "after {:?}\n{}{}",
+                mv,
+                first.to_ascii_string(),
+                replay.to_ascii_string()
+            );
+        }
+    }
+
+    #[test]
+    fn border_squares_are_perimeter_and_the_middle_is_interior() {
+        let grid = board("2 2 2 2\n2 4 4 2\n2 4 4 2\n2 2 2 2");
+        let positions = |tiles: Vec<(Position, &Tile)>| -> Vec<(u16, u16)> {
+            tiles
+                .into_iter()
+                .map(|(pos, _)| (pos.x, pos.y))
+                .sorted()
+                .collect()
+        };
+        assert_eq!(
+            positions(grid.interior_tiles()),
+            vec![(1, 1), (1, 2), (2, 1), (2, 2)]
+        );
+        let perimeter = positions(grid.perimeter_tiles());
+        assert_eq!(perimeter.len(), 12);
+        for square in [
+            (0, 0),
+            (0, 3),
+            (3, 0),
+            (3, 3),
+            (1, 0),
+            (2, 0),
+            (0, 1),
+            (3, 2),
+        ] {
+            assert!(perimeter.contains(&square), "{:?}", square);
+        }
+        assert!(grid.interior_tiles().iter().all(|(_, tile)| tile.n == 4));
+    }
+
+    #[test]
+    fn last_spawn_follows_every_new_tile() {
+        let mut grid = Grid::new_with_seed(4, DEFAULT_TILE_WIDTH, Coordinates::new(0, 0), 0, 3);
+        assert_eq!(grid.last_spawn, None);
+        for _ in 0..16 {
+            let pos = grid.spawn_random_tile().unwrap();
+            assert_eq!(grid.last_spawn, Some(pos));
+        }
+        assert!(grid.spawn_random_tile().is_err());
+        assert!(grid.last_spawn.is_some());
+    }
+
+    #[test]
+    fn target_game_is_won_by_the_exact_tile_only() {
+        let mut grid = board("32 32 0 0\n0 0 0 0\n0 0 0 0\n0 0 0 0");
+        grid.mode = GameMode::Target;
+        grid.spawn_enabled = false;
+        grid.set_target(64).unwrap();
+        assert!(!grid.reached_target());
+        play(&mut grid, Move::Left);
+        assert!(grid.reached_target());
+
+        let mut past = board("128 0 0 0\n0 0 0 0\n0 0 0 0\n0 2 0 0");
+        past.mode = GameMode::Target;
+        past.set_target(64).unwrap();
+        assert!(past.is_winning_tile(64));
+        assert!(!past.is_winning_tile(128));
+        assert!(!past.reached_target());
+    }
+
+    #[test]
+    fn largest_potential_merge_of_a_row() {
+        let largest = |rows| board(rows).largest_potential_merge();
+        assert_eq!(largest("2 2 0 0\n0 0 0 0\n0 0 0 0\n0 0 0 0"), Some(4));
+        assert_eq!(largest("4 2 2 0\n0 0 0 0\n0 0 0 0\n0 0 0 0"), Some(4));
+        assert_eq!(largest("4 4 4 4\n0 0 0 0\n0 0 0 0\n0 0 0 0"), Some(8));
+        assert_eq!(largest("2 4 2 4\n4 2 4 2\n0 0 0 0\n0 0 0 0"), None);
+        assert_eq!(largest("8 0 0 0\n0 0 0 0\n8 0 0 0\n0 0 0 0"), Some(16));
+    }
+
+    #[test]
+    fn merges_are_counted_for_every_direction() {
+        let row = board("2 2 0 0\n0 0 0 0\n0 0 0 0\n0 0 0 0");
+        assert_eq!(row.count_merges_after(Move::Left), 1);
+        assert_eq!(row.count_merges_after(Move::Right), 1);
+        assert_eq!(row.merges_per_direction(), [0, 0, 1, 1]);
+
+        let column = board("2 0 0 0\n0 0 0 0\n2 0 0 0\n0 0 0 0");
+        assert_eq!(column.count_merges_after(Move::Up), 1);
+        assert_eq!(column.merges_per_direction(), [1, 1, 0, 0]);
+
+        let nothing = board("2 4 0 0\n0 0 0 0\n0 0 0 0\n0 0 0 0");
+        assert_eq!(nothing.count_merges_after(Move::Left), 0);
+        assert_eq!(nothing.merges_per_direction(), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn scrubbing_back_shows_the_board_of_earlier_moves() {
+        let mut grid = Grid::new_with_seed(4, DEFAULT_TILE_WIDTH, Coordinates::new(0, 0), 2, 11);
+        let mut seen = vec![(grid.to_matrix(), grid.score)];
+        for mv in [Move::Left, Move::Up, Move::Right, Move::Down, Move::Left] {
+            // a move that changes nothing leaves no snapshot behind
+            if grid.apply_move_scored(mv).is_some() {
+                play(&mut grid, mv);
+                seen.push((grid.to_matrix(), grid.score));
+            }
+        }
+        let moves = grid.history().len();
+        assert_eq!(moves, seen.len() - 1);
+        assert!(moves >= 3, "only {} moves changed the board", moves);
+        for back in 0..=moves {
+            let earlier = grid.board_at(moves - back);
+            assert_eq!(
+                (earlier.to_matrix(), earlier.score),
+                seen[moves - back],
+                "{} moves back",
+                back
+            );
+        }
+    }
+
+    #[test]
+    fn shortest_travel_is_animated_first() {
+        let grid = board("2 0 0 2\n0 0 0 0\n0 0 0 0\n0 0 0 0");
+        // the 2 on the left stays put and the other one travels the whole row onto it
+        assert_eq!(
+            grid.manhattan_gravity_sort(Move::Left),
+            vec![(Position::new(3, 0), Position::new(0, 0))]
+        );
+
+        let grid = board("0 4 0 2\n0 0 0 0\n0 0 0 0\n0 0 0 0");
+        assert_eq!(
+            grid.manhattan_gravity_sort(Move::Left),
+            vec![
+                (Position::new(1, 0), Position::new(0, 0)),
+                (Position::new(3, 0), Position::new(1, 0)),
+            ]
+        );
+
+        let grid = board("0 0 0 2\n0 2 0 0\n0 0 0 0\n0 0 0 0");
+        assert_eq!(
+            grid.manhattan_gravity_sort(Move::Left),
+            vec![
+                (Position::new(1, 1), Position::new(0, 1)),
+                (Position::new(3, 0), Position::new(0, 0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn tiles_are_counted_by_value() {
+        let grid = board("2 0 4 0\n0 2 0 0\n0 0 0 0\n0 0 0 2");
+        assert_eq!(
+            grid.tile_value_distribution(),
+            BTreeMap::from([(2, 3), (4, 1)])
+        );
+        assert_eq!(grid.dominant_value(), Some(2));
+
+        let empty = board("0 0 0 0\n0 0 0 0\n0 0 0 0\n0 0 0 0");
+        assert!(empty.tile_value_distribution().is_empty());
+        assert_eq!(empty.dominant_value(), None);
+
+        let full = board("8 8 8 8\n8 8 8 8\n8 8 8 8\n8 8 8 8");
+        assert_eq!(full.tile_value_distribution(), BTreeMap::from([(8, 16)]));
+    }
+
+    #[test]
+    fn double_merge_and_cascade_report_their_movements_and_merges() {
+        let animation = |from: (u16, u16), to: (u16, u16)| TileAnimation {
+            from: Position::new(from.0, from.1),
+            to: Position::new(to.0, to.1),
+        };
+
+        let double =
+            board("2 2 4 4\n0 0 0 0\n0 0 0 0\n0 0 0 0").check_with_merge_positions(Move::Left);
+        assert_eq!(
+            double.movements,
+            vec![
+                animation((1, 0), (0, 0)),
+                animation((2, 0), (1, 0)),
+                animation((3, 0), (1, 0)),
+            ]
+        );
+        assert_eq!(
+            double.merges,
+            vec![(Position::new(0, 0), 4), (Position::new(1, 0), 8)]
+        );
+        assert_eq!(double.score_delta, 12);
+
+        // the 4 made by the first merge doesn't merge again with the 4 next to it
+        let mut grid = board("2 2 4 8\n0 0 0 0\n0 0 0 0\n0 0 0 0");
+        let cascade = grid.check_with_merge_positions(Move::Left);
+        assert_eq!(
+            cascade.movements,
+            vec![
+                animation((1, 0), (0, 0)),
+                animation((2, 0), (1, 0)),
+                animation((3, 0), (2, 0)),
+            ]
+        );
+        assert_eq!(cascade.merges, vec![(Position::new(0, 0), 4)]);
+        assert_eq!(cascade.score_delta, 4);
+        // it does on the next move, and the 8 that makes on the one after
+        for n in [4, 8, 16] {
+            let result = grid.check_with_merge_positions(Move::Left);
+            assert_eq!(result.merges, vec![(Position::new(0, 0), n)]);
+            grid = grid.simulate(Move::Left);
+        }
+        assert_board(&grid, "16 0 0 0\n0 0 0 0\n0 0 0 0\n0 0 0 0");
+    }
+
+    /// board of the given size with a tile for every value that isn't 0, row after row
+    fn board_from_values(size: u16, values: &[u32]) -> Grid {
+        let tiles: Vec<(Position, u32)> = (0..size * size)
+            .zip(values)
+            .filter(|(_, n)| **n != 0)
+            .map(|(i, n)| (Position::new(i % size, i / size), *n))
+            .collect();
+        Grid::new_from_positions(DEFAULT_TILE_WIDTH, size, &tiles)
+    }
+
+    proptest! {
+        #[test]
+        fn rotating_back_and_forth_keeps_the_tiles(
+            size in 2u16..=MAX_SIZE,
+            values in vec(prop_oneof![Just(0u32), Just(2), Just(4), Just(8)], 256),
+        ) {
+            let grid = board_from_values(size, &values);
+            let mut rotated = grid.clone();
+            rotated.rotate_90_in_place(true);
+            rotated.rotate_90_in_place(false);
+            prop_assert_eq!(&rotated.tiles, &grid.tiles);
+            rotated.rotate_90_in_place(false);
+            rotated.rotate_90_in_place(true);
+            prop_assert_eq!(&rotated.tiles, &grid.tiles);
         }
 
-        return Ok(());
+        #[test]
+        fn rotating_k_then_4_minus_k_times_is_the_identity(
+            size in 2u16..=MAX_SIZE,
+            values in vec(prop_oneof![Just(0u32), Just(2), Just(4), Just(8)], 256),
+            k in 0u8..=4,
+        ) {
+            let grid = board_from_values(size, &values);
+            let rotated = grid.rotate_tiles_clockwise_n(k).rotate_tiles_clockwise_n(4 - k);
+            prop_assert_eq!(&rotated.tiles, &grid.tiles);
+        }
+
+        #[test]
+        fn flips_move_every_tile_to_its_flipped_square(
+            size in 2u16..=MAX_SIZE,
+            values in vec(prop_oneof![Just(0u32), Just(2), Just(4)], 256),
+        ) {
+            let grid = board_from_values(size, &values);
+            for flip in [Flip::Horizontal, Flip::Clock, Flip::CounterClock, Flip::HalfTurn] {
+                let expected: TileMap = grid
+                    .tiles
+                    .iter()
+                    .map(|(pos, tile)| {
+                        let tile = Tile {
+                            spawn_position: Grid::flipped_position(size, &flip, tile.spawn_position),
+                            ..*tile
+                        };
+                        (Grid::flipped_position(size, &flip, *pos), tile)
+                    })
+                    .collect();
+                let mut flipped = grid.clone();
+                flipped.flip(flip);
+                prop_assert_eq!(flipped.tiles, expected);
+            }
+        }
     }
 }
@@ -0,0 +1,536 @@
+use crate::{Settings, BOARD_PADDING, SIDE_PANEL_GAP, SIDE_PANEL_WIDTH, SPLIT_MIN_WIDTH};
+use rust2048::game::{Grid, TILE_SIZES};
+use std::fmt;
+use tui::layout::{Constraint, Direction, Layout, Rect};
+
+/// share of the terminal a dialog takes in both directions, never less than it needs
+const DIALOG_PERCENT: u16 = 60;
+/// smallest menu and settings dialogs that still fit their columns and a few lines of controls
+const MENU_SIZE: (u16, u16) = (74, 16);
+const SETTINGS_SIZE: (u16, u16) = (100, 30);
+
+/// rect in the middle of the area taking the given percentages of its width and height
+pub fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Percentage((100 - percent_y) / 2),
+                Constraint::Percentage(percent_y),
+                Constraint::Percentage((100 - percent_y) / 2),
+            ]
+            .as_ref(),
+        )
+        .split(area);
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(
+            [
+                Constraint::Percentage((100 - percent_x) / 2),
+                Constraint::Percentage(percent_x),
+                Constraint::Percentage((100 - percent_x) / 2),
+            ]
+            .as_ref(),
+        )
+        .split(rows[1])[1]
+}
+
+/// centered box for a dialog, at least `width` by `height` so the content fits
+/// but no bigger than the area, on small terminals it's the whole area
+pub fn dialog_rect(area: Rect, width: u16, height: u16) -> Rect {
+    let rect = centered_rect(DIALOG_PERCENT, DIALOG_PERCENT, area);
+    let width = rect.width.max(width).min(area.width);
+    let height = rect.height.max(height).min(area.height);
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+/// Something wrong with the rects of a layout, named after the methods returning them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LayoutError {
+    OutOfBounds(&'static str, Rect),
+    Overlap(&'static str, &'static str),
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LayoutError::OutOfBounds(name, rect) => {
+                write!(f, "{} {:?} is outside of the terminal", name, rect)
+            }
+            LayoutError::Overlap(a, b) => write!(f, "{} overlaps {}", a, b),
+        }
+    }
+}
+
+/// LayoutEngine computes where everything goes on the screen for the size of the
+/// terminal and the settings, so the renderers only draw into the rects they get
+#[derive(Debug, Clone, Copy)]
+pub struct LayoutEngine {
+    pub terminal: Rect,
+    pub settings: Settings,
+    /// columns and rows the board takes, see `Grid::width` and `Grid::height`
+    pub board: (u16, u16),
+    /// the statistics of the split layout are shown next to or under the game
+    pub split: bool,
+    /// columns the game is pushed to the right, the loss animation shakes the board with it
+    pub shake: u16,
+}
+
+impl LayoutEngine {
+    pub fn new(terminal: Rect, settings: Settings) -> Self {
+        Self {
+            terminal,
+            settings,
+            board: (0, 0),
+            split: false,
+            shake: 0,
+        }
+    }
+
+    pub fn with_board(self, width: u16, height: u16) -> Self {
+        Self {
+            board: (width, height),
+            ..self
+        }
+    }
+
+    fn split_panes(&self) -> Vec<Rect> {
+        Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
+            .split(self.terminal)
+    }
+
+    fn split_rows(&self) -> Vec<Rect> {
+        Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)].as_ref())
+            .split(self.terminal)
+    }
+
+    /// the split layout shows a whole pane of statistics only on wide terminals
+    fn wide_split(&self) -> bool {
+        self.split && self.terminal.width >= SPLIT_MIN_WIDTH
+    }
+
+    /// part of the terminal left to the game and everything around it
+    pub fn play_rect(&self) -> Rect {
+        let mut area = match (self.split, self.wide_split()) {
+            (false, _) => self.terminal,
+            (true, true) => self.split_panes()[0],
+            (true, false) => self.split_rows()[0],
+        };
+        let offset = std::cmp::min(self.shake, area.width);
+        area.x += offset;
+        area.width -= offset;
+        area
+    }
+
+    /// statistics pane of the split layout on a wide terminal
+    pub fn statistics_rect(&self) -> Option<Rect> {
+        match self.wide_split() {
+            true => Some(self.split_panes()[1]),
+            false => None,
+        }
+    }
+
+    /// single line of statistics the split layout falls back to on a narrow terminal
+    pub fn statistics_line_rect(&self) -> Option<Rect> {
+        match self.split && !self.wide_split() {
+            true => Some(self.split_rows()[1]),
+            false => None,
+        }
+    }
+
+    fn statistics_chunks(&self) -> Option<Vec<Rect>> {
+        self.statistics_rect().map(|rect| {
+            Layout::default()
+                .direction(Direction::Vertical)
+                .constraints(
+                    [
+                        Constraint::Length(8),
+                        Constraint::Length(10),
+                        Constraint::Min(0),
+                    ]
+                    .as_ref(),
+                )
+                .split(rect)
+        })
+    }
+
+    /// score after every move, at the top of the statistics pane
+    pub fn score_chart_rect(&self) -> Option<Rect> {
+        self.statistics_chunks().map(|chunks| chunks[0])
+    }
+
+    /// how many tiles of each value there are, under the score chart
+    pub fn tile_chart_rect(&self) -> Option<Rect> {
+        self.statistics_chunks().map(|chunks| chunks[1])
+    }
+
+    pub fn metrics_rect(&self) -> Option<Rect> {
+        self.statistics_chunks().map(|chunks| chunks[2])
+    }
+
+    /// widest of `TILE_SIZES` the whole board fits the play area with, a board too big
+    /// even for the smallest tiles is scrolled through instead as long as at least one
+    /// square fits, none when not even that does
+    pub fn tile_size(&self, game: &Grid) -> Option<u16> {
+        let area = self.play_rect();
+        let fitting = TILE_SIZES.into_iter().find(|size| {
+            let (width, height) = game.simulate_size(*size);
+            width <= area.width && height <= area.height
+        });
+        let smallest = TILE_SIZES[TILE_SIZES.len() - 1];
+        let (width, height) = (
+            area.width.saturating_sub(game.coordinates().x),
+            area.height.saturating_sub(game.coordinates().y),
+        );
+        let scrolled =
+            width >= 2 + smallest + game.margin_x && height >= 1 + smallest / 2 + game.margin_y;
+        fitting.or(match scrolled {
+            true => Some(smallest),
+            false => None,
+        })
+    }
+
+    /// give the board the tile size picked by `tile_size`, an error to show instead of
+    /// the board when the terminal is too small for it
+    pub fn fit_board(&self, game: &mut Grid) -> Result<(), String> {
+        match self.tile_size(game) {
+            Some(size) => {
+                game.change_tile_size(size);
+                Ok(())
+            }
+            None => Err("The size of your terminal is too small and can't fit the game! Try to make it larger.".to_string()),
+        }
+    }
+
+    /// room for the board inside the padding, the board is fitted into it
+    pub fn board_space(&self) -> (u16, u16) {
+        let area = self.play_rect();
+        (
+            area.width.saturating_sub(2 * BOARD_PADDING),
+            area.height.saturating_sub(2 * BOARD_PADDING),
+        )
+    }
+
+    /// the status bar only takes a line when the terminal has one to spare below the board
+    pub fn status_bar_rect(&self) -> Option<Rect> {
+        let area = self.play_rect();
+        if area.height <= self.board.1 + 2 * BOARD_PADDING {
+            return None;
+        }
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)].as_ref())
+            .split(area);
+        Some(rows[1])
+    }
+
+    /// the board and the side panel get their own columns so they can't overlap
+    fn game_columns(&self) -> Vec<Rect> {
+        let mut area = self.play_rect();
+        if let Some(status_bar) = self.status_bar_rect() {
+            area.height -= status_bar.height;
+        }
+        Layout::default()
+            .direction(Direction::Horizontal)
+            .margin(BOARD_PADDING)
+            .constraints(
+                [
+                    Constraint::Length(self.board.0),
+                    Constraint::Length(SIDE_PANEL_GAP),
+                    Constraint::Length(SIDE_PANEL_WIDTH),
+                    Constraint::Min(0),
+                ]
+                .as_ref(),
+            )
+            .split(area)
+    }
+
+    /// the board, its top left corner is where the grid is moved to
+    pub fn game_rect(&self) -> Rect {
+        let column = self.game_columns()[0];
+        Rect {
+            height: std::cmp::min(self.board.1, column.height),
+            ..column
+        }
+    }
+
+    /// panel next to the board with the score and the controls, none if it doesn't fit
+    pub fn sidebar_rect(&self) -> Option<Rect> {
+        let column = self.game_columns()[2];
+        let rect = Rect {
+            y: column.y + 1,
+            height: std::cmp::min(self.board.1, column.height).saturating_sub(1),
+            ..column
+        };
+        // to make sure the controls don't go outside of the terminal
+        // and cause errors they won't be rendered if they don't fit, the column
+        // always starts past the board so only its width can fall short
+        if rect.width < SIDE_PANEL_WIDTH {
+            return None;
+        }
+        Some(rect)
+    }
+
+    fn gauge_height(&self) -> u16 {
+        1 + self.settings.show_board_gauge as u16 + if self.settings.strategy_gauge { 2 } else { 0 }
+    }
+
+    fn sidebar_chunks(&self, hud_lines: u16) -> Option<Vec<Rect>> {
+        self.sidebar_rect().map(|rect| {
+            Layout::default()
+                .direction(Direction::Vertical)
+                .constraints(
+                    [
+                        Constraint::Length(hud_lines),
+                        Constraint::Length(self.gauge_height()),
+                        Constraint::Min(0),
+                    ]
+                    .as_ref(),
+                )
+                .split(rect)
+        })
+    }
+
+    /// top of the side panel with the score and the other lines of the hud
+    pub fn hud_rect(&self, hud_lines: u16) -> Option<Rect> {
+        self.sidebar_chunks(hud_lines).map(|chunks| chunks[0])
+    }
+
+    /// gauges under the hud, one line for each gauge the settings turn on
+    pub fn gauges_rect(&self, hud_lines: u16) -> Option<Rect> {
+        self.sidebar_chunks(hud_lines).map(|chunks| chunks[1])
+    }
+
+    /// whatever is left of the side panel under the gauges
+    pub fn controls_rect(&self, hud_lines: u16) -> Option<Rect> {
+        self.sidebar_chunks(hud_lines).map(|chunks| chunks[2])
+    }
+
+    fn menu_columns(&self, dialog: Rect, first: Constraint, second: Constraint) -> Vec<Rect> {
+        Layout::default()
+            .direction(Direction::Horizontal)
+            .margin(2)
+            .constraints([first, second].as_ref())
+            .split(dialog)
+    }
+
+    /// box of the menu with its border
+    pub fn menu_dialog_rect(&self) -> Rect {
+        dialog_rect(self.terminal, MENU_SIZE.0, MENU_SIZE.1)
+    }
+
+    pub fn menu_rect(&self) -> Rect {
+        let dialog = self.menu_dialog_rect();
+        self.menu_columns(dialog, Constraint::Length(20), Constraint::Length(50))[0]
+    }
+
+    pub fn menu_controls_rect(&self) -> Rect {
+        let dialog = self.menu_dialog_rect();
+        self.menu_columns(dialog, Constraint::Length(20), Constraint::Length(50))[1]
+    }
+
+    /// box of the settings with its border
+    pub fn settings_dialog_rect(&self) -> Rect {
+        dialog_rect(self.terminal, SETTINGS_SIZE.0, SETTINGS_SIZE.1)
+    }
+
+    /// list of the settings with their values
+    pub fn settings_rect(&self) -> Rect {
+        let dialog = self.settings_dialog_rect();
+        self.menu_columns(dialog, Constraint::Length(30), Constraint::Min(0))[0]
+    }
+
+    /// right of the settings, for the preview of the active one or the controls
+    pub fn settings_preview_rect(&self) -> Rect {
+        let dialog = self.settings_dialog_rect();
+        self.menu_columns(dialog, Constraint::Length(30), Constraint::Min(0))[1]
+    }
+
+    /// rects drawn next to each other on every screen, by name, the rects of one group
+    /// mustn't overlap, the side panel and the statistics pane are split into groups
+    /// of their own since their parts are drawn inside of them
+    fn screen_rects(&self) -> Vec<Vec<(&'static str, Rect)>> {
+        // the hud has at least the score and the best score
+        let hud_lines = 2;
+        let game = vec![
+            ("game_rect", Some(self.game_rect())),
+            ("sidebar_rect", self.sidebar_rect()),
+            ("status_bar_rect", self.status_bar_rect()),
+            ("statistics_rect", self.statistics_rect()),
+            ("statistics_line_rect", self.statistics_line_rect()),
+        ];
+        let sidebar = vec![
+            ("hud_rect", self.hud_rect(hud_lines)),
+            ("gauges_rect", self.gauges_rect(hud_lines)),
+            ("controls_rect", self.controls_rect(hud_lines)),
+        ];
+        let statistics = vec![
+            ("score_chart_rect", self.score_chart_rect()),
+            ("tile_chart_rect", self.tile_chart_rect()),
+            ("metrics_rect", self.metrics_rect()),
+        ];
+        let menu = vec![
+            ("menu_rect", Some(self.menu_rect())),
+            ("menu_controls_rect", Some(self.menu_controls_rect())),
+        ];
+        let settings = vec![
+            ("settings_rect", Some(self.settings_rect())),
+            ("settings_preview_rect", Some(self.settings_preview_rect())),
+        ];
+        [game, sidebar, statistics, menu, settings]
+            .into_iter()
+            .map(|rects| {
+                rects
+                    .into_iter()
+                    .filter_map(|(name, rect)| rect.map(|rect| (name, rect)))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// every rect of every screen that sticks out of the terminal or overlaps another
+    /// one of the same screen, empty rects take no room so they never overlap
+    pub fn validate(&self) -> Vec<LayoutError> {
+        let mut errors = vec![];
+        for rects in self.screen_rects() {
+            for (name, rect) in rects.iter() {
+                if self.terminal.union(*rect) != self.terminal {
+                    errors.push(LayoutError::OutOfBounds(name, *rect));
+                }
+            }
+            for (i, (a, rect_a)) in rects.iter().enumerate() {
+                for (b, rect_b) in rects[i + 1..].iter() {
+                    if rect_a.area() > 0 && rect_b.area() > 0 && rect_a.intersects(*rect_b) {
+                        errors.push(LayoutError::Overlap(a, b));
+                    }
+                }
+            }
+        }
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust2048::game::DEFAULT_TILE_WIDTH;
+
+    const SMALL: Rect = Rect {
+        x: 0,
+        y: 0,
+        width: 40,
+        height: 14,
+    };
+    const MEDIUM: Rect = Rect {
+        x: 0,
+        y: 0,
+        width: 80,
+        height: 24,
+    };
+    const LARGE: Rect = Rect {
+        x: 0,
+        y: 0,
+        width: 200,
+        height: 60,
+    };
+
+    fn layout(terminal: Rect, split: bool) -> LayoutEngine {
+        let game = Grid::new(DEFAULT_TILE_WIDTH, 4);
+        let mut layout = LayoutEngine::new(terminal, Settings::new());
+        layout.split = split;
+        let size = layout.tile_size(&game).unwrap_or(DEFAULT_TILE_WIDTH);
+        let (width, height) = game.simulate_size(size);
+        layout.with_board(width, height)
+    }
+
+    #[test]
+    fn every_screen_fits_small_medium_and_large_terminals() {
+        for terminal in [SMALL, MEDIUM, LARGE] {
+            for split in [false, true] {
+                let errors = layout(terminal, split).validate();
+                assert!(
+                    errors.is_empty(),
+                    "{:?} split {}: {:?}",
+                    terminal,
+                    split,
+                    errors
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn board_gets_smaller_tiles_on_smaller_terminals() {
+        let game = Grid::new(DEFAULT_TILE_WIDTH, 4);
+        let tile_size = |terminal| LayoutEngine::new(terminal, Settings::new()).tile_size(&game);
+        assert_eq!(tile_size(LARGE), Some(TILE_SIZES[0]));
+        assert_eq!(tile_size(MEDIUM), Some(TILE_SIZES[1]));
+        assert_eq!(tile_size(SMALL), Some(TILE_SIZES[1]));
+        assert_eq!(tile_size(Rect::new(0, 0, 5, 3)), None);
+    }
+
+    #[test]
+    fn side_panel_is_left_out_when_it_does_not_fit() {
+        assert!(layout(LARGE, false).sidebar_rect().is_some());
+        assert!(layout(SMALL, false).sidebar_rect().is_none());
+        assert!(layout(LARGE, true).statistics_rect().is_some());
+        assert!(layout(MEDIUM, true).statistics_line_rect().is_some());
+    }
+
+    #[test]
+    fn side_panel_starts_past_the_board_at_every_width() {
+        for width in [60, 80, 100, 120, 160, 200] {
+            let layout = layout(Rect::new(0, 0, width, 40), false);
+            let board = layout.game_rect();
+            if let Some(sidebar) = layout.sidebar_rect() {
+                assert!(
+                    sidebar.x >= board.right() + SIDE_PANEL_GAP,
+                    "width {}",
+                    width
+                );
+                assert!(sidebar.right() <= width, "width {}", width);
+                assert!(!sidebar.intersects(board), "width {}", width);
+            }
+        }
+        assert!(layout(Rect::new(0, 0, 200, 40), false)
+            .sidebar_rect()
+            .is_some());
+    }
+
+    #[test]
+    fn centered_rect_leaves_even_margins() {
+        let area = Rect::new(0, 0, 100, 40);
+        assert_eq!(centered_rect(50, 50, area), Rect::new(25, 10, 50, 20));
+        assert_eq!(centered_rect(100, 100, area), area);
+        assert_eq!(
+            centered_rect(60, 50, Rect::new(10, 4, 200, 60)),
+            Rect::new(50, 19, 120, 30)
+        );
+    }
+
+    #[test]
+    fn board_survives_a_terminal_too_small_for_it() {
+        let mut game = Grid::from_string("2 4 0 0\n0 8 0 0\n0 0 16 0\n2 0 0 0").unwrap();
+        game.score = 36;
+        let fits = |terminal, game: &mut Grid| {
+            LayoutEngine::new(terminal, Settings::new()).fit_board(game)
+        };
+        fits(MEDIUM, &mut game).unwrap();
+        let (tiles, score, tile_width) = (game.to_matrix(), game.score, game.tile_width);
+
+        assert!(fits(Rect::new(0, 0, 8, 4), &mut game).is_err());
+        fits(MEDIUM, &mut game).unwrap();
+        assert_eq!(game.to_matrix(), tiles);
+        assert_eq!(game.score, score);
+        assert_eq!(game.tile_width, tile_width);
+    }
+}
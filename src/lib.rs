@@ -0,0 +1,22 @@
+//! Game logic of rust2048 without the terminal, the board, its rules and the solver,
+//! so other frontends like a web page compiled to WebAssembly can drive the same game
+
+/// write a record to the debug log of the `logging` feature, without the feature
+/// the arguments are only type checked and never evaluated
+#[macro_export]
+macro_rules! debug_log {
+    ($level:ident, $($arg:tt)+) => {{
+        #[cfg(feature = "logging")]
+        log::$level!($($arg)+);
+        #[cfg(not(feature = "logging"))]
+        if false {
+            let _ = format_args!($($arg)+);
+        }
+    }};
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod draw;
+pub mod game;
+pub mod save;
+pub mod solver;
@@ -0,0 +1,159 @@
+use crate::game::{GameEvent, Move, Position};
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::time::Instant;
+
+/// Line of the log, a `GameEvent` with its time in milliseconds since the logger started
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum LoggedEvent {
+    Moved {
+        ms: u64,
+        direction: Move,
+        /// number of the move in its game, the first one is 1
+        move_count: u64,
+    },
+    Merged {
+        ms: u64,
+        from: Position,
+        to: Position,
+        value: u32,
+    },
+    Spawned {
+        ms: u64,
+        at: Position,
+        value: u32,
+    },
+    Won {
+        ms: u64,
+    },
+    Lost {
+        ms: u64,
+    },
+}
+
+/// EventLogger appends the events of the games played to a file, one json
+/// object per line, e.g. {"event":"spawned","ms":0,"at":{"x":1,"y":2},"value":2}
+pub struct EventLogger {
+    writer: BufWriter<File>,
+    start: Instant,
+    /// first event of the game being logged, when it changes a new game has started
+    first: Option<GameEvent>,
+    logged: usize,
+    /// moves of the game being logged so far
+    move_count: u64,
+}
+
+impl EventLogger {
+    pub fn new(path: &str) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            writer: BufWriter::new(file),
+            start: Instant::now(),
+            first: None,
+            logged: 0,
+            move_count: 0,
+        })
+    }
+
+    /// write the events added to the game since the last call
+    pub fn log_new(&mut self, events: &[GameEvent]) -> io::Result<()> {
+        if events.first() != self.first.as_ref() {
+            self.first = events.first().copied();
+            self.logged = 0;
+            self.move_count = 0;
+        }
+        if events.len() <= self.logged {
+            return Ok(());
+        }
+
+        for event in &events[self.logged..] {
+            if let GameEvent::Moved(..) = event {
+                self.move_count += 1;
+            }
+            let logged = self.to_logged(event);
+            serde_json::to_writer(&mut self.writer, &logged)?;
+            writeln!(self.writer)?;
+        }
+        self.logged = events.len();
+        self.writer.flush()
+    }
+
+    fn to_logged(&self, event: &GameEvent) -> LoggedEvent {
+        let ms = |at: &Instant| at.saturating_duration_since(self.start).as_millis() as u64;
+        match event {
+            GameEvent::Moved(direction, at) => LoggedEvent::Moved {
+                ms: ms(at),
+                direction: *direction,
+                move_count: self.move_count,
+            },
+            GameEvent::Merged {
+                from,
+                to,
+                value,
+                at,
+            } => LoggedEvent::Merged {
+                ms: ms(at),
+                from: *from,
+                to: *to,
+                value: *value,
+            },
+            GameEvent::Spawned { at, value, instant } => LoggedEvent::Spawned {
+                ms: ms(instant),
+                at: *at,
+                value: *value,
+            },
+            GameEvent::Won(at) => LoggedEvent::Won { ms: ms(at) },
+            GameEvent::Lost(at) => LoggedEvent::Lost { ms: ms(at) },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Grid;
+    use serde_json::Value;
+
+    /// play the moves one after the other, every one until its tiles settle
+    fn play(grid: &mut Grid, moves: &[Move]) {
+        for mv in moves {
+            let _ = grid.on_tick(Some(*mv));
+            while !grid.moving_tiles.is_empty() {
+                let _ = grid.on_tick(None);
+            }
+        }
+    }
+
+    #[test]
+    fn scripted_game_is_logged_event_by_event() {
+        let path = std::env::temp_dir().join(format!("rust2048-log-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut logger = EventLogger::new(path.to_str().unwrap()).unwrap();
+
+        let mut grid = Grid::puzzle("2 2 0 0\n0 0 0 0\n4 0 0 0\n0 0 0 0", 8).unwrap();
+        play(&mut grid, &[Move::Left]);
+        logger.log_new(&grid.event_log).unwrap();
+        play(&mut grid, &[Move::Down, Move::Down]);
+        logger.log_new(&grid.event_log).unwrap();
+
+        let lines: Vec<Value> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        std::fs::remove_file(&path).unwrap();
+        let events: Vec<&str> = lines
+            .iter()
+            .map(|line| line["event"].as_str().unwrap())
+            .collect();
+        assert_eq!(events, ["moved", "merged", "moved", "merged", "won"]);
+        assert_eq!(lines[0]["direction"], "Left");
+        assert_eq!(lines[0]["move_count"], 1);
+        assert_eq!(lines[1]["value"], 4);
+        assert_eq!(lines[1]["to"], serde_json::json!({"x": 0, "y": 0}));
+        assert_eq!(lines[2]["move_count"], 2);
+        assert_eq!(lines[3]["value"], 8);
+    }
+}
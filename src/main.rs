@@ -1,19 +1,32 @@
 #![allow(unused_imports)]
 #![allow(dead_code)]
-mod draw;
-mod game;
+
+#[macro_use]
+extern crate rust2048;
+
+mod achievements;
+mod audio;
+mod layout;
+mod logger;
+mod profiler;
+mod stats;
 
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event as CEvent, KeyCode},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event as CEvent, KeyCode, KeyEvent,
+        KeyModifiers,
+    },
     execute, terminal,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use std::cell::RefCell;
-use std::sync::mpsc::channel;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
 use std::thread;
 use std::{
     error::Error,
-    io,
+    io::{self, BufRead, Write},
     time::{Duration, Instant},
 };
 use tui::{
@@ -24,29 +37,212 @@ use tui::{
     text::{Span, Spans},
     widgets::{
         canvas::{Canvas, Label, Line, Map, MapResolution, Rectangle},
-        Block, BorderType, Borders, Cell, LineGauge, Paragraph, Row, Table, Wrap,
+        BarChart, Block, BorderType, Borders, Cell, Clear, LineGauge, Paragraph, Row, Sparkline,
+        Table, Wrap,
     },
     Frame, Terminal,
 };
 
-use draw::{draw_number, draw_shape, get_bg_color_for_n, get_color_for_n, Direction};
-use game::{Coordinates, Grid, Move, Position, Tile};
+use rust2048::{draw, game, save, solver};
+
+use achievements::Achievements;
+use audio::MergeSounds;
+use draw::{
+    abbreviate, draw_number, draw_shape, get_bg_color_for_n, get_color_for_n, Direction,
+    HighContrast, LossAnimation,
+};
+use game::{
+    Coordinates, GameEvent, GameMode, GameVariant, Grid, MergePreference, Move, Position, Tile,
+    TileSpacing, DEFAULT_TILE_WIDTH,
+};
+use itertools::Itertools;
+use layout::{dialog_rect, LayoutEngine};
+use logger::EventLogger;
+use profiler::Profiler;
+use save::SavedGame;
+use solver::{EvalWeights, SolverConfig};
+use stats::Stats;
 use std::fmt;
+use std::path::{Path, PathBuf};
 use std::rc::{Rc, Weak};
 use std::sync::{Arc, Mutex, RwLock};
 
+/// milliseconds between the ticks at the slowest animation speed, the faster
+/// speeds divide it, lower is smoother but redraws more often
 const BASE_TICK_RATE: u64 = 40;
+/// the base tick rates the settings cycle through, from the smoothest to the
+/// lightest on a slow connection
+const BASE_TICK_RATES: [u64; 5] = [10, 20, 40, 80, 160];
+/// number of ticks it takes a freshly spawned tile to grow to its full size
+const SPAWN_ANIMATION_TICKS: u64 = 3;
+/// number of ticks the tile that spawned last stays outlined, fading for the second half
+const NEW_TILE_HIGHLIGHT_TICKS: u64 = 8;
+/// number of ticks the lives of a hardcore game flash for after losing one
+const LIFE_LOST_FLASH_TICKS: u64 = 10;
+/// ticks the loss animation plays before the info screen, they get
+/// longer or shorter together with the animation speed
+const LOSS_ANIMATION_TICKS: u16 = 12;
+/// how long the menu has to sit idle before the demo game starts
+const IDLE_DEMO_AFTER: Duration = Duration::from_secs(30);
+/// empty space between the edges of the terminal and the board
+const BOARD_PADDING: u16 = 1;
+/// columns between the board and the side panel and the width of the panel
+const SIDE_PANEL_GAP: u16 = 5;
+const SIDE_PANEL_WIDTH: u16 = 25;
+/// terminals don't report releasing a key, so a key counts as held for as long
+/// as its repeated presses keep coming in
+const HOLD_TIMEOUT: Duration = Duration::from_millis(600);
+/// narrowest terminal the split layout shows its statistics pane on,
+/// narrower ones get a single line of statistics under the game
+const SPLIT_MIN_WIDTH: u16 = 120;
+/// games played out from the board to measure how solvable it is
+const SOLVABILITY_ITERATIONS: u32 = 100;
+/// the solvability is measured again only after this many moves
+const SOLVABILITY_CACHE_MOVES: usize = 10;
+/// ticks a tip stays on the game screen before the next one replaces it
+const TIP_TICKS: u64 = 30;
+/// tips the game screen rotates through under the controls
+const TIPS: [&str; 5] = [
+    "Keep your highest tile in a corner",
+    "Fill the row of your highest tile so it can't slide away",
+    "Avoid the move that pulls your highest tile out of its corner",
+    "Merge the small tiles early before they scatter",
+    "Build chains of tiles that each merge into the next",
+];
+/// how long the newly unlocked achievements stay on the screen
+const TOAST_DURATION: Duration = Duration::from_secs(3);
+/// file in the data dir the finished games are kept in between the sessions
+const STATS_FILE: &str = "stats.json";
+/// file in the data dir the statistics screen exports the finished games to
+const STATS_CSV_FILE: &str = "stats.csv";
+/// file in the data dir the unlocked achievements are kept in between the sessions
+const ACHIEVEMENTS_FILE: &str = "achievements.json";
+/// file in the data dir the game is saved to every few moves when the autosave is on,
+/// `--resume` without a file loads it
+const AUTOSAVE_FILE: &str = "autosave.json";
+/// empty file in the data dir left behind once the tutorial is finished or skipped,
+/// the tutorial only plays on the first launch when it isn't there yet
+const TUTORIAL_DONE_FILE: &str = "tutorial_done";
+
+/// path of `file` in the directory the game keeps its data in, `RUST2048_DATA_DIR`
+/// or else `rust2048` in the data dir of the user, e.g. `~/.local/share/rust2048`,
+/// and the current directory when there's neither
+fn data_path(file: &str) -> PathBuf {
+    let dir = match env_setting::<PathBuf>("DATA_DIR") {
+        Some(dir) => dir,
+        None => dirs::data_dir()
+            .map(|dir| dir.join("rust2048"))
+            .unwrap_or_default(),
+    };
+    dir.join(file)
+}
+
+/// time between two ticks, the faster the animation speed the shorter it is,
+/// both are kept within the range the settings offer
+fn tick_rate(animation_speed: u16, base_tick_rate: u64) -> Duration {
+    let speed = animation_speed.clamp(1, 3);
+    let base = base_tick_rate.clamp(
+        BASE_TICK_RATES[0],
+        BASE_TICK_RATES[BASE_TICK_RATES.len() - 1],
+    );
+    Duration::from_millis((4 - speed) as u64 * base)
+}
 
 enum Event<I> {
     Input(I),
     Tick,
 }
 
+/// thread listening to the input of the user and sending it through mpsc to the
+/// rendering thread, if there is no input it sends the tick message. It's told to
+/// stop and joined when dropped so it doesn't keep reading the input after the game
+struct InputThread {
+    running: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl InputThread {
+    fn spawn(settings: Arc<RwLock<Settings>>, tx: Sender<Event<KeyEvent>>) -> Self {
+        let running = Arc::new(AtomicBool::new(true));
+        let running_clone = running.clone();
+        let handle = thread::spawn(move || {
+            let mut last_tick = Instant::now();
+            // polling never waits longer than a tick so the flag is checked at least once a tick
+            while running_clone.load(Ordering::Relaxed) {
+                let tick_rate = {
+                    let settings = settings.read().unwrap();
+                    tick_rate(settings.animation_speed, settings.base_tick_rate)
+                };
+                let timeout = tick_rate
+                    .checked_sub(last_tick.elapsed())
+                    .unwrap_or_else(|| Duration::from_secs(0));
+
+                if event::poll(timeout).expect("poll works") {
+                    if let CEvent::Key(key) = event::read().expect("can read events") {
+                        if tx.send(Event::Input(key)).is_err() {
+                            // the receiving end is gone, nobody is listening anymore
+                            break;
+                        }
+                    }
+                }
+
+                if last_tick.elapsed() >= tick_rate {
+                    if tx.send(Event::Tick).is_err() {
+                        break;
+                    }
+                    last_tick = Instant::now();
+                }
+            }
+        });
+        Self {
+            running,
+            handle: Some(handle),
+        }
+    }
+
+    fn stop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for InputThread {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
 #[repr(u16)]
 #[derive(Clone, Copy, Debug)]
 pub enum SettingsItem {
     GameSize = 1,
     AnimationSpeed = 2,
+    Hints = 3,
+    MergeLinger = 4,
+    TileSpacing = 5,
+    GameMode = 6,
+    BlitzTime = 7,
+    ShowBoardGauge = 8,
+    SpawnAnimation = 9,
+    GhostTiles = 10,
+    IdleDemo = 11,
+    Solver = 12,
+    MergeFactor = 13,
+    MergePreference = 14,
+    HighContrast = 15,
+    StrokeThickness = 16,
+    MergeLimit = 17,
+    CursorInput = 18,
+    LossAnimation = 19,
+    TickRate = 20,
+    StrategyGauge = 21,
+    NewTileHighlight = 22,
+    GameVariant = 23,
+    TargetValue = 24,
+    AutosaveInterval = 25,
+    MergeSounds = 26,
 }
 
 impl fmt::Display for SettingsItem {
@@ -57,9 +253,36 @@ impl fmt::Display for SettingsItem {
 
 impl From<u16> for SettingsItem {
     fn from(n: u16) -> Self {
+        // the items are numbered from 1, 0 and anything past the last item wrap
+        // around so moving up from the first item selects the last one and back
         match n {
-            0 => SettingsItem::AnimationSpeed,
+            0 => SettingsItem::MergeSounds,
+            1 => SettingsItem::GameSize,
             2 => SettingsItem::AnimationSpeed,
+            3 => SettingsItem::Hints,
+            4 => SettingsItem::MergeLinger,
+            5 => SettingsItem::TileSpacing,
+            6 => SettingsItem::GameMode,
+            7 => SettingsItem::BlitzTime,
+            8 => SettingsItem::ShowBoardGauge,
+            9 => SettingsItem::SpawnAnimation,
+            10 => SettingsItem::GhostTiles,
+            11 => SettingsItem::IdleDemo,
+            12 => SettingsItem::Solver,
+            13 => SettingsItem::MergeFactor,
+            14 => SettingsItem::MergePreference,
+            15 => SettingsItem::HighContrast,
+            16 => SettingsItem::StrokeThickness,
+            17 => SettingsItem::MergeLimit,
+            18 => SettingsItem::CursorInput,
+            19 => SettingsItem::LossAnimation,
+            20 => SettingsItem::TickRate,
+            21 => SettingsItem::StrategyGauge,
+            22 => SettingsItem::NewTileHighlight,
+            23 => SettingsItem::GameVariant,
+            24 => SettingsItem::TargetValue,
+            25 => SettingsItem::AutosaveInterval,
+            26 => SettingsItem::MergeSounds,
             _ => SettingsItem::GameSize,
         }
     }
@@ -69,6 +292,51 @@ impl From<u16> for SettingsItem {
 pub struct Settings {
     game_size: u16,
     animation_speed: u16,
+    hints: bool,
+    merge_linger: u16,
+    tile_margin_x: u16,
+    tile_margin_y: u16,
+    game_mode: GameMode,
+    /// length of a blitz game in seconds
+    blitz_time: u64,
+    show_board_gauge: bool,
+    spawn_animation: bool,
+    /// preview where the tiles would slide while shift and a direction are held
+    show_ghost_tiles: bool,
+    /// let the solver play a demo game behind the menu after it sits idle
+    idle_demo: bool,
+    solver: SolverConfig,
+    /// how many times bigger merged tiles get, 3 is the harder variant
+    merge_factor: u32,
+    merge_preference: MergePreference,
+    /// draw the board as large text instead of the canvas, for screen magnifiers
+    high_contrast: HighContrast,
+    /// width of the strokes of the numbers on the tiles, thicker reads better on big tiles
+    tile_stroke_thickness: f64,
+    /// most merges a move can make, None for no limit
+    merge_limit: Option<u32>,
+    /// the arrows move a cursor over the board and space swipes the way it last went,
+    /// for kiosks where swiping with the arrows directly isn't wanted
+    cursor_input: bool,
+    /// played on the board for a moment when the game is lost
+    loss_animation: LossAnimation,
+    /// milliseconds between the ticks at the slowest animation speed, see `tick_rate`
+    base_tick_rate: u64,
+    /// gauge of how healthy the board is by the heuristic of the solver, for learning players
+    strategy_gauge: bool,
+    /// outline the tile that spawned last for a few ticks
+    new_tile_highlight: bool,
+    /// rules the tiles merge by, picked when a new game starts
+    game_variant: GameVariant,
+    /// tile a target game has to make exactly
+    target_value: u32,
+    /// tile winning the other modes instead of the usual one, only `RUST2048_WIN_TARGET`
+    /// sets it
+    win_target: Option<u32>,
+    /// moves between the saves of the game, 0 turns the autosave off
+    autosave_interval: u64,
+    /// a tone for every merge, higher for bigger tiles, needs the audio feature
+    merge_sounds: bool,
     active_item: SettingsItem,
 }
 
@@ -77,36 +345,508 @@ impl Settings {
         Self {
             game_size: 4,
             animation_speed: 3,
+            hints: false,
+            merge_linger: 1,
+            tile_margin_x: game::MARGINX,
+            tile_margin_y: game::MARGINY,
+            game_mode: GameMode::Classic,
+            blitz_time: 60,
+            show_board_gauge: true,
+            spawn_animation: true,
+            show_ghost_tiles: false,
+            idle_demo: false,
+            solver: SolverConfig::default(),
+            merge_factor: 2,
+            merge_preference: MergePreference::TowardMove,
+            high_contrast: HighContrast::Off,
+            tile_stroke_thickness: 1.0,
+            merge_limit: None,
+            cursor_input: false,
+            loss_animation: LossAnimation::Shake,
+            base_tick_rate: BASE_TICK_RATE,
+            strategy_gauge: false,
+            new_tile_highlight: true,
+            game_variant: GameVariant::Standard,
+            target_value: 512,
+            win_target: None,
+            autosave_interval: 0,
+            merge_sounds: false,
             active_item: SettingsItem::GameSize,
         }
     }
 
+    /// default settings with the `RUST2048_` environment variables applied on top,
+    /// e.g. `RUST2048_GAME_SIZE=6` or `RUST2048_GAME_MODE=blitz`, a variable that
+    /// doesn't parse is ignored and one out of range is clamped to it
+    fn load() -> Self {
+        let mut settings = Self::new();
+        if let Some(size) = env_setting::<u16>("GAME_SIZE") {
+            settings.game_size = size.clamp(4, 16);
+        }
+        if let Some(speed) = env_setting::<u16>("ANIMATION_SPEED") {
+            settings.animation_speed = speed.clamp(1, 3);
+        }
+        if let Some(hints) = env_flag("HINTS") {
+            settings.hints = hints;
+        }
+        if let Some(linger) = env_setting::<u16>("MERGE_LINGER") {
+            settings.merge_linger = std::cmp::min(linger, 3);
+        }
+        if let Some(spacing) = env_variant("TILE_SPACING", TileSpacing::Normal, TileSpacing::next) {
+            (settings.tile_margin_x, settings.tile_margin_y) = spacing.margins();
+        }
+        if let Some(mode) = env_variant("GAME_MODE", GameMode::Classic, GameMode::next) {
+            settings.game_mode = mode;
+        }
+        if let Some(seconds) = env_setting::<u64>("BLITZ_TIME") {
+            settings.blitz_time = std::cmp::max(seconds, 1);
+        }
+        if let Some(gauge) = env_flag("BOARD_GAUGE") {
+            settings.show_board_gauge = gauge;
+        }
+        if let Some(animation) = env_flag("SPAWN_ANIMATION") {
+            settings.spawn_animation = animation;
+        }
+        if let Some(ghost) = env_flag("GHOST_TILES") {
+            settings.show_ghost_tiles = ghost;
+        }
+        if let Some(demo) = env_flag("IDLE_DEMO") {
+            settings.idle_demo = demo;
+        }
+        if let Some(depth) = env_setting::<u8>("SOLVER_DEPTH") {
+            settings.solver.depth = depth.clamp(1, 4);
+        }
+        if let Some(factor) = env_setting::<u32>("MERGE_FACTOR") {
+            settings.merge_factor = factor.clamp(2, 3);
+        }
+        let preference = env_variant(
+            "MERGE_PREFERENCE",
+            MergePreference::TowardMove,
+            MergePreference::next,
+        );
+        if let Some(preference) = preference {
+            settings.merge_preference = preference;
+        }
+        if let Some(contrast) = env_variant("HIGH_CONTRAST", HighContrast::Off, HighContrast::next)
+        {
+            settings.high_contrast = contrast;
+        }
+        if let Some(thickness) = env_setting::<f64>("STROKE_THICKNESS") {
+            settings.tile_stroke_thickness = thickness.clamp(1.0, 3.0);
+        }
+        match env_setting::<String>("MERGE_LIMIT") {
+            Some(limit) if limit.eq_ignore_ascii_case("off") => settings.merge_limit = None,
+            Some(limit) => {
+                if let Ok(limit) = limit.parse::<u32>() {
+                    settings.merge_limit = Some(std::cmp::max(limit, 1));
+                }
+            }
+            None => (),
+        }
+        if let Some(cursor) = env_flag("CURSOR_INPUT") {
+            settings.cursor_input = cursor;
+        }
+        let animation = env_variant("LOSS_ANIMATION", LossAnimation::Off, LossAnimation::next);
+        if let Some(animation) = animation {
+            settings.loss_animation = animation;
+        }
+        if let Some(rate) = env_setting::<u64>("TICK_RATE") {
+            settings.base_tick_rate = rate.clamp(
+                BASE_TICK_RATES[0],
+                BASE_TICK_RATES[BASE_TICK_RATES.len() - 1],
+            );
+        }
+        if let Some(highlight) = env_flag("NEW_TILE_HIGHLIGHT") {
+            settings.new_tile_highlight = highlight;
+        }
+        if let Some(target) = env_setting::<u32>("TARGET_VALUE") {
+            settings.target_value = std::cmp::max(target, 2);
+        }
+        if let Some(target) = env_setting::<u32>("WIN_TARGET") {
+            settings.win_target = Some(std::cmp::max(target, 2));
+        }
+        if let Some(sounds) = env_flag("MERGE_SOUNDS") {
+            settings.merge_sounds = sounds;
+        }
+        if let Some(interval) = env_setting::<u64>("AUTOSAVE_INTERVAL") {
+            settings.autosave_interval = interval;
+        }
+        let variant = env_variant("GAME_VARIANT", GameVariant::Standard, GameVariant::next);
+        if let Some(variant) = variant {
+            settings.game_variant = variant;
+        }
+        settings.round_target_value();
+        settings
+    }
+
+    /// round the targets up to a tile the variant and merge factor can make, a target
+    /// past every tile falls back to the default one
+    fn round_target_value(&mut self) {
+        let rule = self.game_variant.rule(self.merge_factor);
+        self.target_value = match rule.next_tile_value(self.target_value) {
+            Some(n) => n,
+            None => rule
+                .next_tile_value(Settings::new().target_value)
+                .unwrap_or(2),
+        };
+        self.win_target = self.win_target.and_then(|n| rule.next_tile_value(n));
+    }
+
+    /// the settings the `RUST2048_` environment variables changed from the defaults,
+    /// e.g. "GameSize is 6"
+    fn env_overrides(&self) -> Vec<String> {
+        let defaults = Self::new();
+        let mut overrides: Vec<String> = (1..=SettingsItem::MergeSounds as u16)
+            .map(SettingsItem::from)
+            .filter(|item| self.get_value(*item) != defaults.get_value(*item))
+            .map(|item| format!("{} is {}", item, self.get_value(item)))
+            .collect();
+        if let Some(target) = self.win_target {
+            overrides.push(format!("WinTarget is {}", target));
+        }
+        overrides
+    }
+
     fn update_settings(&mut self, item: SettingsItem) {
         match item {
             SettingsItem::GameSize => {
-                self.game_size = std::cmp::max((self.game_size + 1) % 9, 4);
+                // boards past 8 are experimental and usually have to be scrolled
+                self.game_size = std::cmp::max((self.game_size + 1) % 17, 4);
             }
             SettingsItem::AnimationSpeed => {
                 self.animation_speed = std::cmp::max((self.animation_speed + 1) % 4, 1);
             }
+            SettingsItem::Hints => {
+                self.hints = !self.hints;
+            }
+            SettingsItem::MergeLinger => {
+                self.merge_linger = (self.merge_linger + 1) % 4;
+            }
+            SettingsItem::TileSpacing => {
+                let margins = (self.tile_margin_x, self.tile_margin_y);
+                let spacing = match TileSpacing::from_margins(margins) {
+                    Some(spacing) => spacing.next(),
+                    None => TileSpacing::Normal,
+                };
+                (self.tile_margin_x, self.tile_margin_y) = spacing.margins();
+            }
+            SettingsItem::GameMode => {
+                self.game_mode = self.game_mode.next();
+            }
+            SettingsItem::BlitzTime => {
+                self.blitz_time = match self.blitz_time {
+                    30 => 60,
+                    60 => 120,
+                    120 => 180,
+                    _ => 30,
+                };
+            }
+            SettingsItem::ShowBoardGauge => {
+                self.show_board_gauge = !self.show_board_gauge;
+            }
+            SettingsItem::SpawnAnimation => {
+                self.spawn_animation = !self.spawn_animation;
+            }
+            SettingsItem::GhostTiles => {
+                self.show_ghost_tiles = !self.show_ghost_tiles;
+            }
+            SettingsItem::IdleDemo => {
+                self.idle_demo = !self.idle_demo;
+            }
+            // the solver has a screen of its own
+            SettingsItem::Solver => (),
+            SettingsItem::MergeFactor => {
+                self.merge_factor = if self.merge_factor == 2 { 3 } else { 2 };
+                self.round_target_value();
+            }
+            SettingsItem::MergePreference => {
+                self.merge_preference = self.merge_preference.next();
+            }
+            SettingsItem::HighContrast => {
+                self.high_contrast = self.high_contrast.next();
+            }
+            SettingsItem::StrokeThickness => {
+                self.tile_stroke_thickness = if self.tile_stroke_thickness >= 3.0 {
+                    1.0
+                } else {
+                    self.tile_stroke_thickness + 0.5
+                };
+            }
+            SettingsItem::MergeLimit => {
+                self.merge_limit = match self.merge_limit {
+                    None => Some(1),
+                    Some(n) if n < 3 => Some(n + 1),
+                    Some(_) => None,
+                };
+            }
+            SettingsItem::CursorInput => {
+                self.cursor_input = !self.cursor_input;
+            }
+            SettingsItem::LossAnimation => {
+                self.loss_animation = self.loss_animation.next();
+            }
+            SettingsItem::TickRate => {
+                let next = BASE_TICK_RATES
+                    .iter()
+                    .position(|rate| *rate == self.base_tick_rate)
+                    .map_or(0, |i| (i + 1) % BASE_TICK_RATES.len());
+                self.base_tick_rate = BASE_TICK_RATES[next];
+            }
+            SettingsItem::StrategyGauge => {
+                self.strategy_gauge = !self.strategy_gauge;
+            }
+            SettingsItem::NewTileHighlight => {
+                self.new_tile_highlight = !self.new_tile_highlight;
+            }
+            SettingsItem::GameVariant => {
+                self.game_variant = self.game_variant.next();
+                self.round_target_value();
+            }
+            SettingsItem::TargetValue => {
+                self.target_value = match self.target_value {
+                    n if n >= 4096 => 64,
+                    n => n * 2,
+                };
+                self.round_target_value();
+            }
+            SettingsItem::AutosaveInterval => {
+                self.autosave_interval = match self.autosave_interval {
+                    0 => 10,
+                    10 => 25,
+                    25 => 50,
+                    50 => 100,
+                    _ => 0,
+                };
+            }
+            SettingsItem::MergeSounds => {
+                self.merge_sounds = !self.merge_sounds;
+            }
         }
     }
 
-    fn get_value(&self, item: SettingsItem) -> u16 {
+    fn get_value(&self, item: SettingsItem) -> String {
         match item {
-            SettingsItem::GameSize => self.game_size,
-            SettingsItem::AnimationSpeed => self.animation_speed,
+            SettingsItem::GameSize => self.game_size.to_string(),
+            SettingsItem::AnimationSpeed => self.animation_speed.to_string(),
+            SettingsItem::Hints => on_off(self.hints),
+            SettingsItem::MergeLinger => self.merge_linger.to_string(),
+            SettingsItem::TileSpacing => {
+                match TileSpacing::from_margins((self.tile_margin_x, self.tile_margin_y)) {
+                    Some(spacing) => format!("{:?}", spacing),
+                    None => "Custom".to_string(),
+                }
+            }
+            SettingsItem::GameMode => format!("{:?}", self.game_mode),
+            SettingsItem::BlitzTime => format!("{}s", self.blitz_time),
+            SettingsItem::ShowBoardGauge => on_off(self.show_board_gauge),
+            SettingsItem::SpawnAnimation => on_off(self.spawn_animation),
+            SettingsItem::GhostTiles => on_off(self.show_ghost_tiles),
+            SettingsItem::IdleDemo => on_off(self.idle_demo),
+            SettingsItem::Solver => format!("Depth {}", self.solver.depth),
+            SettingsItem::MergeFactor => format!("x{}", self.merge_factor),
+            SettingsItem::MergePreference => format!("{:?}", self.merge_preference),
+            SettingsItem::HighContrast => format!("{:?}", self.high_contrast),
+            SettingsItem::StrokeThickness => format!("{:.1}", self.tile_stroke_thickness),
+            SettingsItem::MergeLimit => match self.merge_limit {
+                Some(n) => format!("{} per move", n),
+                None => "Off".to_string(),
+            },
+            SettingsItem::CursorInput => {
+                if self.cursor_input {
+                    "Cursor".to_string()
+                } else {
+                    "Direct".to_string()
+                }
+            }
+            SettingsItem::LossAnimation => format!("{:?}", self.loss_animation),
+            SettingsItem::TickRate => format!("{}ms", self.base_tick_rate),
+            SettingsItem::StrategyGauge => on_off(self.strategy_gauge),
+            SettingsItem::NewTileHighlight => on_off(self.new_tile_highlight),
+            SettingsItem::GameVariant => format!("{:?}", self.game_variant),
+            SettingsItem::TargetValue => format!("{}", self.target_value),
+            SettingsItem::AutosaveInterval => match self.autosave_interval {
+                0 => "Off".to_string(),
+                n => format!("{} moves", n),
+            },
+            SettingsItem::MergeSounds => on_off(self.merge_sounds),
+        }
+    }
+
+    /// move the solver setting one step up or down, the switches
+    /// are flipped either way
+    fn adjust_solver(&mut self, item: SolverItem, up: bool) {
+        let solver = &mut self.solver;
+        let weight = |w: f64| {
+            let w = if up { w + 0.1 } else { w - 0.1 };
+            // round to one decimal so the steps don't drift
+            (w.max(0.0) * 10.0).round() / 10.0
+        };
+        match item {
+            SolverItem::Depth => {
+                solver.depth = match up {
+                    true => std::cmp::min(solver.depth + 1, 4),
+                    false => std::cmp::max(solver.depth - 1, 1),
+                }
+            }
+            SolverItem::EmptyCells => {
+                solver.weights.empty_cells = weight(solver.weights.empty_cells)
+            }
+            SolverItem::Monotonicity => {
+                solver.weights.monotonicity = weight(solver.weights.monotonicity)
+            }
+            SolverItem::Smoothness => solver.weights.smoothness = weight(solver.weights.smoothness),
+            SolverItem::Corner => solver.weights.corner = weight(solver.weights.corner),
+            SolverItem::MaxTile => solver.weights.max_tile = weight(solver.weights.max_tile),
+            SolverItem::Concentration => {
+                solver.weights.concentration = weight(solver.weights.concentration)
+            }
+            SolverItem::Compactness => {
+                solver.weights.compactness = weight(solver.weights.compactness)
+            }
+            SolverItem::Openness => solver.weights.openness = weight(solver.weights.openness),
+            SolverItem::Perimeter => solver.weights.perimeter = weight(solver.weights.perimeter),
+            SolverItem::PotentialMerge => {
+                solver.weights.potential_merge = weight(solver.weights.potential_merge)
+            }
+            SolverItem::Parallel => solver.parallel = !solver.parallel,
+            SolverItem::TranspositionTable => {
+                solver.use_transposition_table = !solver.use_transposition_table
+            }
+            SolverItem::TimeLimit => {
+                solver.time_limit_ms = match up {
+                    true => std::cmp::min(solver.time_limit_ms + 50, 2000),
+                    false => std::cmp::max(solver.time_limit_ms.saturating_sub(50), 50),
+                }
+            }
+            SolverItem::ResetDefaults => (),
+        }
+    }
+
+    fn get_solver_value(&self, item: SolverItem) -> String {
+        let solver = &self.solver;
+        match item {
+            SolverItem::Depth => solver.depth.to_string(),
+            SolverItem::EmptyCells => format!("{:.1}", solver.weights.empty_cells),
+            SolverItem::Monotonicity => format!("{:.1}", solver.weights.monotonicity),
+            SolverItem::Smoothness => format!("{:.1}", solver.weights.smoothness),
+            SolverItem::Corner => format!("{:.1}", solver.weights.corner),
+            SolverItem::MaxTile => format!("{:.1}", solver.weights.max_tile),
+            SolverItem::Concentration => format!("{:.1}", solver.weights.concentration),
+            SolverItem::Compactness => format!("{:.1}", solver.weights.compactness),
+            SolverItem::Openness => format!("{:.1}", solver.weights.openness),
+            SolverItem::Perimeter => format!("{:.1}", solver.weights.perimeter),
+            SolverItem::PotentialMerge => format!("{:.1}", solver.weights.potential_merge),
+            SolverItem::Parallel => on_off(solver.parallel),
+            SolverItem::TranspositionTable => on_off(solver.use_transposition_table),
+            SolverItem::TimeLimit => format!("{}ms", solver.time_limit_ms),
+            SolverItem::ResetDefaults => String::new(),
+        }
+    }
+}
+
+#[repr(u16)]
+#[derive(Clone, Copy, Debug)]
+pub enum SolverItem {
+    Depth = 1,
+    EmptyCells = 2,
+    Monotonicity = 3,
+    Smoothness = 4,
+    Corner = 5,
+    MaxTile = 6,
+    Concentration = 7,
+    Compactness = 8,
+    Openness = 9,
+    Perimeter = 10,
+    PotentialMerge = 11,
+    Parallel = 12,
+    TranspositionTable = 13,
+    TimeLimit = 14,
+    ResetDefaults = 15,
+}
+
+impl fmt::Display for SolverItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl From<u16> for SolverItem {
+    fn from(n: u16) -> Self {
+        match n {
+            0 => SolverItem::ResetDefaults,
+            2 => SolverItem::EmptyCells,
+            3 => SolverItem::Monotonicity,
+            4 => SolverItem::Smoothness,
+            5 => SolverItem::Corner,
+            6 => SolverItem::MaxTile,
+            7 => SolverItem::Concentration,
+            8 => SolverItem::Compactness,
+            9 => SolverItem::Openness,
+            10 => SolverItem::Perimeter,
+            11 => SolverItem::PotentialMerge,
+            12 => SolverItem::Parallel,
+            13 => SolverItem::TranspositionTable,
+            14 => SolverItem::TimeLimit,
+            15 => SolverItem::ResetDefaults,
+            _ => SolverItem::Depth,
+        }
+    }
+}
+
+/// value of the `RUST2048_<name>` environment variable, None if it isn't set or doesn't parse
+fn env_setting<T: std::str::FromStr>(name: &str) -> Option<T> {
+    std::env::var(format!("RUST2048_{}", name))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// on/off environment variable, true, 1 and on turn it on and false, 0 and off turn it off
+fn env_flag(name: &str) -> Option<bool> {
+    let value = env_setting::<String>(name)?.to_ascii_lowercase();
+    match value.as_str() {
+        "true" | "1" | "on" => Some(true),
+        "false" | "0" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+/// variant of a setting that cycles through its values whose name matches the environment
+/// variable, ignoring case and underscores so `toward_move` picks `TowardMove`
+fn env_variant<T>(name: &str, first: T, next: fn(&T) -> T) -> Option<T>
+where
+    T: Copy + PartialEq + fmt::Debug,
+{
+    let value = env_setting::<String>(name)?.replace('_', "");
+    let mut variant = first;
+    loop {
+        if format!("{:?}", variant).eq_ignore_ascii_case(&value) {
+            return Some(variant);
+        }
+        variant = next(&variant);
+        if variant == first {
+            return None;
         }
     }
 }
 
+fn on_off(value: bool) -> String {
+    if value { "On" } else { "Off" }.to_string()
+}
+
 #[repr(u16)]
 #[derive(Clone, Copy, Debug)]
 pub enum MenuItem {
     Play = 1,
-    Reset = 2,
-    Settings = 3,
-    Exit = 4,
+    /// new board, the best score and the statistics of the session are kept
+    NewGame = 2,
+    /// new board and the best score, the statistics and the achievements are cleared too
+    ResetAll = 3,
+    Settings = 4,
+    Statistics = 5,
+    Achievements = 6,
+    Exit = 7,
 }
 
 impl fmt::Display for MenuItem {
@@ -117,149 +857,963 @@ impl fmt::Display for MenuItem {
 
 impl From<u16> for MenuItem {
     fn from(n: u16) -> Self {
+        // wraps around like `SettingsItem`
         match n {
             0 => MenuItem::Exit,
-            2 => MenuItem::Reset,
-            3 => MenuItem::Settings,
-            4 => MenuItem::Exit,
+            1 => MenuItem::Play,
+            2 => MenuItem::NewGame,
+            3 => MenuItem::ResetAll,
+            4 => MenuItem::Settings,
+            5 => MenuItem::Statistics,
+            6 => MenuItem::Achievements,
+            7 => MenuItem::Exit,
             _ => MenuItem::Play,
         }
     }
 }
 
+/// Actions that ask for a confirmation before they're done
+#[derive(Debug)]
+pub enum ConfirmItem {
+    NewGame,
+    ResetAll,
+    Quit,
+}
+
+#[derive(Debug)]
 pub enum InfoItem {
     GameLost,
     GameWon,
+    TimeUp,
 }
 
+#[derive(Debug)]
 pub enum Screen {
     Menu(MenuItem),
     Game,
     Settings,
+    SolverSettings(SolverItem),
     Info(InfoItem),
+    Confirm(ConfirmItem),
+    History(usize),
+    Statistics,
+    /// the game on the left and its statistics updating live on the right
+    SplitLayout,
+    /// terms of the solver's evaluation of the board
+    AiDebug,
+    /// the game played with the tutorial guiding the player through its first moves
+    Tutorial(TutorialStep),
+    Achievements,
+}
+
+/// Steps of the first launch tutorial, each one waits for the player to do what it asks
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TutorialStep {
+    Move,
+    Merge,
+    Goal,
+}
+
+impl TutorialStep {
+    fn text(&self) -> &'static str {
+        match self {
+            TutorialStep::Move => "Slide every tile on the board with the arrows or WASD. Make a move.",
+            TutorialStep::Merge => {
+                "Two tiles with the same number merge into one when they slide into each other. Merge two tiles."
+            }
+            TutorialStep::Goal => {
+                "Keep merging to make a 2048 tile. The game is lost once the board is full and nothing can merge. Press ENTER to play."
+            }
+        }
+    }
+}
+
+/// remember that the tutorial was played, failing to is harmless, it's just shown again
+fn finish_tutorial() {
+    let path = data_path(TUTORIAL_DONE_FILE);
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    let _ = std::fs::write(path, "");
+}
+
+/// delete the stats, the achievements, the tutorial marker and the autosave so the
+/// next launch starts like the first one, the exported csv is left alone
+fn remove_saved_data() {
+    for file in [
+        STATS_FILE,
+        ACHIEVEMENTS_FILE,
+        TUTORIAL_DONE_FILE,
+        AUTOSAVE_FILE,
+    ] {
+        match std::fs::remove_file(data_path(file)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                debug_log!(warn, "{} not deleted: {}", file, err)
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Command line options
+#[derive(Debug, Default)]
+pub struct Args {
+    /// render in the normal terminal buffer instead of the alternate screen
+    /// and leave the final board in the scrollback
+    inline: bool,
+    /// csv file the timing of every frame is written to
+    profile: Option<String>,
+    /// file every event of the games is appended to as json
+    log: Option<String>,
+    /// exit after the given number of frames, used for automated profiling runs
+    auto_quit_after: Option<u64>,
+    /// overlay the position and terminal coordinates of every square
+    debug: bool,
+    /// play with moves read from stdin and boards written to stdout, without the tui
+    headless: bool,
+    /// end the headless game after the given number of moves
+    headless_moves: Option<u64>,
+    /// file the merge trees of the final board are written to as a Graphviz graph
+    export_dot: Option<String>,
+    /// file the debug log is written to, needs the `logging` feature
+    debug_log: Option<String>,
+    /// saved game to pick up instead of starting a new one, see `AUTOSAVE_FILE`
+    resume: Option<PathBuf>,
+    /// file with the board of a puzzle and the tile that solves it, see `Grid::puzzle`
+    puzzle: Option<(PathBuf, u32)>,
+}
+
+impl Args {
+    fn parse() -> Self {
+        let mut args = Self::default();
+        let mut iter = std::env::args().skip(1).peekable();
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--inline" => args.inline = true,
+                "--profile" => args.profile = iter.next(),
+                "--log" => args.log = iter.next(),
+                "--export-dot" => args.export_dot = iter.next(),
+                "--debug-log" => args.debug_log = iter.next(),
+                "--resume" => {
+                    args.resume = match iter.peek() {
+                        Some(path) if !path.starts_with("--") => iter.next().map(PathBuf::from),
+                        _ => Some(data_path(AUTOSAVE_FILE)),
+                    }
+                }
+                "--puzzle" => {
+                    let path = iter.next().map(PathBuf::from);
+                    let target = iter.next().and_then(|n| n.parse().ok());
+                    args.puzzle = path.zip(target);
+                }
+                "--debug" => args.debug = true,
+                "--headless" => args.headless = true,
+                "--headless-moves" => {
+                    args.headless_moves = iter.next().and_then(|n| n.parse().ok())
+                }
+                "--auto-quit-after" => {
+                    args.auto_quit_after = iter.next().and_then(|n| n.parse().ok())
+                }
+                _ => (),
+            }
+        }
+        args
+    }
+}
+
+/// send the records of the `debug_log!` calls to the file, `RUST_LOG` filters them
+/// like it does for any other program using `env_logger`
+#[cfg(feature = "logging")]
+fn init_debug_log(path: &str) -> Result<(), Box<dyn Error>> {
+    let file = std::fs::File::create(path)?;
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug"))
+        .target(env_logger::Target::Pipe(Box::new(file)))
+        .format(|buf, record| {
+            let millis = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_millis())
+                .unwrap_or(0);
+            writeln!(
+                buf,
+                "{} {} {} {}",
+                millis,
+                record.level(),
+                record.target(),
+                record.args()
+            )
+        })
+        .try_init()?;
+    Ok(())
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    if let Some(path) = &args.debug_log {
+        #[cfg(feature = "logging")]
+        init_debug_log(path)?;
+        #[cfg(not(feature = "logging"))]
+        eprintln!(
+            "--debug-log {} is ignored, build with --features logging",
+            path
+        );
+    }
+    // reported before the terminal is taken over so the warnings stay readable
+    for setting in Settings::load().env_overrides() {
+        eprintln!("warning: {} set by the environment", setting);
+    }
+    if args.headless {
+        return run_headless(&args);
+    }
+    // read and checked before the terminal is taken over so a bad save is reported readably,
+    // the tile width is fitted to the terminal once it is set up
+    let resumed = match &args.resume {
+        Some(path) => Some(Grid::from_saved(
+            &SavedGame::read(path)?,
+            DEFAULT_TILE_WIDTH,
+        )?),
+        None => None,
+    };
+    let puzzle = load_puzzle(&args)?;
+    let mut profiler = match &args.profile {
+        Some(path) => Some(Profiler::new(path)?),
+        None => None,
+    };
+
+    // put the terminal back before the panic message is printed, so the message
+    // is readable and the shell isn't left in raw mode
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        debug_log!(error, "panic: {}", info);
+        let _ = disable_raw_mode();
+        let _ = execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture);
+        default_hook(info);
+    }));
+
     // setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    if args.inline {
+        execute!(stdout, EnableMouseCapture)?;
+    } else {
+        execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    }
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    // create app and run it
-    let game = Grid::new(6, 4);
-    let res = run_game(&mut terminal, game);
+    // create app and run it, with the biggest tiles the terminal fits or the smallest
+    // ones if it fits none, the game then waits for the terminal to grow
+    let size = Settings::load().game_size;
+    let Rect { width, height, .. } = terminal.size()?;
+    let mut game = Grid::new_auto_sized(
+        size,
+        size,
+        width.saturating_sub(2 * BOARD_PADDING),
+        height.saturating_sub(2 * BOARD_PADDING),
+    )
+    .unwrap_or_else(|_| {
+        Grid::new_with_initial_tiles(size, 6, Coordinates::new(0, 0), 2, &mut rand::thread_rng())
+    });
+    if let Some(mut resumed) = resumed {
+        resumed.change_tile_size(game.tile_width);
+        game = resumed;
+    }
+    if let Some(puzzle) = puzzle {
+        game = puzzle;
+    }
+    game.mv(Coordinates::new(BOARD_PADDING, BOARD_PADDING));
+    game.refresh_coordinates();
+    let res = run_game(&mut terminal, game, &args, profiler.as_mut());
 
     // restore terminal
     disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
+    if args.inline {
+        terminal.clear()?;
+        execute!(terminal.backend_mut(), DisableMouseCapture)?;
+    } else {
+        execute!(
+            terminal.backend_mut(),
+            LeaveAlternateScreen,
+            DisableMouseCapture
+        )?;
+    }
     terminal.show_cursor()?;
 
-    if let Err(err) = res {
-        println!("{:?}", err)
+    // reported after the terminal is restored, whichever way the game ended, so it
+    // isn't wiped with the alternate screen
+    if let Some(profiler) = profiler {
+        eprintln!("{}", profiler.finish()?);
+    }
+    if let (Ok(game), Some(path)) = (&res, &args.export_dot) {
+        std::fs::write(path, game.serialize_to_dot())?;
+    }
+    match res {
+        Ok(game) if args.inline => print!("{}", format_board(&game)),
+        Ok(_) => (),
+        Err(err) => {
+            debug_log!(error, "run_game failed: {:?}", err);
+            println!("{:?}", err)
+        }
     }
 
     Ok(())
 }
 
-fn run_game<B: Backend>(
-    terminal: &mut Terminal<B>,
-    mut game: Grid,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let settings = Arc::new(RwLock::new(Settings::new()));
-    let settings_clone = settings.clone();
-    let mut active_screen = Screen::Menu(MenuItem::Play);
+/// the puzzle given with `--puzzle`, none without it
+fn load_puzzle(args: &Args) -> Result<Option<Grid>, Box<dyn Error>> {
+    match &args.puzzle {
+        Some((path, target)) => Ok(Some(Grid::puzzle(
+            &std::fs::read_to_string(path)?,
+            *target,
+        )?)),
+        None => Ok(None),
+    }
+}
 
-    let (tx, rx) = channel();
-    thread::spawn(move || {
-        // spawn a thread that will be listening to the input of the user and
-        // send this input through mpsc to the rendering thread, if there is no
-        // input it will send the tick message
-        let mut last_tick = Instant::now();
-        loop {
-            let animation_speed = settings_clone.read().unwrap().animation_speed;
-            let tick_rate = Duration::from_millis((4 - animation_speed) as u64 * BASE_TICK_RATE);
-            let timeout = tick_rate
-                .checked_sub(last_tick.elapsed())
-                .unwrap_or_else(|| Duration::from_secs(0));
-
-            if event::poll(timeout).expect("poll works") {
-                if let CEvent::Key(key) = event::read().expect("can read events") {
-                    tx.send(Event::Input(key)).expect("can send events");
-                }
-            }
+/// play a game driven by stdin, one move per line (U, D, L or R), the board is
+/// written to stdout as a json array of rows at the start and after every move,
+/// the final score is written once the game ends, stdin is closed or the move limit is hit
+fn run_headless(args: &Args) -> Result<(), Box<dyn Error>> {
+    let settings = Settings::load();
+    let mut game = match load_puzzle(args)? {
+        Some(puzzle) => puzzle,
+        None => new_game(&Grid::new(6, settings.game_size), &settings),
+    };
+    let mut logger = match &args.log {
+        Some(path) => Some(EventLogger::new(path)?),
+        None => None,
+    };
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "{}", serde_json::to_string(&game.to_matrix())?)?;
 
-            if last_tick.elapsed() >= tick_rate {
-                if let Ok(_) = tx.send(Event::Tick) {
-                    last_tick = Instant::now();
-                }
+    let mut moves = 0;
+    for line in io::stdin().lock().lines() {
+        let mv = match line?.trim() {
+            "U" => Move::Up,
+            "D" => Move::Down,
+            "L" => Move::Left,
+            "R" => Move::Right,
+            other => {
+                eprintln!("unknown move {:?}, expected U, D, L or R", other);
+                continue;
             }
+        };
+        moves += 1;
+
+        // there is nobody to watch the animation so the ticks run until the tiles settle
+        let mut result = game.on_tick(Some(mv));
+        while result.is_ok() && !game.moving_tiles.is_empty() {
+            result = game.on_tick(None);
         }
-    });
+        if let Some(logger) = logger.as_mut() {
+            logger.log_new(&game.event_log)?;
+        }
+        writeln!(stdout, "{}", serde_json::to_string(&game.to_matrix())?)?;
+        if result.is_err() || args.headless_moves.is_some_and(|max| moves >= max) {
+            break;
+        }
+    }
 
-    loop {
-        terminal.draw(|f| {
-            // render black background by default
-            f.render_widget(
-                Block::default().style(Style::default().bg(Color::Black)),
-                f.size(),
-            );
-            match &active_screen {
-                Screen::Menu(active_menu_item) => render_menu(f, active_menu_item),
-                Screen::Game => {
-                    let Rect {
-                        width: terminal_width,
-                        height: terminal_height,
-                        ..
-                    } = f.size();
-                    match game.adjust_size(terminal_width, terminal_height) {
-                        Ok(_) => render_game(f, &mut game),
-                        Err(err) => render_error(f, err),
-                    }
-                }
-                Screen::Settings => render_settings(f, settings.clone()),
-                Screen::Info(info_item) => match info_item {
-                    InfoItem::GameWon => render_info(f, "Game Won", "You have won the game!"),
-                    InfoItem::GameLost => render_info(f, "Game Lost", "You have lost the game :("),
-                },
-            }
-        })?;
+    writeln!(stdout, "{{\"score\":{}}}", game.score)?;
+    Ok(())
+}
 
-        match rx.recv()? {
-            // listen to messages from the other thread and based on the
-            // message decide what to do
-            Event::Input(event) => {
-                if event.code == KeyCode::Char('q') {
-                    disable_raw_mode()?;
-                    terminal.show_cursor()?;
-                    break;
-                }
-                match &active_screen {
-                    Screen::Menu(active_menu_item) => match event.code {
-                        KeyCode::Char('w') | KeyCode::Up => {
-                            let item = *active_menu_item as u16 - 1;
-                            active_screen = Screen::Menu(MenuItem::from(item));
-                        }
-                        KeyCode::Char('s') | KeyCode::Down => {
-                            let item = *active_menu_item as u16 + 1;
-                            active_screen = Screen::Menu(MenuItem::from(item));
-                        }
+/// plain text version of the board, used to leave a record in the terminal
+fn format_board(game: &Grid) -> String {
+    game.to_matrix()
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|n| match n {
+                    0 => format!("{:>6}", "."),
+                    n => format!("{:>6}", n),
+                })
+                .collect::<String>()
+                + "\n"
+        })
+        .collect()
+}
+
+/// fresh game with the current settings applied
+fn new_game(game: &Grid, settings: &Settings) -> Grid {
+    new_game_with_seed(game, settings, rand::random())
+}
+
+/// new game whose tiles all come from the seed, the same seed and moves replay a game
+fn new_game_with_seed(game: &Grid, settings: &Settings, seed: u64) -> Grid {
+    let mut new_game = Grid::new_with_seed(
+        settings.game_size,
+        game.tile_width,
+        game.coordinates(),
+        0,
+        seed,
+    );
+    new_game.merge_linger = settings.merge_linger;
+    new_game.change_margins(settings.tile_margin_x, settings.tile_margin_y);
+    new_game.mode = settings.game_mode;
+    new_game.change_merge_factor(settings.merge_factor);
+    new_game.set_variant(settings.game_variant);
+    new_game.merge_preference = settings.merge_preference;
+    new_game.merge_limit = settings.merge_limit;
+    let target = match settings.game_mode {
+        GameMode::Target => Some(settings.target_value),
+        _ => settings.win_target,
+    };
+    if let Some(target) = target {
+        if let Err(err) = new_game.set_target(target) {
+            debug_log!(warn, "target of the new game ignored: {}", err);
+        }
+    }
+    // the first tiles spawn once the rules are set so they have the values of the variant
+    for _ in 0..2 {
+        let _ = new_game.spawn_random_tile();
+    }
+    new_game
+}
+
+/// save the game once it made `interval` more moves than at the last save, the tiles
+/// have to settle first so the board is saved after the move rather than before it.
+/// A failed save is only logged, the game goes on and tries again `interval` moves later
+fn autosave(game: &Grid, interval: u64, autosaved_at: &mut u64, path: &Path) {
+    if game.moves < *autosaved_at {
+        // a new game started since the last save
+        *autosaved_at = 0;
+    }
+    if interval == 0 || game.moves < *autosaved_at + interval || !game.moving_tiles.is_empty() {
+        return;
+    }
+    match game.to_saved().write_atomic(path) {
+        Ok(_) => debug_log!(
+            debug,
+            "autosaved moves={} path={}",
+            game.moves,
+            path.display()
+        ),
+        Err(err) => debug_log!(warn, "autosave failed path={}: {}", path.display(), err),
+    }
+    *autosaved_at = game.moves;
+}
+
+/// merges score three times as much in the first third of a blitz game
+/// and twice as much in the second third
+fn blitz_multiplier(time_left: Duration, total: Duration) -> u32 {
+    let thirds = 3 * time_left.as_millis() / std::cmp::max(total.as_millis(), 1);
+    std::cmp::min(1 + thirds as u32, 3)
+}
+
+/// arrow key previewed by the key event if it's a direction pressed with shift
+fn ghost_key(event: &KeyEvent) -> Option<KeyCode> {
+    match event.code {
+        KeyCode::Char('W') => Some(KeyCode::Up),
+        KeyCode::Char('S') => Some(KeyCode::Down),
+        KeyCode::Char('A') => Some(KeyCode::Left),
+        KeyCode::Char('D') => Some(KeyCode::Right),
+        KeyCode::Up | KeyCode::Down | KeyCode::Left | KeyCode::Right
+            if event.modifiers.contains(KeyModifiers::SHIFT) =>
+        {
+            Some(event.code)
+        }
+        _ => None,
+    }
+}
+
+/// highlighted square of the cursor input and the direction it last moved in,
+/// which is the direction the board is swiped in
+#[derive(Debug, Clone, Copy, Default)]
+struct Cursor {
+    pos: Position,
+    direction: Option<Move>,
+}
+
+impl Cursor {
+    /// move one square, staying on a board of the given size
+    fn step(&mut self, mv: Move, size: u16) {
+        let Position { x, y } = self.pos;
+        let last = size.saturating_sub(1);
+        self.pos = match mv {
+            Move::Up => Position::new(x, y.saturating_sub(1)),
+            Move::Down => Position::new(x, std::cmp::min(y + 1, last)),
+            Move::Left => Position::new(x.saturating_sub(1), y),
+            Move::Right => Position::new(std::cmp::min(x + 1, last), y),
+        };
+        self.direction = Some(mv);
+    }
+}
+
+/// direction the cursor moves in for the key, the same keys that move the board
+fn cursor_key(key: KeyCode) -> Option<Move> {
+    match key {
+        KeyCode::Char('w') => Some(Move::Up),
+        KeyCode::Char('s') => Some(Move::Down),
+        KeyCode::Char('a') => Some(Move::Left),
+        KeyCode::Char('d') => Some(Move::Right),
+        key => move_for_key(key),
+    }
+}
+
+/// chance of the solver winning from the board, measured on a thread of its own
+/// because playing the games out takes seconds
+#[derive(Default)]
+struct Solvability {
+    /// first event of the game and the number of moves made when it was last measured
+    measured_at: Option<(Option<GameEvent>, usize)>,
+    value: Option<f64>,
+    /// the running measurement and the flag that stops it
+    job: Option<(Receiver<f64>, Arc<AtomicBool>)>,
+}
+
+impl Solvability {
+    /// pick up a finished measurement and start a new one if the board is a
+    /// different game or moved on since the last one, which stops the running one
+    fn update(&mut self, game: &Grid) {
+        let first = game.event_log.first().copied();
+        let moves = game.event_summary().total_moves;
+        let stale = match self.measured_at {
+            Some((measured_first, measured_moves)) => {
+                measured_first != first || moves.abs_diff(measured_moves) >= SOLVABILITY_CACHE_MOVES
+            }
+            None => true,
+        };
+        if !stale {
+            if let Some((job, _)) = &self.job {
+                match job.try_recv() {
+                    Ok(value) => {
+                        self.value = Some(value);
+                        self.job = None;
+                    }
+                    Err(TryRecvError::Empty) => (),
+                    Err(TryRecvError::Disconnected) => self.job = None,
+                }
+            }
+            return;
+        }
+        self.cancel();
+        self.measured_at = Some((first, moves));
+        let (tx, rx) = channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let board = game.clone();
+        let flag = cancel.clone();
+        thread::spawn(move || {
+            let measured = solver::measure_solve_difficulty(
+                &board,
+                SOLVABILITY_ITERATIONS,
+                moves as u64,
+                &flag,
+            );
+            if let Some(value) = measured {
+                let _ = tx.send(value);
+            }
+        });
+        self.job = Some((rx, cancel));
+    }
+
+    /// stop the running measurement and forget the last one, the next update
+    /// measures the board again
+    fn cancel(&mut self) {
+        if let Some((_, cancel)) = self.job.take() {
+            cancel.store(true, Ordering::Relaxed);
+        }
+        self.measured_at = None;
+        self.value = None;
+    }
+}
+
+impl Drop for Solvability {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// quitting with q asks first when it would throw away a game that's being played,
+/// anywhere else and for a game without any score yet it quits right away
+fn should_confirm_quit(screen: &Screen, game: &Grid) -> bool {
+    matches!(screen, Screen::Game | Screen::SplitLayout) && game.score > 0
+}
+
+/// true when `mv` comes in again before the key of the `last` move was let go, the
+/// terminal repeating the press of a key held down rather than a press of its own
+fn is_held_repeat(last: Option<(Move, Instant)>, mv: Move, now: Instant) -> bool {
+    matches!(last, Some((last, at)) if last == mv && now.duration_since(at) < HOLD_TIMEOUT)
+}
+
+fn move_for_key(key: KeyCode) -> Option<Move> {
+    match key {
+        KeyCode::Up => Some(Move::Up),
+        KeyCode::Down => Some(Move::Down),
+        KeyCode::Left => Some(Move::Left),
+        KeyCode::Right => Some(Move::Right),
+        _ => None,
+    }
+}
+
+fn run_game<B: Backend>(
+    terminal: &mut Terminal<B>,
+    mut game: Grid,
+    args: &Args,
+    mut profiler: Option<&mut Profiler>,
+) -> Result<Grid, Box<dyn std::error::Error>> {
+    let settings = Arc::new(RwLock::new(Settings::load()));
+    let settings_clone = settings.clone();
+    // the tip shown under the controls of the game screen, the next one every `TIP_TICKS`
+    let mut tip_index: usize = 0;
+    let mut tip_ticks: u64 = 0;
+    let mut active_screen = match data_path(TUTORIAL_DONE_FILE).exists() {
+        true => Screen::Menu(MenuItem::Play),
+        false => Screen::Tutorial(TutorialStep::Move),
+    };
+
+    let (tx, rx) = channel();
+    // stopped when it goes out of scope, whichever way the game ends
+    let _input = InputThread::spawn(settings_clone, tx);
+
+    let mut logger = match &args.log {
+        Some(path) => Some(EventLogger::new(path)?),
+        None => None,
+    };
+    let mut frames: u64 = 0;
+    // highest score of all the games played in this session
+    let mut best_score = 0;
+    // end of the current blitz game, the clock starts with the first tick of the game
+    let mut blitz_deadline: Option<Instant> = None;
+    // keys held down to preview the ghost tiles and when they were last pressed
+    let mut held_keys: HashSet<KeyCode> = HashSet::new();
+    let mut last_held = Instant::now();
+    // last move pressed on the board and when, to tell a held key from new presses
+    let mut last_move: Option<(Move, Instant)> = None;
+    // last time any key was pressed and the demo game played while the menu sits idle
+    let mut last_input = Instant::now();
+    let mut demo: Option<Grid> = None;
+    let mut cursor = Cursor::default();
+    let autosave_path = data_path(AUTOSAVE_FILE);
+    let mut stats = Stats::load(&data_path(STATS_FILE)).unwrap_or_else(|err| {
+        debug_log!(warn, "saved stats not loaded: {}", err);
+        Stats::default()
+    });
+    let mut achievements =
+        Achievements::load(&data_path(ACHIEVEMENTS_FILE)).unwrap_or_else(|err| {
+            debug_log!(warn, "saved achievements not loaded: {}", err);
+            Achievements::default()
+        });
+    // names of the achievements the last game unlocked and when they were first shown
+    let mut toast: Option<(String, Instant)> = None;
+    // ticks left of the loss animation, the game is lost once they run out
+    let mut losing: Option<u16> = None;
+    // the terminal can't fit the board, the game waits untouched until it grows back
+    let mut too_small = false;
+    // with --debug the animation can be paused and stepped through one tick at a time
+    let mut paused = false;
+    // outcome of the last export shown on the statistics screen
+    let mut export_status: Option<String> = None;
+    let mut solvability = Solvability::default();
+    // move of the finished game the info screen shows while the player looks back
+    // through it, none while the info dialog is up
+    let mut review: Option<usize> = None;
+    // screen a confirmation dialog was opened from, answering no goes back to it
+    let mut confirmed_from: Option<Screen> = None;
+    // moves the game had when it was last saved, see `autosave`
+    let mut autosaved_at: u64 = game.moves;
+    let mut merge_sounds = MergeSounds::default();
+    #[cfg(feature = "logging")]
+    let mut logged_screen = String::new();
+
+    loop {
+        #[cfg(feature = "logging")]
+        {
+            let screen = format!("{:?}", active_screen);
+            if screen != logged_screen {
+                log::debug!("screen from={} to={}", logged_screen, screen);
+                logged_screen = screen;
+            }
+        }
+        if let Some(profiler) = profiler.as_mut() {
+            profiler.start_frame()?;
+        }
+        if let Some(logger) = logger.as_mut() {
+            logger.log_new(&game.event_log)?;
+        }
+        let interval = settings.read().unwrap().autosave_interval;
+        autosave(&game, interval, &mut autosaved_at, &autosave_path);
+        match settings.read().unwrap().merge_sounds {
+            true => merge_sounds.play_new(&game.event_log),
+            false => merge_sounds.mute(&game.event_log),
+        }
+        if let Some(max_frames) = args.auto_quit_after {
+            if frames >= max_frames {
+                break;
+            }
+        }
+        frames += 1;
+        best_score = std::cmp::max(best_score, game.score);
+        match active_screen {
+            Screen::Statistics => solvability.update(&game),
+            // nobody looks at the measurement anywhere else
+            _ => solvability.cancel(),
+        }
+        let finished = match active_screen {
+            Screen::Info(InfoItem::GameWon) => Some(true),
+            Screen::Info(InfoItem::GameLost) | Screen::Info(InfoItem::TimeUp) => Some(false),
+            _ => None,
+        };
+        if let Some(won) = finished {
+            if stats.record(&game, won) {
+                if let Err(err) = stats.save(&data_path(STATS_FILE)) {
+                    debug_log!(warn, "stats not saved: {}", err);
+                }
+                let unlocked = achievements.check(&game, &stats);
+                if !unlocked.is_empty() {
+                    if let Err(err) = achievements.save(&data_path(ACHIEVEMENTS_FILE)) {
+                        debug_log!(warn, "achievements not saved: {}", err);
+                    }
+                    let names = unlocked.iter().map(|a| a.name).join(", ");
+                    toast = Some((names, Instant::now()));
+                }
+            }
+        }
+        if matches!(&toast, Some((_, shown_at)) if shown_at.elapsed() >= TOAST_DURATION) {
+            toast = None;
+        }
+
+        terminal.draw(|f| {
+            // render black background by default
+            f.render_widget(
+                Block::default().style(Style::default().bg(Color::Black)),
+                f.size(),
+            );
+            match &active_screen {
+                Screen::Menu(active_menu_item) => {
+                    if let Some(board) = demo.as_mut() {
+                        let layout = LayoutEngine::new(f.size(), *settings.read().unwrap());
+                        if layout.fit_board(board).is_ok() {
+                            render_board(f, board, &settings.read().unwrap());
+                            // fade the demo so the menu stays readable on top of it
+                            f.render_widget(
+                                Block::default()
+                                    .style(Style::default().add_modifier(Modifier::DIM)),
+                                f.size(),
+                            );
+                        }
+                    }
+                    render_menu(f, active_menu_item, &settings.read().unwrap())
+                }
+                Screen::Game | Screen::SplitLayout | Screen::Tutorial(_) => {
+                    let mut layout = LayoutEngine::new(f.size(), *settings.read().unwrap());
+                    layout.split = matches!(active_screen, Screen::SplitLayout);
+                    if let Some(rect) = layout.statistics_line_rect() {
+                        render_statistics_line(f, &game, rect);
+                    }
+                    render_live_statistics(f, &game, &layout);
+                    if let (Some(ticks), LossAnimation::Shake) =
+                        (losing, settings.read().unwrap().loss_animation)
+                    {
+                        // every other tick the board jumps a couple of columns to the right
+                        layout.shake = ticks % 2 * 2;
+                    }
+                    let fits = layout.fit_board(&mut game);
+                    too_small = fits.is_err();
+                    match fits {
+                        Ok(_) => {
+                            let mut hud = vec![Spans::from(format!("Best {}", best_score))];
+                            if let Some(deadline) = blitz_deadline {
+                                hud.extend(render_blitz_hud(deadline, game.score_multiplier));
+                            }
+                            if game.mode == GameMode::Hardcore {
+                                hud.push(render_lives_hud(&game));
+                            }
+                            if game.mode == GameMode::Endless {
+                                hud.push(Spans::from(format!("Runs: {}", game.conversions)));
+                            }
+                            if let Some(n) = game.largest_potential_merge() {
+                                hud.push(Spans::from(format!("Next merge: {}", n)));
+                            }
+                            if game.mode == GameMode::Target {
+                                hud.push(Spans::from(format!("Make {}", game.winning_value())));
+                            }
+                            hud.extend(render_merges_hud(&game));
+                            if args.debug {
+                                let [up, down, left, right] = game.merges_per_direction();
+                                hud.push(Spans::from(format!(
+                                    "↑{} ↓{} ←{} →{}",
+                                    up, down, left, right
+                                )));
+                            }
+                            if paused {
+                                hud.push(Spans::from(vec![Span::styled(
+                                    format!(
+                                        "Paused at tick {} - . to step, {} moving",
+                                        game.ticks,
+                                        game.moving_tiles.len()
+                                    ),
+                                    Style::default().fg(Color::Magenta),
+                                )]));
+                            }
+                            render_game(
+                                f,
+                                layout,
+                                &mut game,
+                                &settings.read().unwrap(),
+                                hud,
+                                &active_screen,
+                                tip_index,
+                            );
+                            if let Some(mv) = held_keys.iter().find_map(|key| move_for_key(*key)) {
+                                render_ghost_tiles(f, &game, mv);
+                            }
+                            if settings.read().unwrap().cursor_input {
+                                render_cursor(f, &game, cursor);
+                            }
+                            if let (Some(ticks), LossAnimation::Flash) =
+                                (losing, settings.read().unwrap().loss_animation)
+                            {
+                                if ticks % 2 == 1 {
+                                    render_loss_flash(f, &game);
+                                }
+                            }
+                            if settings.read().unwrap().hints {
+                                render_hints(f, &game);
+                            }
+                            if args.debug {
+                                render_debug_coordinates(f, &game);
+                            }
+                            if let Screen::Tutorial(step) = &active_screen {
+                                render_tutorial(f, &game, *step);
+                            }
+                        }
+                        Err(err) => render_error(f, err),
+                    }
+                }
+                Screen::Settings => render_settings(f, settings.clone()),
+                Screen::SolverSettings(active_item) => {
+                    render_solver_settings(f, &settings.read().unwrap(), active_item)
+                }
+                Screen::Statistics => {
+                    render_statistics(f, &game, &stats, solvability.value, &export_status)
+                }
+                Screen::AiDebug => render_ai_debug(f, &game, &settings.read().unwrap().solver),
+                Screen::Achievements => render_achievements(f, &achievements),
+                Screen::History(index) => {
+                    let layout = LayoutEngine::new(f.size(), *settings.read().unwrap());
+                    let mut board = game.board_at(*index);
+                    match layout.fit_board(&mut board) {
+                        Ok(_) => render_history(
+                            f,
+                            &mut board,
+                            &settings.read().unwrap(),
+                            &active_screen,
+                            *index,
+                            game.history().len(),
+                        ),
+                        Err(err) => render_error(f, err),
+                    }
+                }
+                Screen::Info(_) if review.is_some() => {
+                    let index = review.unwrap_or(0);
+                    let layout = LayoutEngine::new(f.size(), *settings.read().unwrap());
+                    let mut board = game.board_at(index);
+                    match layout.fit_board(&mut board) {
+                        Ok(_) => render_history(
+                            f,
+                            &mut board,
+                            &settings.read().unwrap(),
+                            &active_screen,
+                            index,
+                            game.history().len(),
+                        ),
+                        Err(err) => render_error(f, err),
+                    }
+                }
+                Screen::Confirm(ConfirmItem::NewGame) => render_confirm(
+                    f,
+                    "New Game",
+                    "Start a new game? The current one will be lost.",
+                ),
+                Screen::Confirm(ConfirmItem::ResetAll) => render_confirm(
+                    f,
+                    "Reset All",
+                    "Start over? The current game, the best score, the statistics, the achievements and the saved game will be lost.",
+                ),
+                Screen::Confirm(ConfirmItem::Quit) => {
+                    render_confirm(f, "Quit", "Quit? The current game will be lost.")
+                }
+                Screen::Info(info_item) => match info_item {
+                    InfoItem::GameWon => {
+                        render_info(f, "Game Won", "You have won the game!", game.seed)
+                    }
+                    InfoItem::GameLost => {
+                        render_info(f, "Game Lost", "You have lost the game :(", game.seed)
+                    }
+                    InfoItem::TimeUp => render_info(
+                        f,
+                        "Time Up",
+                        &format!("Time is up! Your final score is {}", game.score),
+                        game.seed,
+                    ),
+                },
+            }
+            if let Some((names, _)) = &toast {
+                render_toast(f, names);
+            }
+        })?;
+        if let Some(profiler) = profiler.as_mut() {
+            profiler.rendered();
+        }
+
+        let event = rx.recv()?;
+        if let Some(profiler) = profiler.as_mut() {
+            profiler.polled();
+        }
+
+        match event {
+            // listen to messages from the other thread and based on the
+            // message decide what to do
+            Event::Input(event) => {
+                last_input = Instant::now();
+                // raw mode turns ctrl + c into a key press instead of a signal,
+                // it quits right away but still restores the terminal on the way out
+                let interrupt = event.code == KeyCode::Char('c')
+                    && event.modifiers.contains(KeyModifiers::CONTROL);
+                if event.code == KeyCode::Char('q') && should_confirm_quit(&active_screen, &game) {
+                    let quit = Screen::Confirm(ConfirmItem::Quit);
+                    confirmed_from = Some(std::mem::replace(&mut active_screen, quit));
+                    continue;
+                }
+                if event.code == KeyCode::Char('q') || interrupt {
+                    disable_raw_mode()?;
+                    terminal.show_cursor()?;
+                    break;
+                }
+                if demo.take().is_some() {
+                    // any other key only stops the demo and brings the menu back
+                    continue;
+                }
+                match &active_screen {
+                    Screen::Menu(active_menu_item) => match event.code {
+                        KeyCode::Char('w') | KeyCode::Up => {
+                            let item = *active_menu_item as u16 - 1;
+                            active_screen = Screen::Menu(MenuItem::from(item));
+                        }
+                        KeyCode::Char('s') | KeyCode::Down => {
+                            let item = *active_menu_item as u16 + 1;
+                            active_screen = Screen::Menu(MenuItem::from(item));
+                        }
                         KeyCode::Enter => match active_menu_item {
                             MenuItem::Play => {
                                 active_screen = Screen::Game;
                             }
-                            MenuItem::Reset => {
-                                game = Grid::new(game.tile_width, game.size);
+                            MenuItem::NewGame => {
+                                game = new_game(&game, &settings.read().unwrap());
+                                blitz_deadline = None;
                                 active_screen = Screen::Game;
                             }
+                            MenuItem::ResetAll => {
+                                active_screen = Screen::Confirm(ConfirmItem::ResetAll);
+                            }
                             MenuItem::Settings => {
                                 active_screen = Screen::Settings;
                             }
+                            MenuItem::Statistics => {
+                                active_screen = Screen::Statistics;
+                            }
+                            MenuItem::Achievements => {
+                                active_screen = Screen::Achievements;
+                            }
                             MenuItem::Exit => {
                                 disable_raw_mode()?;
                                 terminal.show_cursor()?;
@@ -273,20 +1827,127 @@ fn run_game<B: Backend>(
                         }
                         _ => (),
                     },
-                    Screen::Game => {
+                    Screen::Tutorial(step) => match (*step, event.code) {
+                        (_, KeyCode::Esc) | (TutorialStep::Goal, KeyCode::Enter) => {
+                            finish_tutorial();
+                            active_screen = Screen::Game;
+                        }
+                        (TutorialStep::Goal, _) => (),
+                        (step, code) => {
+                            if let Some(mv) = cursor_key(code) {
+                                // the tutorial board is never won or lost in a few moves,
+                                // and if it were Esc still leads out of it
+                                let _ = game.on_tick(Some(mv));
+                                let next = match step {
+                                    _ if game.moving_tiles.is_empty() => step,
+                                    TutorialStep::Move => TutorialStep::Merge,
+                                    _ if !game.last_merges.is_empty() => TutorialStep::Goal,
+                                    _ => step,
+                                };
+                                active_screen = Screen::Tutorial(next);
+                            }
+                        }
+                    },
+                    // the board can't be played while the loss animation plays
+                    Screen::Game | Screen::SplitLayout if losing.is_some() => (),
+                    // no moves are made blind while only the error is shown
+                    Screen::Game | Screen::SplitLayout
+                        if too_small && event.code != KeyCode::Esc => {}
+                    Screen::Game | Screen::SplitLayout => {
+                        if settings.read().unwrap().show_ghost_tiles {
+                            if let Some(key) = ghost_key(&event) {
+                                held_keys.clear();
+                                held_keys.insert(key);
+                                last_held = Instant::now();
+                                continue;
+                            }
+                        }
+                        held_keys.clear();
+                        let cursor_input = settings.read().unwrap().cursor_input;
                         let mv = match event.code {
                             KeyCode::Esc => {
                                 active_screen = Screen::Menu(MenuItem::Play);
                                 continue;
                             }
+                            KeyCode::Char('h') => {
+                                active_screen = Screen::History(game.history().len());
+                                continue;
+                            }
+                            KeyCode::Char('n') => {
+                                let confirm = Screen::Confirm(ConfirmItem::NewGame);
+                                confirmed_from =
+                                    Some(std::mem::replace(&mut active_screen, confirm));
+                                continue;
+                            }
+                            KeyCode::F(3) => {
+                                active_screen = Screen::AiDebug;
+                                continue;
+                            }
+                            KeyCode::Char('p') if args.debug => {
+                                paused = !paused;
+                                continue;
+                            }
+                            // the colors can be tried out on the board without leaving it
+                            KeyCode::Char('t') => {
+                                settings
+                                    .write()
+                                    .unwrap()
+                                    .update_settings(SettingsItem::HighContrast);
+                                continue;
+                            }
+                            // a single tick of the paused animation
+                            KeyCode::Char('.') if paused => None,
+                            code if event.modifiers.contains(KeyModifiers::CONTROL)
+                                && move_for_key(code).is_some() =>
+                            {
+                                game.scroll(move_for_key(code).unwrap());
+                                continue;
+                            }
+                            KeyCode::Tab => {
+                                active_screen = match active_screen {
+                                    Screen::SplitLayout => Screen::Game,
+                                    _ => Screen::SplitLayout,
+                                };
+                                continue;
+                            }
+                            KeyCode::Char(' ') | KeyCode::Enter if cursor_input => cursor.direction,
+                            // the directions only move the cursor, the swipe is committed apart
+                            code if cursor_input && cursor_key(code).is_some() => {
+                                cursor.step(cursor_key(code).unwrap(), game.size);
+                                None
+                            }
                             KeyCode::Char('w') | KeyCode::Up => Some(Move::Up),
                             KeyCode::Char('s') | KeyCode::Down => Some(Move::Down),
                             KeyCode::Char('a') | KeyCode::Left => Some(Move::Left),
                             KeyCode::Char('d') | KeyCode::Right => Some(Move::Right),
                             _ => None,
                         };
-                        game.on_tick(mv)
-                            .expect("Error should've been caught earlier!");
+                        if let Some(mv) = mv {
+                            let now = Instant::now();
+                            let held = is_held_repeat(last_move, mv, now);
+                            last_move = Some((mv, now));
+                            // a held key keeps repeating its move after the board stopped
+                            // changing, only a press of its own may cost a hardcore life
+                            if held && game.mode == GameMode::Hardcore && game.check(mv).is_empty()
+                            {
+                                continue;
+                            }
+                        }
+                        // a key press can finish the game too, e.g. a hardcore game out of lives
+                        match game.on_tick(mv) {
+                            Err(err) if err == "Game Won" => {
+                                active_screen = Screen::Info(InfoItem::GameWon)
+                            }
+                            Err(err) if err == "Game Lost" => {
+                                match settings.read().unwrap().loss_animation {
+                                    LossAnimation::Off => {
+                                        active_screen = Screen::Info(InfoItem::GameLost)
+                                    }
+                                    _ => losing = Some(LOSS_ANIMATION_TICKS),
+                                }
+                            }
+                            _ => (),
+                        }
                     }
                     Screen::Settings => {
                         let mut settings = settings.write().unwrap();
@@ -303,10 +1964,95 @@ fn run_game<B: Backend>(
                                 SettingsItem::AnimationSpeed => {
                                     settings.update_settings(SettingsItem::AnimationSpeed)
                                 }
+                                SettingsItem::Hints => {
+                                    settings.update_settings(SettingsItem::Hints)
+                                }
+                                SettingsItem::MergeLinger => {
+                                    settings.update_settings(SettingsItem::MergeLinger);
+                                    game.merge_linger = settings.merge_linger;
+                                }
+                                SettingsItem::TileSpacing => {
+                                    settings.update_settings(SettingsItem::TileSpacing);
+                                    game.change_margins(
+                                        settings.tile_margin_x,
+                                        settings.tile_margin_y,
+                                    );
+                                }
+                                SettingsItem::SpawnAnimation => {
+                                    settings.update_settings(SettingsItem::SpawnAnimation);
+                                }
+                                SettingsItem::GhostTiles => {
+                                    settings.update_settings(SettingsItem::GhostTiles);
+                                }
+                                SettingsItem::IdleDemo => {
+                                    settings.update_settings(SettingsItem::IdleDemo);
+                                }
+                                SettingsItem::MergeFactor => {
+                                    settings.update_settings(SettingsItem::MergeFactor);
+                                    game = new_game(&game, &settings);
+                                    blitz_deadline = None;
+                                }
+                                SettingsItem::MergePreference => {
+                                    settings.update_settings(SettingsItem::MergePreference);
+                                    game.merge_preference = settings.merge_preference;
+                                }
+                                SettingsItem::HighContrast => {
+                                    settings.update_settings(SettingsItem::HighContrast);
+                                }
+                                SettingsItem::StrokeThickness => {
+                                    settings.update_settings(SettingsItem::StrokeThickness);
+                                }
+                                SettingsItem::MergeLimit => {
+                                    settings.update_settings(SettingsItem::MergeLimit);
+                                    game.merge_limit = settings.merge_limit;
+                                }
+                                SettingsItem::CursorInput => {
+                                    settings.update_settings(SettingsItem::CursorInput);
+                                }
+                                SettingsItem::LossAnimation => {
+                                    settings.update_settings(SettingsItem::LossAnimation);
+                                }
+                                SettingsItem::TickRate => {
+                                    settings.update_settings(SettingsItem::TickRate);
+                                }
+                                SettingsItem::StrategyGauge => {
+                                    settings.update_settings(SettingsItem::StrategyGauge);
+                                }
+                                SettingsItem::NewTileHighlight => {
+                                    settings.update_settings(SettingsItem::NewTileHighlight);
+                                }
+                                SettingsItem::GameVariant => {
+                                    settings.update_settings(SettingsItem::GameVariant);
+                                    game = new_game(&game, &settings);
+                                    blitz_deadline = None;
+                                }
+                                SettingsItem::TargetValue => {
+                                    settings.update_settings(SettingsItem::TargetValue);
+                                }
+                                SettingsItem::AutosaveInterval => {
+                                    settings.update_settings(SettingsItem::AutosaveInterval);
+                                }
+                                SettingsItem::MergeSounds => {
+                                    settings.update_settings(SettingsItem::MergeSounds);
+                                }
                                 SettingsItem::GameSize => {
                                     settings.update_settings(SettingsItem::GameSize);
-                                    game.change_size(settings.game_size);
-                                    game = Grid::new(game.tile_width, game.size);
+                                    game = new_game(&game, &settings);
+                                    blitz_deadline = None;
+                                }
+                                SettingsItem::GameMode => {
+                                    settings.update_settings(SettingsItem::GameMode);
+                                    game = new_game(&game, &settings);
+                                    blitz_deadline = None;
+                                }
+                                SettingsItem::BlitzTime => {
+                                    settings.update_settings(SettingsItem::BlitzTime)
+                                }
+                                SettingsItem::ShowBoardGauge => {
+                                    settings.update_settings(SettingsItem::ShowBoardGauge)
+                                }
+                                SettingsItem::Solver => {
+                                    active_screen = Screen::SolverSettings(SolverItem::Depth)
                                 }
                             },
                             KeyCode::Esc => {
@@ -315,193 +2061,1390 @@ fn run_game<B: Backend>(
                             _ => (),
                         }
                     }
+                    Screen::SolverSettings(active_item) => {
+                        let mut settings = settings.write().unwrap();
+                        match event.code {
+                            KeyCode::Char('w') | KeyCode::Up => {
+                                let item = *active_item as u16 - 1;
+                                active_screen = Screen::SolverSettings(SolverItem::from(item));
+                            }
+                            KeyCode::Char('s') | KeyCode::Down => {
+                                let item = *active_item as u16 + 1;
+                                active_screen = Screen::SolverSettings(SolverItem::from(item));
+                            }
+                            KeyCode::Char('a') | KeyCode::Left => {
+                                settings.adjust_solver(*active_item, false)
+                            }
+                            KeyCode::Char('d') | KeyCode::Right => {
+                                settings.adjust_solver(*active_item, true)
+                            }
+                            KeyCode::Enter => match active_item {
+                                SolverItem::ResetDefaults => {
+                                    settings.solver = SolverConfig::default()
+                                }
+                                item => settings.adjust_solver(*item, true),
+                            },
+                            KeyCode::Esc => active_screen = Screen::Settings,
+                            _ => (),
+                        }
+                    }
+                    Screen::History(index) => match event.code {
+                        KeyCode::Char('a') | KeyCode::Left => {
+                            active_screen = Screen::History(index.saturating_sub(1));
+                        }
+                        KeyCode::Char('d') | KeyCode::Right => {
+                            let index = std::cmp::min(index + 1, game.history().len());
+                            active_screen = Screen::History(index);
+                        }
+                        KeyCode::Enter => {
+                            game.restore(*index);
+                            active_screen = Screen::Game;
+                        }
+                        KeyCode::Esc | KeyCode::Char('h') => active_screen = Screen::Game,
+                        _ => (),
+                    },
+                    Screen::AiDebug => match event.code {
+                        KeyCode::F(3) | KeyCode::Esc => active_screen = Screen::Game,
+                        _ => (),
+                    },
+                    Screen::Achievements => {
+                        if event.code == KeyCode::Esc {
+                            active_screen = Screen::Menu(MenuItem::Achievements);
+                        }
+                    }
+                    Screen::Statistics => match event.code {
+                        KeyCode::Esc => {
+                            export_status = None;
+                            active_screen = Screen::Menu(MenuItem::Statistics);
+                        }
+                        KeyCode::Char('e') => {
+                            let path = data_path(STATS_CSV_FILE);
+                            export_status = Some(match stats.export_csv(&path) {
+                                Ok(n) => format!("Exported {} games to {}", n, path.display()),
+                                Err(err) => format!("Export failed: {}", err),
+                            });
+                            // the exported games aren't exported again after a restart
+                            if let Err(err) = stats.save(&data_path(STATS_FILE)) {
+                                debug_log!(warn, "stats not saved: {}", err);
+                            }
+                        }
+                        _ => (),
+                    },
                     Screen::Info(_) => match event.code {
+                        // step through the snapshots of the finished game, stepping
+                        // past the last one brings the dialog back
+                        KeyCode::Char('a') | KeyCode::Left => {
+                            let last = game.history().len();
+                            review = Some(review.unwrap_or(last).saturating_sub(1));
+                        }
+                        KeyCode::Char('d') | KeyCode::Right => {
+                            review = review
+                                .map(|index| index + 1)
+                                .filter(|index| *index < game.history().len());
+                        }
+                        KeyCode::Esc if review.is_some() => review = None,
                         KeyCode::Enter => {
-                            game = Grid::new(game.tile_width, game.size);
+                            review = None;
+                            game = new_game(&game, &settings.read().unwrap());
+                            blitz_deadline = None;
                             active_screen = Screen::Game;
                         }
+                        KeyCode::Char('r') => {
+                            review = None;
+                            game = new_game_with_seed(&game, &settings.read().unwrap(), game.seed);
+                            blitz_deadline = None;
+                            active_screen = Screen::Game;
+                        }
+                        KeyCode::Char('n') => {
+                            review = None;
+                            let confirm = Screen::Confirm(ConfirmItem::NewGame);
+                            confirmed_from = Some(std::mem::replace(&mut active_screen, confirm));
+                        }
                         KeyCode::Esc => active_screen = Screen::Menu(MenuItem::Play),
                         _ => (),
                     },
-                }
-            }
-            Event::Tick => match &active_screen {
-                Screen::Game => match game.on_tick(None) {
-                    Err(err) if err == "Game Won" => {
-                        active_screen = Screen::Info(InfoItem::GameWon)
-                    }
-                    Err(err) if err == "Game Lost" => {
-                        active_screen = Screen::Info(InfoItem::GameLost)
-                    }
-                    _ => (),
-                },
-                _ => (),
+                    Screen::Confirm(ConfirmItem::NewGame) => match event.code {
+                        KeyCode::Char('y') | KeyCode::Enter => {
+                            game = new_game(&game, &settings.read().unwrap());
+                            blitz_deadline = None;
+                            active_screen = Screen::Game;
+                        }
+                        // back to the game or the info screen of the finished game
+                        KeyCode::Char('n') | KeyCode::Esc => {
+                            active_screen = confirmed_from.take().unwrap_or(Screen::Game)
+                        }
+                        _ => (),
+                    },
+                    Screen::Confirm(ConfirmItem::Quit) => match event.code {
+                        KeyCode::Char('y') | KeyCode::Enter => {
+                            disable_raw_mode()?;
+                            terminal.show_cursor()?;
+                            break;
+                        }
+                        // back to the game or its split layout
+                        KeyCode::Char('n') | KeyCode::Esc => {
+                            active_screen = confirmed_from.take().unwrap_or(Screen::Game)
+                        }
+                        _ => (),
+                    },
+                    Screen::Confirm(ConfirmItem::ResetAll) => match event.code {
+                        KeyCode::Char('y') | KeyCode::Enter => {
+                            game = new_game(&game, &settings.read().unwrap());
+                            blitz_deadline = None;
+                            best_score = 0;
+                            stats.clear();
+                            achievements.clear();
+                            export_status = None;
+                            remove_saved_data();
+                            active_screen = Screen::Game;
+                        }
+                        KeyCode::Char('n') | KeyCode::Esc => {
+                            active_screen = Screen::Menu(MenuItem::ResetAll)
+                        }
+                        _ => (),
+                    },
+                }
+            }
+            Event::Tick => {
+                tip_ticks += 1;
+                if tip_ticks.is_multiple_of(TIP_TICKS) {
+                    tip_index += 1;
+                }
+                if last_held.elapsed() > HOLD_TIMEOUT {
+                    held_keys.clear();
+                }
+                match &active_screen {
+                    Screen::Tutorial(_) => {
+                        let _ = game.on_tick(None);
+                    }
+                    Screen::Game | Screen::SplitLayout if paused => (),
+                    Screen::Game | Screen::SplitLayout if losing.is_some() => {
+                        losing = losing
+                            .and_then(|ticks| ticks.checked_sub(1))
+                            .filter(|t| *t > 0);
+                        if losing.is_none() {
+                            active_screen = Screen::Info(InfoItem::GameLost);
+                        }
+                    }
+                    Screen::Game | Screen::SplitLayout => {
+                        if game.mode == GameMode::Blitz {
+                            let total = Duration::from_secs(settings.read().unwrap().blitz_time);
+                            let deadline =
+                                *blitz_deadline.get_or_insert_with(|| Instant::now() + total);
+                            match deadline.checked_duration_since(Instant::now()) {
+                                Some(time_left) => {
+                                    game.score_multiplier = blitz_multiplier(time_left, total)
+                                }
+                                None => {
+                                    active_screen = Screen::Info(InfoItem::TimeUp);
+                                    continue;
+                                }
+                            }
+                        }
+                        match game.on_tick(None) {
+                            Err(err) if err == "Game Won" => {
+                                active_screen = Screen::Info(InfoItem::GameWon)
+                            }
+                            Err(err) if err == "Game Lost" => {
+                                match settings.read().unwrap().loss_animation {
+                                    LossAnimation::Off => {
+                                        active_screen = Screen::Info(InfoItem::GameLost)
+                                    }
+                                    _ => losing = Some(LOSS_ANIMATION_TICKS),
+                                }
+                            }
+                            _ => (),
+                        }
+                    }
+                    Screen::Menu(_)
+                        if settings.read().unwrap().idle_demo
+                            && last_input.elapsed() > IDLE_DEMO_AFTER =>
+                    {
+                        let board = demo.get_or_insert_with(|| {
+                            let mut board = new_game(&game, &settings.read().unwrap());
+                            board.mode = GameMode::Classic;
+                            board
+                        });
+                        let mv = match board.moving_tiles.len() {
+                            0 => solver::best_move(board, &settings.read().unwrap().solver),
+                            _ => None,
+                        };
+                        if board.on_tick(mv).is_err() {
+                            // the demo starts over once it's won or lost
+                            demo = None;
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    if let Some(logger) = logger.as_mut() {
+        logger.log_new(&game.event_log)?;
+    }
+
+    Ok(game)
+}
+
+pub fn render_menu<B>(f: &mut Frame<B>, active_item: &MenuItem, settings: &Settings)
+where
+    B: Backend,
+{
+    let layout = LayoutEngine::new(f.size(), *settings);
+    render_dialog_backdrop(f, layout.menu_dialog_rect());
+
+    let menu_text: Vec<Spans> = (1..=7)
+        .map(|n| {
+            let span = if *active_item as u16 == n {
+                Span::styled(
+                    MenuItem::from(n).to_string(),
+                    Style::default()
+                        .fg(Color::LightBlue)
+                        .add_modifier(Modifier::BOLD),
+                )
+            } else {
+                Span::raw(MenuItem::from(n).to_string())
+            };
+            Spans::from(vec![span])
+        })
+        .collect::<Vec<Spans>>();
+    let menu = Paragraph::new(menu_text).block(Block::default());
+
+    f.render_widget(menu, layout.menu_rect());
+    render_controls(
+        f,
+        &Screen::Menu(*active_item),
+        layout.menu_controls_rect(),
+        "",
+    );
+
+    let border = Block::default()
+        .borders(Borders::ALL)
+        .title("Menu")
+        .border_type(BorderType::Plain);
+    f.render_widget(border, layout.menu_dialog_rect());
+}
+
+pub fn render_settings<B>(f: &mut Frame<B>, settings: Arc<RwLock<Settings>>)
+where
+    B: Backend,
+{
+    let settings = settings.read().unwrap();
+    let layout = LayoutEngine::new(f.size(), *settings);
+    render_dialog_backdrop(f, layout.settings_dialog_rect());
+
+    let text: Vec<Spans> = (1..=26)
+        .map(|n| {
+            let spans = if settings.active_item as u16 == n {
+                vec![
+                    Span::styled(
+                        SettingsItem::from(n).to_string(),
+                        Style::default()
+                            .fg(Color::LightBlue)
+                            .add_modifier(Modifier::BOLD),
+                    ),
+                    Span::raw(" ".to_string() + &settings.get_value(SettingsItem::from(n))),
+                ]
+            } else {
+                vec![
+                    Span::raw(SettingsItem::from(n).to_string()),
+                    Span::raw(" ".to_string() + &settings.get_value(SettingsItem::from(n))),
+                ]
+            };
+            Spans::from(spans)
+        })
+        .collect::<Vec<Spans>>();
+    let menu = Paragraph::new(text).style(Style::default().fg(Color::White));
+
+    f.render_widget(menu, layout.settings_rect());
+    match settings.active_item {
+        SettingsItem::GameSize | SettingsItem::AnimationSpeed => {
+            render_settings_preview(f, &settings, layout.settings_preview_rect())
+        }
+        _ => render_controls(f, &Screen::Settings, layout.settings_preview_rect(), ""),
+    }
+
+    let border = Block::default()
+        .borders(Borders::ALL)
+        .style(Style::default().fg(Color::White))
+        .title("Settings")
+        .border_type(BorderType::Plain);
+    f.render_widget(border, layout.settings_dialog_rect());
+}
+
+/// a snake of growing tiles along the top two rows like a game going well, on the
+/// biggest boards it starts with the empty squares of the tiles too big for a u32
+fn settings_preview_tiles(size: u16) -> Vec<(Position, u32)> {
+    (0..size * 2)
+        .filter_map(|i| {
+            let (x, y) = (i % size, i / size);
+            let x = if y % 2 == 0 { size - 1 - x } else { x };
+            let n = 2_u32.checked_pow((size * 2 - i) as u32)?;
+            Some((Position::new(x, y), n))
+        })
+        .collect()
+}
+
+/// small board of the selected size with a few tiles on it
+pub fn render_settings_preview<B: Backend>(f: &mut Frame<B>, settings: &Settings, rect: Rect) {
+    let size = settings.game_size;
+    let tiles = settings_preview_tiles(size);
+    let preview = Grid::new_from_positions(6, size, &tiles);
+
+    let title = Paragraph::new(format!("Preview {}x{}", size, size));
+    f.render_widget(title, Rect { height: 1, ..rect });
+    render_minimap(
+        f,
+        &preview,
+        Rect {
+            y: rect.y + 2,
+            height: rect.height.saturating_sub(2),
+            ..rect
+        },
+    );
+}
+
+/// board drawn with a colored block for every square, two columns wide and one row tall
+pub fn render_minimap<B: Backend>(f: &mut Frame<B>, game: &Grid, rect: Rect) {
+    for x in 0..game.size {
+        for y in 0..game.size {
+            let square = Rect {
+                x: rect.x + x * 3,
+                y: rect.y + y,
+                width: 2,
+                height: 1,
+            };
+            if square.right() > rect.right() || square.bottom() > rect.bottom() {
+                continue;
+            }
+            let color = match game.tiles.get(&Position::new(x, y)) {
+                Some(tile) => get_bg_color_for_n(tile.n),
+                None => Color::DarkGray,
+            };
+            f.render_widget(Block::default().style(Style::default().bg(color)), square);
+        }
+    }
+}
+
+pub fn render_solver_settings<B>(f: &mut Frame<B>, settings: &Settings, active_item: &SolverItem)
+where
+    B: Backend,
+{
+    let mut text: Vec<Spans> = (1..=15)
+        .map(|n| {
+            let item = SolverItem::from(n);
+            let style = if *active_item as u16 == n {
+                Style::default()
+                    .fg(Color::LightBlue)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            Spans::from(vec![
+                Span::styled(item.to_string(), style),
+                Span::raw(" ".to_string() + &settings.get_solver_value(item)),
+            ])
+        })
+        .collect();
+    text.push(Spans::from(""));
+    text.push(Spans::from("Left/Right - Adjust"));
+    let menu = Paragraph::new(text).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::White))
+            .title("Solver")
+            .border_type(BorderType::Plain),
+    );
+
+    f.render_widget(menu, f.size());
+}
+
+pub fn render_statistics<B>(
+    f: &mut Frame<B>,
+    game: &Grid,
+    stats: &Stats,
+    solvability: Option<f64>,
+    export_status: &Option<String>,
+) where
+    B: Backend,
+{
+    let summary = game.event_summary();
+    let most_active_cell = match summary.most_active_cell {
+        Some(pos) => format!("({}, {})", pos.x, pos.y),
+        None => "-".to_string(),
+    };
+    let text: Vec<Spans> = vec![
+        Spans::from(vec![Span::raw(format!("Moves {}", summary.total_moves))]),
+        Spans::from(vec![Span::raw(format!("Merges {}", summary.total_merges))]),
+        Spans::from(vec![Span::raw(format!(
+            "Biggest merge {}",
+            summary.biggest_merge
+        ))]),
+        Spans::from(vec![Span::raw(format!(
+            "Most active square {}",
+            most_active_cell
+        ))]),
+        Spans::from(vec![Span::raw(format!(
+            "Mean displacement {:.2}",
+            game.mean_displacement()
+        ))]),
+        match solvability {
+            Some(value) => {
+                let color = match value {
+                    v if v >= 0.66 => Color::Green,
+                    v if v >= 0.33 => Color::Yellow,
+                    _ => Color::Red,
+                };
+                Spans::from(vec![Span::styled(
+                    format!("Solvability: {:.0}%", value * 100.0),
+                    Style::default().fg(color),
+                )])
+            }
+            None => Spans::from(vec![Span::raw("Solvability: measuring...")]),
+        },
+        Spans::from(""),
+        Spans::from(vec![Span::raw(format!(
+            "Games finished {} | Won {} | Best score {}",
+            stats.games.len(),
+            stats.wins(),
+            stats.best_score()
+        ))]),
+        Spans::from(vec![Span::styled(
+            match export_status {
+                Some(status) => status.clone(),
+                None => format!(
+                    "Press e to export the games to {}",
+                    data_path(STATS_CSV_FILE).display()
+                ),
             },
+            Style::default().fg(Color::Gray),
+        )]),
+    ];
+    let chunks = Layout::default()
+        .direction(tui::layout::Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(text.len() as u16 + 2),
+                Constraint::Min(0),
+            ]
+            .as_ref(),
+        )
+        .split(f.size());
+    let statistics = Paragraph::new(text).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::White))
+            .title("Statistics")
+            .border_type(BorderType::Plain),
+    );
+    f.render_widget(statistics, chunks[0]);
+
+    // share of the tiles on the board each value has, lowest value first
+    let distribution = game.tile_value_distribution();
+    let total = distribution.values().sum::<u32>().max(1) as f64;
+    let rows = distribution.iter().map(|(n, count)| {
+        Row::new(vec![
+            Cell::from(n.to_string()),
+            Cell::from(format!("{:>5}", count)),
+            Cell::from(format!("{:>6.1}%", *count as f64 / total * 100.0)),
+        ])
+    });
+    let table = Table::new(rows)
+        .header(
+            Row::new(vec!["Tile", "Count", "Share"]).style(Style::default().fg(Color::LightBlue)),
+        )
+        .widths(
+            [
+                Constraint::Length(10),
+                Constraint::Length(7),
+                Constraint::Length(8),
+            ]
+            .as_ref(),
+        )
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::White))
+                .title("Tiles")
+                .border_type(BorderType::Plain),
+        );
+    f.render_widget(table, chunks[1]);
+}
+
+/// weighted terms of the solver's evaluation of the board, each with a bar as long
+/// as its share of the largest term so one taking over stands out
+pub fn render_ai_debug<B>(f: &mut Frame<B>, game: &Grid, config: &SolverConfig)
+where
+    B: Backend,
+{
+    let components = solver::score_components(game, &config.weights);
+    let terms = [
+        ("Empty cells", components.empty_cells),
+        ("Monotonicity", components.monotonicity),
+        ("Smoothness", components.smoothness),
+        ("Corner bonus", components.corner_bonus),
+        ("Max tile", components.max_tile),
+        ("Concentration", components.concentration),
+        ("Compactness", components.compactness),
+        ("Openness", components.openness),
+        ("Perimeter", components.perimeter),
+        ("Potential merge", components.potential_merge),
+    ];
+    let largest = terms
+        .iter()
+        .map(|(_, value)| value.abs())
+        .fold(0.0, f64::max);
+    const BAR_WIDTH: f64 = 30.0;
+    let rows = terms.iter().map(|(name, value)| {
+        let length = match largest {
+            l if l > 0.0 => (value.abs() / l * BAR_WIDTH).round() as usize,
+            _ => 0,
+        };
+        // negative terms pull the value down, they get a bar of their own color
+        let color = if *value < 0.0 {
+            Color::Red
+        } else {
+            Color::Green
+        };
+        Row::new(vec![
+            Cell::from(*name),
+            Cell::from(format!("{:>8.2}", value)),
+            Cell::from("█".repeat(length)).style(Style::default().fg(color)),
+        ])
+    });
+    let total = Row::new(vec![
+        Cell::from("Total"),
+        Cell::from(format!("{:>8.2}", components.total)),
+        Cell::from(""),
+    ])
+    .style(Style::default().add_modifier(Modifier::BOLD));
+    let table = Table::new(rows.chain(std::iter::once(total)))
+        .header(Row::new(vec!["Term", "Value", ""]).style(Style::default().fg(Color::LightBlue)))
+        .widths(
+            [
+                Constraint::Length(15),
+                Constraint::Length(10),
+                Constraint::Length(BAR_WIDTH as u16),
+            ]
+            .as_ref(),
+        )
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::White))
+                .title("Solver evaluation - F3 to go back")
+                .border_type(BorderType::Plain),
+        );
+    f.render_widget(table, f.size());
+}
+
+pub fn render_blitz_hud<'a>(deadline: Instant, multiplier: u32) -> Vec<Spans<'a>> {
+    let time_left = deadline
+        .checked_duration_since(Instant::now())
+        .unwrap_or_else(|| Duration::from_secs(0));
+    vec![
+        Spans::from(vec![Span::styled(
+            format!("Time left {}s", time_left.as_secs()),
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )]),
+        Spans::from(vec![Span::raw(format!("Multiplier x{}", multiplier))]),
+    ]
+}
+
+/// what the merges of the last move added to the score, biggest first,
+/// e.g. "+16 (two 8s)", "+4 (two 2s)", "= +20", the other variants merge
+/// different tiles so only the tile made is named, e.g. "+5 (made a 5)"
+pub fn render_merges_hud(game: &Grid) -> Vec<Spans<'static>> {
+    if game.last_merges.is_empty() {
+        return vec![];
+    }
+    let mut lines: Vec<Spans> = game
+        .last_merges
+        .iter()
+        .sorted_by(|a, b| b.cmp(a))
+        .map(|n| {
+            let merge = match game.variant {
+                GameVariant::Standard => format!("+{} (two {}s)", n, n / game.merge_factor),
+                _ => format!("+{} (made a {})", n, n),
+            };
+            Spans::from(vec![Span::styled(merge, Style::default().fg(Color::Green))])
+        })
+        .collect();
+    let total: u32 = game.last_merges.iter().sum();
+    lines.push(Spans::from(vec![Span::styled(
+        format!("= +{}", total),
+        Style::default()
+            .fg(Color::Green)
+            .add_modifier(Modifier::BOLD),
+    )]));
+    lines
+}
+
+/// lives left in a hardcore game, flashing for a moment after a move that didn't do anything
+pub fn render_lives_hud(game: &Grid) -> Spans<'static> {
+    match game.life_lost_at {
+        Some(tick) if game.ticks.saturating_sub(tick) < LIFE_LOST_FLASH_TICKS => {
+            Spans::from(vec![Span::styled(
+                format!("Lives {} - nothing moved!", game.lives),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            )])
+        }
+        _ => Spans::from(vec![Span::raw(format!("Lives {}", game.lives))]),
+    }
+}
+
+/// the board as a table of large bold numbers in the place of the canvas tiles
+pub fn render_board_table<B>(f: &mut Frame<B>, game: &Grid, high_contrast: HighContrast)
+where
+    B: Backend,
+{
+    let rect = Rect {
+        x: game.coordinates().x,
+        y: game.coordinates().y,
+        width: game.width(),
+        height: game.height(),
+    };
+    let (_, background) = high_contrast.colors_for_n(0);
+    let empty = Style::default().bg(match high_contrast {
+        HighContrast::BlackOnWhite => Color::Gray,
+        _ => Color::DarkGray,
+    });
+    let (columns, rows) = game.visible_range();
+    let matrix = game.to_matrix();
+    let rows = matrix[rows.start as usize..rows.end as usize]
+        .iter()
+        .map(|row| {
+            let visible = &row[columns.start as usize..columns.end as usize];
+            let cells = visible.iter().map(|&n| {
+                if n == 0 {
+                    return Cell::from("").style(empty);
+                }
+                let (fg, bg) = high_contrast.colors_for_n(n);
+                // the number sits in the middle row of the tile
+                let mut lines = vec![Spans::from(""); (game.tile_height / 2) as usize];
+                lines.push(Spans::from(format!(
+                    "{:^width$}",
+                    n,
+                    width = game.tile_width as usize
+                )));
+                Cell::from(lines).style(Style::default().fg(fg).bg(bg).add_modifier(Modifier::BOLD))
+            });
+            Row::new(cells)
+                .height(game.tile_height)
+                .bottom_margin(game.margin_y)
+        });
+    let widths = vec![Constraint::Length(game.tile_width); columns.len()];
+    let table = Table::new(rows)
+        .widths(&widths)
+        .column_spacing(game.margin_x);
+
+    let block = Block::default()
+        .title("2048")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .style(Style::default().bg(background));
+    f.render_widget(block, rect);
+    // the first square starts at the same spot as on the canvas board
+    let inner = Rect {
+        x: rect.x + game.margin_x,
+        y: rect.y + game.margin_y,
+        width: rect.width.saturating_sub(game.margin_x + 1),
+        height: rect.height.saturating_sub(game.margin_y + 1),
+    };
+    f.render_widget(table, inner);
+}
+
+/// the board with all of its tiles, without the side panel
+pub fn render_board<B>(f: &mut Frame<B>, game: &mut Grid, settings: &Settings)
+where
+    B: Backend,
+{
+    let rect = Rect {
+        x: game.coordinates().x,
+        y: game.coordinates().y,
+        width: game.width(),
+        height: game.height(),
+    };
+    let title = if game.board_full() {
+        "2048 | Board full"
+    } else {
+        "2048"
+    };
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
+    f.render_widget(block, rect);
+    let (columns, rows) = game.visible_range();
+    for x in columns {
+        for y in rows.clone() {
+            let Coordinates { x, y } = game.get_coordinates_at(Position::new(x, y));
+            let rect = Rect {
+                x,
+                y,
+                width: game.tile_width,
+                height: game.tile_height,
+            };
+            let empty_tile = Block::default().style(Style::default().bg(Color::DarkGray));
+            f.render_widget(empty_tile, rect);
+        }
+    }
+    // render tiles, the moving ones last so they are drawn on top of the others
+    let tiles = game
+        .tiles_sorted()
+        .into_iter()
+        .sorted_by_key(|(pos, _)| game.moving_tiles.iter().any(|(p, _)| p == pos));
+    for (pos, tile) in tiles {
+        let mut rect = Rect {
+            x: tile.coordinates.x,
+            y: tile.coordinates.y,
+            width: game.tile_width,
+            height: game.tile_height,
+        };
+        // tiles of a scrolled board that are, or are sliding, out of view
+        let board = Rect {
+            x: game.coordinates().x + 1,
+            y: game.coordinates().y + 1,
+            width: game.width().saturating_sub(2),
+            height: game.height().saturating_sub(1),
+        };
+        if board.intersection(rect) != rect {
+            continue;
+        }
+        if let Some(spawned_at) = tile.spawned_at {
+            let age = game.ticks.saturating_sub(spawned_at);
+            if settings.spawn_animation && age < SPAWN_ANIMATION_TICKS {
+                // freshly spawned tiles start small and grow to their full size
+                let scale = (age + 1) as f64 / (SPAWN_ANIMATION_TICKS + 1) as f64;
+                rect = scale_rect(rect, scale);
+            }
+        }
+        let canvas = Canvas::default()
+            .marker(symbols::Marker::Braille)
+            .x_bounds([0.0, 10.0])
+            .y_bounds([0.0, 10.0])
+            .paint(|ctx| {
+                draw_number(ctx, tile.n, settings.tile_stroke_thickness);
+            });
+        f.render_widget(canvas, rect);
+        let bg = Block::default().style(Style::default().bg(get_bg_color_for_n(tile.n)));
+        f.render_widget(bg, rect);
+        if settings.new_tile_highlight && game.last_spawn == Some(pos) {
+            let age = tile
+                .spawned_at
+                .map_or(u64::MAX, |spawned_at| game.ticks.saturating_sub(spawned_at));
+            if age < NEW_TILE_HIGHLIGHT_TICKS {
+                let mut style = Style::default().fg(Color::White);
+                if age >= NEW_TILE_HIGHLIGHT_TICKS / 2 {
+                    style = style.add_modifier(Modifier::DIM);
+                }
+                let outline = Block::default().borders(Borders::ALL).border_style(style);
+                f.render_widget(outline, rect);
+            }
         }
     }
+}
+
+/// one line at the bottom of the game screen with the rules and the size of the board
+pub fn render_status_bar<B>(f: &mut Frame<B>, game: &Grid, rect: Rect)
+where
+    B: Backend,
+{
+    let status = format!(" {:?} | {}x{}", game.mode, game.size, game.size);
+    let status_bar =
+        Paragraph::new(status).style(Style::default().fg(Color::Black).bg(Color::Gray));
+    f.render_widget(status_bar, rect);
+}
+
+pub fn render_game<B>(
+    f: &mut Frame<B>,
+    layout: LayoutEngine,
+    game: &mut Grid,
+    settings: &Settings,
+    hud: Vec<Spans>,
+    screen: &Screen,
+    tip_index: usize,
+) where
+    B: Backend,
+{
+    // a board bigger than the terminal only shows what fits, making room for the side
+    // panel too once it has to scroll anyway, boards that fit are left alone
+    let (width, height) = layout.board_space();
+    game.fit_viewport(width, height);
+    if game.visible_range().0.len() < game.size as usize {
+        game.fit_viewport(
+            width.saturating_sub(SIDE_PANEL_GAP + SIDE_PANEL_WIDTH),
+            height,
+        );
+    }
+
+    let layout = layout.with_board(game.width(), game.height());
+    if let Some(rect) = layout.status_bar_rect() {
+        render_status_bar(f, game, rect);
+    }
+
+    let board = layout.game_rect();
+    let origin = Coordinates::new(board.x, board.y);
+    if game.coordinates() != origin {
+        game.mv(origin);
+        game.refresh_coordinates();
+    }
+    match settings.high_contrast {
+        HighContrast::Off => render_board(f, game, settings),
+        high_contrast => render_board_table(f, game, high_contrast),
+    }
+
+    if layout.sidebar_rect().is_none() {
+        return;
+    }
+
+    let mut hud_text = vec![Spans::from(vec![Span::styled(
+        format!("Score {}", game.score),
+        Style::default()
+            .fg(Color::White)
+            .add_modifier(Modifier::BOLD),
+    )])];
+    hud_text.extend(hud);
+    let hud_lines = hud_text.len() as u16;
+    let (hud_rect, mut gauges, controls) = match (
+        layout.hud_rect(hud_lines),
+        layout.gauges_rect(hud_lines),
+        layout.controls_rect(hud_lines),
+    ) {
+        (Some(hud), Some(gauges), Some(controls)) => (hud, gauges, controls),
+        _ => return,
+    };
+
+    f.render_widget(Paragraph::new(hud_text), hud_rect);
+    if settings.show_board_gauge {
+        render_board_gauge(f, game, gauges);
+        gauges.y += 1;
+        gauges.height = gauges.height.saturating_sub(1);
+    }
+    if settings.strategy_gauge {
+        render_strategy_gauge(f, game, &settings.solver.weights, gauges);
+    }
+    render_controls(f, screen, controls, &strategy_tip(game, tip_index));
+}
+
+/// statistics pane of the split layout: the score after every move of the history,
+/// how many tiles of each value are on the board and a few metrics
+pub fn render_live_statistics<B>(f: &mut Frame<B>, game: &Grid, layout: &LayoutEngine)
+where
+    B: Backend,
+{
+    let (score_chart, tile_chart, metrics_rect) = match (
+        layout.score_chart_rect(),
+        layout.tile_chart_rect(),
+        layout.metrics_rect(),
+    ) {
+        (Some(score), Some(tiles), Some(metrics)) => (score, tiles, metrics),
+        _ => return,
+    };
+
+    let scores: Vec<u64> = game
+        .history()
+        .iter()
+        .map(|snapshot| snapshot.score as u64)
+        .chain(std::iter::once(game.score as u64))
+        .collect();
+    let chart = Sparkline::default()
+        .block(Block::default().borders(Borders::ALL).title("Score"))
+        .data(&scores)
+        .style(Style::default().fg(Color::LightBlue));
+    f.render_widget(chart, score_chart);
+
+    let counts = game.tile_value_distribution();
+    let labels: Vec<String> = counts.keys().map(|n| abbreviate(*n)).collect();
+    let bars: Vec<(&str, u64)> = counts
+        .iter()
+        .zip(labels.iter())
+        .map(|((_, count), label)| (label.as_str(), *count as u64))
+        .collect();
+    let histogram = BarChart::default()
+        .block(Block::default().borders(Borders::ALL).title("Tiles"))
+        .data(&bars)
+        .bar_width(5)
+        .bar_style(Style::default().fg(Color::Yellow))
+        .value_style(Style::default().fg(Color::Black).bg(Color::Yellow));
+    f.render_widget(histogram, tile_chart);
+
+    let summary = game.event_summary();
+    let metrics = vec![
+        Spans::from(format!("Moves {}", summary.total_moves)),
+        Spans::from(format!("Merges {}", summary.total_merges)),
+        Spans::from(format!("Biggest merge {}", summary.biggest_merge)),
+        Spans::from(format!("Mean displacement {:.2}", game.mean_displacement())),
+        Spans::from(format!("Compactness {:.2}", game.compactness_score())),
+    ];
+    let metrics =
+        Paragraph::new(metrics).block(Block::default().borders(Borders::ALL).title("Metrics"));
+    f.render_widget(metrics, metrics_rect);
+}
+
+/// the split layout on a terminal too narrow for the statistics pane
+pub fn render_statistics_line<B>(f: &mut Frame<B>, game: &Grid, rect: Rect)
+where
+    B: Backend,
+{
+    let summary = game.event_summary();
+    let line = format!(
+        " Moves {} | Merges {} | Biggest merge {} | Compactness {:.2}",
+        summary.total_moves,
+        summary.total_merges,
+        summary.biggest_merge,
+        game.compactness_score()
+    );
+    let line = Paragraph::new(line).style(Style::default().fg(Color::Black).bg(Color::Gray));
+    f.render_widget(line, rect);
+}
+
+/// rect scaled around its center, it never gets smaller than a single cell
+fn scale_rect(rect: Rect, scale: f64) -> Rect {
+    let width = std::cmp::max((rect.width as f64 * scale).round() as u16, 1);
+    let height = std::cmp::max((rect.height as f64 * scale).round() as u16, 1);
+    Rect {
+        x: rect.x + (rect.width - width) / 2,
+        y: rect.y + (rect.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+pub fn render_board_gauge<B>(f: &mut Frame<B>, game: &Grid, rect: Rect)
+where
+    B: Backend,
+{
+    let ratio = game.occupied_fraction();
+    let color = match ratio {
+        r if r < 0.5 => Color::Green,
+        r if r <= 0.8 => Color::Yellow,
+        _ => Color::Red,
+    };
+    let gauge = LineGauge::default()
+        .gauge_style(Style::default().fg(color))
+        .line_set(symbols::line::THICK)
+        .label(format!("Board {:>3.0}%", ratio * 100.0))
+        .ratio(ratio);
+
+    f.render_widget(
+        gauge,
+        Rect {
+            height: std::cmp::min(rect.height, 1),
+            ..rect
+        },
+    );
+}
+
+/// how close the board is to the best arrangement of its tiles, see `solver::strategy_score`,
+/// with the empty cells, monotonicity and corner terms of the heuristic under it, both
+/// rated with the weights the solver plays with
+pub fn render_strategy_gauge<B>(f: &mut Frame<B>, game: &Grid, weights: &EvalWeights, rect: Rect)
+where
+    B: Backend,
+{
+    let ratio = solver::strategy_score(game, weights);
+    let color = match ratio {
+        r if r >= 0.8 => Color::Green,
+        r if r >= 0.5 => Color::Yellow,
+        _ => Color::Red,
+    };
+    let gauge = LineGauge::default()
+        .gauge_style(Style::default().fg(color))
+        .line_set(symbols::line::THICK)
+        .label(format!("Strategy {:>3.0}%", ratio * 100.0))
+        .ratio(ratio);
+    f.render_widget(
+        gauge,
+        Rect {
+            height: std::cmp::min(rect.height, 1),
+            ..rect
+        },
+    );
+    if rect.height < 2 {
+        return;
+    }
+    let components = solver::score_components(game, weights);
+    let corner = match game.max_tile_position() {
+        Some(pos) if game.is_corner(pos) => "✓",
+        _ => "✗",
+    };
+    let details = Paragraph::new(format!(
+        "Empty {} Mono {:.1} Corner {}",
+        (game.size * game.size) as usize - game.tiles.len(),
+        components.monotonicity,
+        corner
+    ))
+    .style(Style::default().fg(Color::DarkGray));
+    f.render_widget(
+        details,
+        Rect {
+            y: rect.y + 1,
+            height: 1,
+            ..rect
+        },
+    );
+}
+
+pub fn render_history<B>(
+    f: &mut Frame<B>,
+    board: &mut Grid,
+    settings: &Settings,
+    screen: &Screen,
+    index: usize,
+    history_len: usize,
+) where
+    B: Backend,
+{
+    let layout = LayoutEngine::new(f.size(), *settings);
+    render_game(f, layout, board, settings, vec![], screen, 0);
+
+    let rect = Rect {
+        x: board.coordinates().x,
+        y: board.coordinates().y + board.height(),
+        width: board.width(),
+        height: 2,
+    };
+
+    if rect.bottom() > f.size().bottom() {
+        return;
+    }
 
-    Ok(())
+    let text: Vec<Spans> = vec![
+        Spans::from(vec![Span::styled(
+            format!("Move {} of {}", index, history_len),
+            Style::default()
+                .fg(Color::LightBlue)
+                .add_modifier(Modifier::BOLD),
+        )]),
+        Spans::from(vec![Span::raw(match screen {
+            Screen::Info(_) => "Step - Left | Right, Back - ESC",
+            _ => "Step - Left | Right, Restore - ENTER",
+        })]),
+    ];
+    let info =
+        Paragraph::new(text).block(Block::default().style(Style::default().fg(Color::White)));
+
+    f.render_widget(info, rect);
 }
 
-pub fn render_menu<B>(f: &mut Frame<B>, active_item: &MenuItem)
+/// red frame around the board blinking while the loss animation plays
+fn render_loss_flash<B>(f: &mut Frame<B>, game: &Grid)
 where
     B: Backend,
 {
-    let chunks = Layout::default()
-        .direction(tui::layout::Direction::Horizontal)
-        .margin(2)
-        .constraints([Constraint::Length(20), Constraint::Length(50)].as_ref())
-        .split(f.size());
-
-    let menu_text: Vec<Spans> = (1..=4)
-        .map(|n| {
-            let span;
-            if *active_item as u16 == n {
-                span = Span::styled(
-                    MenuItem::from(n).to_string(),
-                    Style::default()
-                        .fg(Color::LightBlue)
-                        .add_modifier(Modifier::BOLD),
-                );
-            } else {
-                span = Span::raw(MenuItem::from(n).to_string())
-            }
-            Spans::from(vec![span])
-        })
-        .collect::<Vec<Spans>>();
-    let menu = Paragraph::new(menu_text).block(Block::default());
+    let rect = Rect {
+        x: game.coordinates().x,
+        y: game.coordinates().y,
+        width: game.width(),
+        height: game.height(),
+    };
+    if rect.right() > f.size().right() || rect.bottom() > f.size().bottom() {
+        return;
+    }
+    let frame = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Thick)
+        .border_style(Style::default().fg(Color::Red));
+    f.render_widget(frame, rect);
+}
 
-    f.render_widget(menu, chunks[0]);
-    render_controls(f, chunks[1]);
+/// area of the terminal the square at `pos` takes
+fn square_rect(game: &Grid, pos: Position) -> Rect {
+    let Coordinates { x, y } = game.get_coordinates_at(pos);
+    Rect {
+        x,
+        y,
+        width: game.tile_width,
+        height: game.tile_height,
+    }
+}
 
-    let border = Block::default()
+/// frame around the square the cursor is on with an arrow of the direction it will swipe in
+fn render_cursor<B>(f: &mut Frame<B>, game: &Grid, cursor: Cursor)
+where
+    B: Backend,
+{
+    let rect = square_rect(game, cursor.pos);
+    let size = f.size();
+    if !game.is_visible(cursor.pos) || rect.right() > size.right() || rect.bottom() > size.bottom()
+    {
+        return;
+    }
+    let arrow = match cursor.direction {
+        Some(Move::Up) => "↑",
+        Some(Move::Down) => "↓",
+        Some(Move::Left) => "←",
+        Some(Move::Right) => "→",
+        None => "",
+    };
+    let frame = Block::default()
+        .title(arrow)
         .borders(Borders::ALL)
-        .title("Menu")
-        .border_type(BorderType::Plain);
-    f.render_widget(border, f.size());
+        .border_type(BorderType::Thick)
+        .border_style(Style::default().fg(Color::LightYellow));
+    f.render_widget(frame, rect);
 }
 
-pub fn render_settings<B>(f: &mut Frame<B>, settings: Arc<RwLock<Settings>>)
+/// preview of the move while its direction is held: the tiles the move leaves alone
+/// are dimmed and the squares the others end up on are outlined, in yellow when
+/// the square is taken by a tile they merge into or that moves away first
+pub fn render_ghost_tiles<B>(f: &mut Frame<B>, game: &Grid, mv: Move)
 where
     B: Backend,
 {
-    let settings = settings.read().unwrap();
-    let text: Vec<Spans> = (1..=2)
-        .map(|n| {
-            let spans;
-            if settings.active_item as u16 == n {
-                spans = vec![
-                    Span::styled(
-                        SettingsItem::from(n).to_string(),
-                        Style::default()
-                            .fg(Color::LightBlue)
-                            .add_modifier(Modifier::BOLD),
-                    ),
-                    Span::raw(
-                        " ".to_string() + &settings.get_value(SettingsItem::from(n)).to_string(),
-                    ),
-                ];
-            } else {
-                spans = vec![
-                    Span::raw(SettingsItem::from(n).to_string()),
-                    Span::raw(
-                        " ".to_string() + &settings.get_value(SettingsItem::from(n)).to_string(),
-                    ),
-                ];
+    let moves = game.check(mv);
+    for (pos, _) in game.tiles_sorted() {
+        let affected = moves.iter().any(|(from, to)| *from == pos || *to == pos);
+        if affected || !game.is_visible(pos) {
+            continue;
+        }
+        let still = Block::default().style(Style::default().add_modifier(Modifier::DIM));
+        f.render_widget(still, square_rect(game, pos));
+    }
+    for (_, to) in moves {
+        if !game.is_visible(to) {
+            continue;
+        }
+        let ghost_tile = match game.tiles.contains_key(&to) {
+            true => Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(Style::default().fg(Color::LightYellow)),
+            false => Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .style(Style::default().bg(Color::Gray).add_modifier(Modifier::DIM)),
+        };
+        f.render_widget(ghost_tile, square_rect(game, to));
+    }
+}
+
+/// position and terminal coordinates of every square in its top left corner,
+/// the mean displacement of the tiles goes under the board next to the hints
+pub fn render_debug_coordinates<B>(f: &mut Frame<B>, game: &Grid)
+where
+    B: Backend,
+{
+    let size = f.size();
+    let (columns, rows) = game.visible_range();
+    for x in columns {
+        for y in rows.clone() {
+            let rect = square_rect(game, Position::new(x, y));
+            let (cx, cy) = (rect.x, rect.y);
+            if rect.right() > size.right() || rect.bottom() > size.bottom() {
+                continue;
             }
-            Spans::from(spans)
-        })
-        .collect::<Vec<Spans>>();
-    let menu = Paragraph::new(text).block(
+            let mut text = vec![
+                Spans::from(format!("{},{}", x, y)),
+                Spans::from(format!("{}:{}", cx, cy)),
+            ];
+            if let Some(tree) = game.trace_merge_tree(Position::new(x, y)) {
+                text.push(Spans::from(format!("depth: {}", tree.depth())));
+            }
+            if let Some(tile) = game.tiles.get(&Position::new(x, y)) {
+                text.push(Spans::from(format!("{}^{}", tile.n, tile.merge_count)));
+            }
+            let overlay = Paragraph::new(text).style(Style::default().fg(Color::Magenta));
+            f.render_widget(overlay, rect);
+        }
+    }
+
+    let rect = Rect {
+        x: game.coordinates().x,
+        y: game.coordinates().y + game.height() + 1,
+        width: game.width(),
+        height: 1,
+    };
+    if rect.bottom() > size.bottom() {
+        return;
+    }
+    let (centroid_x, centroid_y) = game.centroid();
+    let displacement = Paragraph::new(format!(
+        "Mean displacement {:.2} | Best row {} | Best column {} | ⊙ ({:.1}, {:.1}) | Open: {} cells",
+        game.mean_displacement(),
+        game.best_row_sum(),
+        game.best_column_sum(),
+        centroid_x,
+        centroid_y,
+        game.find_largest_empty_region()
+    ))
+    .style(Style::default().fg(Color::Magenta));
+    f.render_widget(displacement, rect);
+}
+
+/// box under the board explaining what the step of the tutorial asks for
+pub fn render_tutorial<B>(f: &mut Frame<B>, game: &Grid, step: TutorialStep)
+where
+    B: Backend,
+{
+    let size = f.size();
+    let x = game.coordinates().x;
+    let rect = Rect {
+        x,
+        y: game.coordinates().y + game.height(),
+        width: std::cmp::min(
+            std::cmp::max(game.width(), 40),
+            size.width.saturating_sub(x),
+        ),
+        height: 5,
+    };
+    if rect.bottom() > size.bottom() {
+        return;
+    }
+    let text = vec![
+        Spans::from(step.text()),
+        Spans::from(Span::styled(
+            "Skip - ESC",
+            Style::default().add_modifier(Modifier::DIM),
+        )),
+    ];
+    let tutorial = Paragraph::new(text).wrap(Wrap { trim: true }).block(
         Block::default()
+            .title("Tutorial")
             .borders(Borders::ALL)
-            .style(Style::default().fg(Color::White))
-            .title("Settings")
-            .border_type(BorderType::Plain),
+            .border_style(Style::default().fg(Color::LightBlue)),
     );
-
-    f.render_widget(menu, f.size());
+    f.render_widget(tutorial, rect);
 }
 
-pub fn render_game<B>(f: &mut Frame<B>, game: &mut Grid)
+pub fn render_hints<B>(f: &mut Frame<B>, game: &Grid)
 where
     B: Backend,
 {
-    // render the grid
+    let moves = [Move::Up, Move::Down, Move::Left, Move::Right];
+    let risky: Vec<String> = moves
+        .iter()
+        .filter(|mv| game.leaves_corner(**mv))
+        .map(|mv| format!("{:?}", mv))
+        .collect();
+    let biggest = moves
+        .iter()
+        .map(|mv| (*mv, game.largest_merge_in_move(*mv)))
+        .filter(|(_, n)| *n > 0)
+        .max_by_key(|(_, n)| *n);
+
+    // the largest tile has no wall to lean on in the middle of the board
+    let stranded = match game.max_tile_position() {
+        Some(pos) => game.interior_tiles().iter().any(|(p, _)| *p == pos),
+        None => false,
+    };
+
     let rect = Rect {
-        x: game.coordinates.x,
-        y: game.coordinates.y,
+        x: game.coordinates().x,
+        y: game.coordinates().y + game.height(),
         width: game.width(),
-        height: game.height(),
+        height: 1,
     };
-    let block = Block::default()
-        .title("2048")
-        .borders(Borders::ALL)
-        .border_type(BorderType::Rounded);
-    f.render_widget(block, rect);
-    for x in 0..game.size {
-        for y in 0..game.size {
-            let Coordinates { x, y } = game.get_coordinates_at(Position::new(x, y));
-            let rect = Rect {
-                x,
-                y,
-                width: game.tile_width,
-                height: game.tile_height,
-            };
-            let empty_tile = Block::default().style(Style::default().bg(Color::DarkGray));
-            f.render_widget(empty_tile, rect);
+
+    if (risky.is_empty() && biggest.is_none() && !stranded) || rect.bottom() > f.size().bottom() {
+        return;
+    }
+
+    let mut text = vec![];
+    if let Some((mv, n)) = biggest {
+        text.push(Span::styled(
+            format!("Hint: {:?} creates {}", mv, n),
+            Style::default().fg(Color::LightGreen),
+        ));
+    }
+    if !risky.is_empty() {
+        if !text.is_empty() {
+            text.push(Span::raw(" | "));
         }
+        text.push(Span::styled(
+            format!(
+                "Careful: {} moves your largest tile out of its corner",
+                risky.join(", ")
+            ),
+            Style::default().fg(Color::Yellow),
+        ));
     }
-    // render tiles
-    for (_, tile) in game.tiles.iter() {
-        let rect = Rect {
-            x: tile.coordinates.x,
-            y: tile.coordinates.y,
-            width: game.tile_width,
-            height: game.tile_height,
-        };
-        let canvas = Canvas::default()
-            .marker(symbols::Marker::Braille)
-            .x_bounds([0.0, 10.0])
-            .y_bounds([0.0, 10.0])
-            .paint(|ctx| {
-                draw_number(ctx, tile.n);
-            });
-        f.render_widget(canvas, rect);
-        let tile = Block::default().style(Style::default().bg(get_bg_color_for_n(tile.n)));
-        f.render_widget(tile, rect);
+    if stranded {
+        if !text.is_empty() {
+            text.push(Span::raw(" | "));
+        }
+        text.push(Span::styled(
+            "Bring your largest tile back to the edge",
+            Style::default().fg(Color::Yellow),
+        ));
     }
+    let hint = Paragraph::new(Spans::from(text));
 
-    let rect = Rect {
-        x: game.coordinates.x + game.width() + 5,
-        y: 1,
-        width: 25,
-        height: game.height() - 1,
-    };
+    f.render_widget(hint, rect);
+}
 
-    if rect.right() > f.size().right() || rect.bottom() > f.size().bottom() {
-        // to make sure the controls don't go outside of the terminal
-        // and cause errors they won't be rendered if they don't fit
-        return;
+/// tip shown under the controls of the game screen, the next one of `TIPS` unless
+/// no merge on the board comes close to the largest tile or one value fills the board
+fn strategy_tip(game: &Grid, tip_index: usize) -> String {
+    if game.largest_potential_merge().unwrap_or(0) < game.max_tile_value() / 4 {
+        return "Nothing big can merge, reorganize the board around your largest tile".to_string();
     }
-
-    render_controls(f, rect);
+    if let Some(n) = game.dominant_value() {
+        let count = game.tile_value_distribution()[&n];
+        // half of the tiles sharing a value that covers a row's worth of squares
+        if count >= game.size as u32 && count * 2 > game.tiles.len() as u32 {
+            return format!("You have many {}s: make more merges.", n);
+        }
+    }
+    TIPS[tip_index % TIPS.len()].to_string()
 }
 
-pub fn render_controls<B>(f: &mut Frame<B>, rect: Rect)
+/// the controls of the screen, on the game screen followed by one of the tips
+pub fn render_controls<B>(f: &mut Frame<B>, screen: &Screen, rect: Rect, tip: &str)
 where
     B: Backend,
 {
-    let controls_text: Vec<Spans> = vec![
-        Spans::from(vec![Span::raw("Controls")]),
-        Spans::from(vec![Span::raw("Up - Arrow Up | W")]),
-        Spans::from(vec![Span::raw("Down - Arrow Down | S")]),
-        Spans::from(vec![Span::raw("Left - Arrow Left | A")]),
-        Spans::from(vec![Span::raw("Right - Arrow Right | D")]),
-        Spans::from(vec![Span::raw("Quit - Q")]),
-        Spans::from(vec![Span::raw("Select - ENTER")]),
-        Spans::from(vec![Span::raw("Back - ESC")]),
-    ];
+    let controls: &[&str] = match screen {
+        Screen::Game | Screen::SplitLayout | Screen::Tutorial(_) => &[
+            "Up - Arrow Up | W",
+            "Down - Arrow Down | S",
+            "Left - Arrow Left | A",
+            "Right - Arrow Right | D",
+            "Preview - Shift + Direction",
+            "History / Undo - H",
+            "Scroll - Ctrl + Arrows",
+            "Statistics - Tab",
+            "Solver Evaluation - F3",
+            "Colors - T",
+            "New Game - N",
+            "Menu - ESC",
+            "Quit - Q",
+        ],
+        Screen::Menu(_) => &[
+            "Up - Arrow Up | W",
+            "Down - Arrow Down | S",
+            "Select - ENTER",
+            "Quit - Q | ESC",
+        ],
+        Screen::Settings | Screen::SolverSettings(_) => &[
+            "Up - Arrow Up | W",
+            "Down - Arrow Down | S",
+            "Change - ENTER",
+            "Back - ESC",
+        ],
+        Screen::History(_) => &["Step - Arrow Left | Right", "Restore - ENTER", "Back - ESC"],
+        Screen::Info(_) => &[
+            "Step - Arrow Left | Right",
+            "Play again - ENTER",
+            "Replay the seed - R",
+            "Back - ESC",
+        ],
+        _ => &["Back - ESC", "Quit - Q"],
+    };
+    let mut controls_text: Vec<Spans> = vec![Spans::from(vec![Span::raw("Controls")])];
+    controls_text.extend(
+        controls
+            .iter()
+            .map(|line| Spans::from(vec![Span::raw(*line)])),
+    );
+    if let Screen::Game | Screen::SplitLayout = screen {
+        controls_text.push(Spans::from(""));
+        controls_text.push(Spans::from(format!("Tip: {}", tip)));
+    }
     let controls = Paragraph::new(controls_text)
         .block(Block::default().style(Style::default().fg(Color::DarkGray)));
 
@@ -512,6 +3455,8 @@ pub fn render_error<B>(f: &mut Frame<B>, error: String)
 where
     B: Backend,
 {
+    let rect = dialog_rect(f.size(), error.chars().count() as u16 + 4, 3);
+    render_dialog_backdrop(f, rect);
     let text = Paragraph::new(error)
         .style(Style::default().fg(Color::LightCyan))
         .block(
@@ -520,15 +3465,114 @@ where
                 .style(Style::default().fg(Color::White))
                 .title("Error")
                 .border_type(BorderType::Plain),
+        )
+        .wrap(Wrap { trim: true });
+    f.render_widget(text, rect);
+}
+
+/// every achievement with its description, the unlocked ones highlighted
+pub fn render_achievements<B>(f: &mut Frame<B>, achievements: &Achievements)
+where
+    B: Backend,
+{
+    let rows = achievements.list.iter().map(|achievement| {
+        let (mark, style) = match achievement.unlocked {
+            true => ("★", Style::default().fg(Color::Yellow)),
+            false => ("☆", Style::default().fg(Color::DarkGray)),
+        };
+        Row::new(vec![
+            Cell::from(mark),
+            Cell::from(achievement.name),
+            Cell::from(achievement.description),
+        ])
+        .style(style)
+    });
+    let title = format!(
+        "Achievements {}/{} - ESC to go back",
+        achievements.unlocked_count(),
+        achievements.list.len()
+    );
+    let table = Table::new(rows)
+        .widths(
+            [
+                Constraint::Length(2),
+                Constraint::Length(14),
+                Constraint::Min(0),
+            ]
+            .as_ref(),
+        )
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::White))
+                .title(title)
+                .border_type(BorderType::Plain),
         );
-    f.render_widget(text, f.size());
+    f.render_widget(table, f.size());
 }
 
-pub fn render_info<B>(f: &mut Frame<B>, title: &str, message: &str)
+/// box in the top right corner announcing the achievements a game just unlocked
+pub fn render_toast<B>(f: &mut Frame<B>, names: &str)
 where
     B: Backend,
 {
     let size = f.size();
+    let width = std::cmp::min(names.chars().count() as u16 + 4, size.width);
+    let rect = Rect {
+        x: size.right() - width,
+        y: size.y,
+        width,
+        height: std::cmp::min(3, size.height),
+    };
+    let toast = Paragraph::new(Span::styled(
+        names,
+        Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::BOLD),
+    ))
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Achievement unlocked")
+            .style(Style::default().bg(Color::Black)),
+    );
+    f.render_widget(Clear, rect);
+    f.render_widget(toast, rect);
+}
+
+pub fn render_confirm<B>(f: &mut Frame<B>, title: &str, question: &str)
+where
+    B: Backend,
+{
+    let text: Vec<Spans> = vec![
+        Spans::from(vec![Span::styled(
+            question,
+            Style::default()
+                .fg(Color::LightBlue)
+                .add_modifier(Modifier::BOLD),
+        )]),
+        Spans::from(vec![Span::raw("Press y to confirm or n to cancel.")]),
+    ];
+    let width = std::cmp::max(question.chars().count(), 34) as u16 + 4;
+    let rect = dialog_rect(f.size(), width, 4);
+    render_dialog_backdrop(f, rect);
+    let confirm = Paragraph::new(text).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::White))
+            .title(title)
+            .border_type(BorderType::Plain),
+    );
+    f.render_widget(confirm, rect);
+}
+
+pub fn render_info<B>(f: &mut Frame<B>, title: &str, message: &str, seed: u64)
+where
+    B: Backend,
+{
+    // wide enough for the line about what to press next
+    let rect = dialog_rect(f.size(), 66, 6);
+    render_dialog_backdrop(f, rect);
     let text: Vec<Spans> = vec![
         Spans::from(vec![Span::styled(
             message,
@@ -536,7 +3580,16 @@ where
                 .fg(Color::LightBlue)
                 .add_modifier(Modifier::BOLD),
         )]),
-        Spans::from(vec![Span::raw("Press enter to reset and play again.")]),
+        Spans::from(vec![Span::raw(
+            "Press enter to reset and play again or n to start a new game.",
+        )]),
+        Spans::from(vec![Span::raw(format!(
+            "Seed {} - press r to replay the same tiles.",
+            seed
+        ))]),
+        Spans::from(vec![Span::raw(
+            "Press left to look back through the moves.",
+        )]),
     ];
     let info = Paragraph::new(text).block(
         Block::default()
@@ -545,5 +3598,232 @@ where
             .title(title)
             .border_type(BorderType::Plain),
     );
-    f.render_widget(info, size);
+    f.render_widget(info, rect);
+}
+
+/// dim everything around a dialog and clear the box it's drawn into
+pub fn render_dialog_backdrop<B>(f: &mut Frame<B>, rect: Rect)
+where
+    B: Backend,
+{
+    f.render_widget(
+        Block::default().style(Style::default().add_modifier(Modifier::DIM)),
+        f.size(),
+    );
+    f.render_widget(Clear, rect);
+    f.render_widget(
+        Block::default().style(Style::default().bg(Color::Black)),
+        rect,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// every number from 1 to `last` is its own item, 0 wraps to the last one and
+    /// the number past the last one to the first
+    fn assert_numbering<T: From<u16>>(last: u16, number: fn(T) -> u16) {
+        for n in 1..=last {
+            assert_eq!(number(T::from(n)), n);
+        }
+        assert_eq!(number(T::from(0)), last);
+        assert_eq!(number(T::from(last + 1)), 1);
+    }
+
+    #[test]
+    fn environment_overrides_the_settings() {
+        std::env::set_var("RUST2048_GAME_SIZE", "6");
+        std::env::set_var("RUST2048_ANIMATION_SPEED", "1");
+        std::env::set_var("RUST2048_WIN_TARGET", "500");
+        std::env::set_var("RUST2048_HINTS", "maybe");
+        let settings = Settings::load();
+        for name in ["GAME_SIZE", "ANIMATION_SPEED", "WIN_TARGET", "HINTS"] {
+            std::env::remove_var(format!("RUST2048_{}", name));
+        }
+
+        assert_eq!(settings.game_size, 6);
+        assert_eq!(settings.animation_speed, 1);
+        // rounded up to a tile of the standard game, the hints flag doesn't parse
+        assert_eq!(settings.win_target, Some(512));
+        assert!(!settings.hints);
+        assert_eq!(
+            settings.env_overrides(),
+            ["GameSize is 6", "AnimationSpeed is 1", "WinTarget is 512"]
+        );
+    }
+
+    #[test]
+    fn settings_preview_fits_every_board_size() {
+        let tiles = settings_preview_tiles(4);
+        assert_eq!(tiles[0], (Position::new(3, 0), 256));
+        assert_eq!(tiles[7], (Position::new(3, 1), 2));
+        let tiles = settings_preview_tiles(16);
+        assert_eq!(tiles.len(), 31);
+        assert_eq!(tiles[0], (Position::new(14, 0), 1 << 31));
+    }
+
+    #[test]
+    fn settings_items_wrap_around() {
+        assert_numbering::<SettingsItem>(SettingsItem::MergeSounds as u16, |item| item as u16);
+    }
+
+    #[test]
+    fn menu_items_wrap_around() {
+        assert_numbering::<MenuItem>(MenuItem::Exit as u16, |item| item as u16);
+    }
+
+    #[test]
+    fn solver_items_wrap_around() {
+        assert_numbering::<SolverItem>(SolverItem::ResetDefaults as u16, |item| item as u16);
+    }
+
+    #[test]
+    fn only_the_same_move_soon_after_is_a_held_key() {
+        let pressed = Instant::now();
+        let last = Some((Move::Left, pressed));
+        assert!(is_held_repeat(last, Move::Left, pressed + HOLD_TIMEOUT / 2));
+        assert!(!is_held_repeat(
+            last,
+            Move::Right,
+            pressed + HOLD_TIMEOUT / 2
+        ));
+        assert!(!is_held_repeat(last, Move::Left, pressed + HOLD_TIMEOUT));
+        assert!(!is_held_repeat(None, Move::Left, pressed));
+    }
+
+    #[test]
+    fn confirmed_new_game_starts_from_a_clean_board() {
+        let mut settings = Settings::new();
+        settings.game_size = 5;
+        let mut played = Grid::from_string("2 2 0 0\n4 4 0 0\n0 0 0 0\n0 0 0 0").unwrap();
+        played.mv(Coordinates::new(3, 2));
+        let _ = played.on_tick(Some(Move::Left));
+        while !played.moving_tiles.is_empty() {
+            let _ = played.on_tick(None);
+        }
+        assert!(played.score > 0 && played.moves > 0);
+
+        let fresh = new_game(&played, &settings);
+        assert_eq!(fresh.score, 0);
+        assert_eq!(fresh.moves, 0);
+        assert!(fresh.history().is_empty());
+        assert_eq!(fresh.size, 5);
+        assert_eq!(fresh.tiles.len(), 2);
+        assert_eq!(fresh.coordinates(), played.coordinates());
+    }
+
+    #[test]
+    fn merges_hud_breaks_the_score_down() {
+        let game = Grid::from_string("2 2 8 8\n0 0 0 0\n0 0 0 0\n0 0 0 0")
+            .unwrap()
+            .simulate(Move::Left);
+        let lines: Vec<String> = render_merges_hud(&game)
+            .iter()
+            .map(|line| line.0.iter().map(|span| span.content.as_ref()).collect())
+            .collect();
+        assert_eq!(lines, ["+16 (two 8s)", "+4 (two 2s)", "= +20"]);
+    }
+
+    #[test]
+    fn only_reset_all_deletes_the_saved_data() {
+        let dir = std::env::temp_dir().join(format!("rust2048_reset_{}", std::process::id()));
+        std::env::set_var("RUST2048_DATA_DIR", &dir);
+        let files = [
+            STATS_FILE,
+            ACHIEVEMENTS_FILE,
+            TUTORIAL_DONE_FILE,
+            AUTOSAVE_FILE,
+        ];
+        std::fs::create_dir_all(&dir).unwrap();
+        for file in files.iter().chain([&STATS_CSV_FILE]) {
+            std::fs::write(data_path(file), "{}").unwrap();
+        }
+
+        let game = Grid::new(6, 4);
+        new_game(&game, &Settings::new());
+        assert!(files.iter().all(|file| data_path(file).exists()));
+
+        remove_saved_data();
+        assert!(files.iter().all(|file| !data_path(file).exists()));
+        // the exported games are the player's, not the game's
+        assert!(data_path(STATS_CSV_FILE).exists());
+
+        std::env::remove_var("RUST2048_DATA_DIR");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn tick_rate_stays_within_the_settings_at_the_extremes() {
+        let ms = |speed, base| tick_rate(speed, base).as_millis();
+        assert_eq!(ms(3, 10), 10);
+        assert_eq!(ms(1, 160), 3 * 160);
+        assert_eq!(ms(3, BASE_TICK_RATE), BASE_TICK_RATE as u128);
+        // out of range values are clamped rather than ticking as fast as possible
+        assert_eq!(ms(0, 0), 3 * 10);
+        assert_eq!(ms(u16::MAX, u64::MAX), 160);
+    }
+
+    #[test]
+    fn quitting_asks_only_when_a_scored_game_would_be_lost() {
+        let fresh = Grid::new(6, 4);
+        let mut scored = fresh.clone();
+        scored.score = 4;
+        assert!(should_confirm_quit(&Screen::Game, &scored));
+        assert!(should_confirm_quit(&Screen::SplitLayout, &scored));
+        assert!(!should_confirm_quit(&Screen::Game, &fresh));
+        assert!(!should_confirm_quit(&Screen::Menu(MenuItem::Play), &scored));
+        assert!(!should_confirm_quit(&Screen::Settings, &scored));
+    }
+
+    #[test]
+    fn color_key_cycles_through_every_contrast_and_back() {
+        let mut settings = Settings::new();
+        let mut seen = vec![settings.high_contrast];
+        for _ in 0..4 {
+            settings.update_settings(SettingsItem::HighContrast);
+            seen.push(settings.high_contrast);
+        }
+        assert_eq!(
+            seen,
+            vec![
+                HighContrast::Off,
+                HighContrast::TileColors,
+                HighContrast::WhiteOnBlack,
+                HighContrast::BlackOnWhite,
+                HighContrast::Off,
+            ]
+        );
+        assert_eq!(settings.get_value(SettingsItem::HighContrast), "Off");
+    }
+
+    #[test]
+    fn autosave_is_written_every_interval_and_loads_back() {
+        let path =
+            std::env::temp_dir().join(format!("rust2048_autosave_{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut game = Grid::new_with_seed(4, 6, Coordinates::new(0, 0), 2, 5);
+        let mut autosaved_at = game.moves;
+        for mv in [Move::Left, Move::Up, Move::Right, Move::Down]
+            .iter()
+            .cycle()
+        {
+            if game.moves == 3 {
+                break;
+            }
+            assert!(!path.exists(), "saved after {} moves", game.moves);
+            let _ = game.on_tick(Some(*mv));
+            while !game.moving_tiles.is_empty() {
+                let _ = game.on_tick(None);
+            }
+            autosave(&game, 3, &mut autosaved_at, &path);
+        }
+
+        let saved = SavedGame::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let loaded = Grid::from_saved(&saved, 6).unwrap();
+        assert_eq!(loaded.to_matrix(), game.to_matrix());
+        assert_eq!(loaded.score, game.score);
+        assert_eq!(loaded.moves, game.moves);
+    }
 }
@@ -0,0 +1,118 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::time::Instant;
+
+/// number of frames after which the csv gets flushed to the disk
+const FLUSH_INTERVAL: u64 = 100;
+
+/// Instants at which the phases of a single frame finished
+#[derive(Debug, Clone, Copy)]
+struct FrameTimes {
+    start: Instant,
+    rendered: Option<Instant>,
+    polled: Option<Instant>,
+}
+
+/// Profiler writes the timing of every frame of the main loop to a csv file,
+/// one line per frame: frame_number, render_us, event_poll_us, tick_us, total_us
+pub struct Profiler {
+    writer: BufWriter<File>,
+    frame_number: u64,
+    current: Option<FrameTimes>,
+    totals: Vec<u128>,
+}
+
+impl Profiler {
+    pub fn new(path: &str) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(
+            writer,
+            "frame_number,render_us,event_poll_us,tick_us,total_us"
+        )?;
+        Ok(Self {
+            writer,
+            frame_number: 0,
+            current: None,
+            totals: vec![],
+        })
+    }
+
+    /// finish the previous frame if there was one and start measuring a new one
+    pub fn start_frame(&mut self) -> io::Result<()> {
+        self.end_frame()?;
+        self.current = Some(FrameTimes {
+            start: Instant::now(),
+            rendered: None,
+            polled: None,
+        });
+        Ok(())
+    }
+
+    pub fn rendered(&mut self) {
+        if let Some(frame) = self.current.as_mut() {
+            frame.rendered = Some(Instant::now());
+        }
+    }
+
+    pub fn polled(&mut self) {
+        if let Some(frame) = self.current.as_mut() {
+            frame.polled = Some(Instant::now());
+        }
+    }
+
+    fn end_frame(&mut self) -> io::Result<()> {
+        let frame = match self.current.take() {
+            Some(frame) => frame,
+            None => return Ok(()),
+        };
+        let end = Instant::now();
+        let rendered = frame.rendered.unwrap_or(end);
+        let polled = frame.polled.unwrap_or(end);
+
+        let render = rendered.duration_since(frame.start);
+        let event_poll = polled.duration_since(rendered);
+        let tick = end.duration_since(polled);
+        let total = end.duration_since(frame.start);
+
+        self.frame_number += 1;
+        self.totals.push(total.as_micros());
+        writeln!(
+            self.writer,
+            "{},{},{},{},{}",
+            self.frame_number,
+            render.as_micros(),
+            event_poll.as_micros(),
+            tick.as_micros(),
+            total.as_micros()
+        )?;
+        if self.frame_number.is_multiple_of(FLUSH_INTERVAL) {
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// flush the csv and summarize the frame time percentiles, to be printed once
+    /// the terminal is restored
+    pub fn finish(mut self) -> io::Result<String> {
+        self.end_frame()?;
+        self.writer.flush()?;
+
+        self.totals.sort_unstable();
+        Ok(format!(
+            "frames: {}, total frame time P50: {}us, P95: {}us, P99: {}us",
+            self.frame_number,
+            percentile(&self.totals, 50.0),
+            percentile(&self.totals, 95.0),
+            percentile(&self.totals, 99.0)
+        ))
+    }
+}
+
+/// percentile of already sorted values
+fn percentile(sorted: &[u128], p: f64) -> u128 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let index = (p / 100.0 * (sorted.len() - 1) as f64).round() as usize;
+    sorted[index]
+}
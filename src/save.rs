@@ -0,0 +1,147 @@
+use crate::game::{GameMode, GameVariant, MergePreference, Move, Position};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Event of the saved game log, a `GameEvent` with its time in milliseconds since
+/// the first event of the game
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum SavedEvent {
+    Moved {
+        ms: u64,
+        direction: Move,
+    },
+    Merged {
+        ms: u64,
+        from: Position,
+        to: Position,
+        value: u32,
+    },
+    Spawned {
+        ms: u64,
+        at: Position,
+        value: u32,
+    },
+    Won {
+        ms: u64,
+    },
+    Lost {
+        ms: u64,
+    },
+}
+
+impl SavedEvent {
+    pub fn ms(&self) -> u64 {
+        match self {
+            SavedEvent::Moved { ms, .. }
+            | SavedEvent::Merged { ms, .. }
+            | SavedEvent::Spawned { ms, .. }
+            | SavedEvent::Won { ms }
+            | SavedEvent::Lost { ms } => *ms,
+        }
+    }
+}
+
+/// Everything needed to pick a game up again, see `Grid::to_saved` and `Grid::from_saved`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedGame {
+    /// rows of space separated values, 0 for an empty square, see `Grid::from_string`
+    pub board: String,
+    /// estimated from the tiles when it's left out, see `Grid::compute_score_for_state`
+    #[serde(default)]
+    pub score: Option<u32>,
+    pub moves: u64,
+    pub mode: GameMode,
+    pub variant: GameVariant,
+    pub merge_factor: u32,
+    pub target: Option<u32>,
+    /// the game goes on spawning the same tiles it would have
+    pub seed: u64,
+    pub spawned: u64,
+    pub game_id: u64,
+    pub merge_preference: MergePreference,
+    pub merge_limit: Option<u32>,
+    pub merge_linger: u16,
+    pub margin_x: u16,
+    pub margin_y: u16,
+    /// the event log of the game, empty in a save made before it was kept
+    #[serde(default)]
+    pub events: Vec<SavedEvent>,
+}
+
+impl SavedGame {
+    /// write the game as json next to `path` first and then move it over the old save
+    /// once it's on the disk, a crash halfway through leaves the old save untouched
+    pub fn write_atomic(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let mut file = File::create(&tmp)?;
+        file.write_all(serde_json::to_string(self)?.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)
+    }
+
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let json = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Grid, DEFAULT_TILE_WIDTH};
+
+    fn saved_board(rows: &str) -> SavedGame {
+        let mut saved = Grid::from_string("2 0\n0 2").unwrap().to_saved();
+        saved.board = rows.to_string();
+        saved
+    }
+
+    #[test]
+    fn saved_game_loads_back_with_its_rules() {
+        let mut game = Grid::from_string("2 4 0 0\n0 8 0 0\n0 0 16 0\n0 0 0 2").unwrap();
+        game.score = 40;
+        game.merge_preference = MergePreference::AwayFromMove;
+        game.merge_limit = Some(1);
+        game.merge_linger = 3;
+        game.change_margins(1, 0);
+
+        let path = std::env::temp_dir().join(format!("rust2048_{}_save.json", std::process::id()));
+        game.to_saved().write_atomic(&path).unwrap();
+        let saved = SavedGame::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let loaded = Grid::from_saved(&saved, DEFAULT_TILE_WIDTH).unwrap();
+        assert!(loaded.same_tiles(&game));
+        assert_eq!(loaded.score, 40);
+        assert_eq!(loaded.game_id, game.game_id);
+        assert_eq!(loaded.merge_preference, MergePreference::AwayFromMove);
+        assert_eq!(loaded.merge_limit, Some(1));
+        assert_eq!(loaded.merge_linger, 3);
+        assert_eq!((loaded.margin_x, loaded.margin_y), (1, 0));
+    }
+
+    #[test]
+    fn broken_saves_are_rejected() {
+        let tile_width = DEFAULT_TILE_WIDTH;
+        assert!(Grid::from_saved(&saved_board("2 0\n0 3"), tile_width).is_err());
+        assert!(Grid::from_saved(&saved_board("2 0 0\n0 2"), tile_width).is_err());
+        assert!(Grid::from_saved(&saved_board("2"), tile_width).is_err());
+        let huge = vec![vec!["0"; 17].join(" "); 17].join("\n");
+        assert!(Grid::from_saved(&saved_board(&huge), tile_width).is_err());
+    }
+
+    #[test]
+    fn missing_score_is_estimated_from_the_tiles() {
+        let mut saved = saved_board("2 4\n8 2");
+        saved.score = None;
+        let loaded = Grid::from_saved(&saved, DEFAULT_TILE_WIDTH).unwrap();
+        assert_eq!(loaded.score, 12);
+    }
+}
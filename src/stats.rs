@@ -0,0 +1,233 @@
+use crate::game::{GameMode, Grid};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const CSV_HEADER: &str = "timestamp,score,moves,max_tile,result,mode,seed";
+
+/// Outcome of a single finished game
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GameRecord {
+    /// `Grid::game_id` of the game, a game is recorded only once
+    pub game_id: u64,
+    /// seconds since the unix epoch when the game ended
+    pub timestamp: u64,
+    pub score: u32,
+    pub moves: usize,
+    pub max_tile: u32,
+    pub won: bool,
+    pub mode: GameMode,
+    /// the game can be replayed from its seed
+    pub seed: u64,
+}
+
+impl GameRecord {
+    pub fn new(game: &Grid, won: bool) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Self {
+            game_id: game.game_id,
+            timestamp,
+            score: game.score,
+            moves: game.event_summary().total_moves,
+            max_tile: game.tiles.values().map(|tile| tile.n).max().unwrap_or(0),
+            won,
+            mode: game.mode,
+            seed: game.seed,
+        }
+    }
+
+    fn to_csv_row(self) -> String {
+        format!(
+            "{},{},{},{},{},{:?},{}",
+            self.timestamp,
+            self.score,
+            self.moves,
+            self.max_tile,
+            if self.won { "win" } else { "loss" },
+            self.mode,
+            self.seed
+        )
+    }
+}
+
+/// Stats keeps the records of the finished games, saved as json between the sessions
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Stats {
+    pub games: Vec<GameRecord>,
+    /// number of records already written by `export_csv`
+    exported: usize,
+}
+
+impl Stats {
+    /// stats saved at `path`, none recorded yet when there's no file
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(json) => Ok(serde_json::from_str(&json)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)
+    }
+
+    /// record the game unless it already was, its info screen can be shown again,
+    /// true if it's recorded now
+    pub fn record(&mut self, game: &Grid, won: bool) -> bool {
+        if self
+            .games
+            .iter()
+            .any(|record| record.game_id == game.game_id)
+        {
+            return false;
+        }
+        self.games.push(GameRecord::new(game, won));
+        true
+    }
+
+    /// forget every game recorded so far, the csv already exported is left alone
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn wins(&self) -> usize {
+        self.games.iter().filter(|game| game.won).count()
+    }
+
+    pub fn best_score(&self) -> u32 {
+        self.games.iter().map(|game| game.score).max().unwrap_or(0)
+    }
+
+    /// the header and one row per game
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(CSV_HEADER);
+        for game in self.games.iter() {
+            csv.push('\n');
+            csv.push_str(&game.to_csv_row());
+        }
+        csv.push('\n');
+        csv
+    }
+
+    /// append the games that weren't exported yet to the csv at `path`,
+    /// the header is only written when the file is created
+    pub fn export_csv(&mut self, path: &Path) -> io::Result<usize> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let exists = path.exists();
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        if !exists {
+            writeln!(file, "{}", CSV_HEADER)?;
+        }
+        let new = &self.games[self.exported..];
+        for game in new.iter() {
+            writeln!(file, "{}", game.to_csv_row())?;
+        }
+        let count = new.len();
+        self.exported = self.games.len();
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// file in the temp dir only this test uses
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("rust2048_{}_{}", std::process::id(), name));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn records_become_csv_rows() {
+        let record = GameRecord {
+            game_id: 1,
+            timestamp: 1_700_000_000,
+            score: 2932,
+            moves: 250,
+            max_tile: 256,
+            won: false,
+            mode: GameMode::Classic,
+            seed: 42,
+        };
+        let stats = Stats {
+            games: vec![
+                record,
+                GameRecord {
+                    game_id: 2,
+                    timestamp: 1_700_000_600,
+                    score: 20480,
+                    moves: 950,
+                    max_tile: 2048,
+                    won: true,
+                    mode: GameMode::Blitz,
+                    seed: 7,
+                },
+            ],
+            exported: 0,
+        };
+        assert_eq!(
+            stats.to_csv(),
+            format!(
+                "{}\n1700000000,2932,250,256,loss,Classic,42\n1700000600,20480,950,2048,win,Blitz,7\n",
+                CSV_HEADER
+            )
+        );
+    }
+
+    #[test]
+    fn a_game_is_recorded_once() {
+        let game = Grid::new(6, 4);
+        let mut stats = Stats::default();
+        assert!(stats.record(&game, false));
+        assert!(!stats.record(&game.clone(), false));
+        // the same seed played again is another game
+        let replay = Grid::new_with_seed(4, 6, game.coordinates(), 2, game.seed);
+        assert!(stats.record(&replay, true));
+        assert_eq!((stats.games.len(), stats.wins()), (2, 1));
+    }
+
+    #[test]
+    fn stats_load_back_what_was_saved() {
+        let path = temp_path("stats.json");
+        assert!(Stats::load(&path).unwrap().games.is_empty());
+
+        let mut stats = Stats::default();
+        stats.record(&Grid::new(6, 4), true);
+        stats.save(&path).unwrap();
+        let loaded = Stats::load(&path).unwrap();
+        assert_eq!(loaded.games, stats.games);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn export_appends_only_the_new_games_with_their_seed() {
+        let path = temp_path("stats.csv");
+        let mut stats = Stats::default();
+        let game = Grid::new(6, 4);
+        stats.record(&game, false);
+        assert_eq!(stats.export_csv(&path).unwrap(), 1);
+        assert_eq!(stats.export_csv(&path).unwrap(), 0);
+        stats.record(&Grid::new(6, 4), true);
+        assert_eq!(stats.export_csv(&path).unwrap(), 1);
+
+        let csv = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], CSV_HEADER);
+        assert!(lines[1].ends_with(&format!(",loss,Classic,{}", game.seed)));
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,114 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// runs the game headless with `args` on a 4x4 board, feeding it `moves`, and
+/// returns the lines written to stdout
+fn run_headless(args: &[&str], moves: &str) -> Vec<String> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rust2048"))
+        .arg("--headless")
+        .args(args)
+        .env("RUST2048_GAME_SIZE", "4")
+        .env(
+            "RUST2048_DATA_DIR",
+            std::env::temp_dir().join("rust2048-headless-test"),
+        )
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("the game starts");
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(moves.as_bytes())
+        .expect("the moves are written");
+    let output = child.wait_with_output().expect("the game ends");
+    assert!(output.status.success());
+    String::from_utf8(output.stdout)
+        .expect("the output is utf-8")
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+fn parse_board(line: &str) -> Vec<Vec<u32>> {
+    serde_json::from_str(line).expect("a board is a json array of rows")
+}
+
+#[test]
+fn prints_the_board_after_every_move_and_the_score_at_the_end() {
+    let lines = run_headless(&[], "L\nU\n");
+    assert_eq!(lines.len(), 4, "{:?}", lines);
+    for line in &lines[..3] {
+        let board = parse_board(line);
+        assert_eq!(board.len(), 4);
+        assert!(board.iter().all(|row| row.len() == 4));
+    }
+    assert!(lines[3].starts_with("{\"score\":"), "{:?}", lines[3]);
+}
+
+#[test]
+fn skips_unknown_moves() {
+    let lines = run_headless(&[], "X\nL\n");
+    assert_eq!(lines.len(), 3, "{:?}", lines);
+}
+
+#[test]
+fn stops_after_the_move_limit() {
+    let lines = run_headless(&["--headless-moves", "1"], "L\nU\nR\n");
+    assert_eq!(lines.len(), 3, "{:?}", lines);
+    assert!(lines[2].starts_with("{\"score\":"));
+}
+
+#[test]
+fn puzzle_is_played_without_spawns_until_solved() {
+    let path = std::env::temp_dir().join(format!("rust2048-puzzle-{}.txt", std::process::id()));
+    std::fs::write(&path, "2 2 0 0\n4 0 0 0\n0 0 0 0\n0 0 0 0\n").unwrap();
+    let lines = run_headless(&["--puzzle", path.to_str().unwrap(), "8"], "U\nL\nU\nR\n");
+    std::fs::remove_file(&path).unwrap();
+
+    let boards: Vec<Vec<Vec<u32>>> = lines[..lines.len() - 1]
+        .iter()
+        .map(|line| parse_board(line))
+        .collect();
+    assert_eq!(boards[0][0], vec![2, 2, 0, 0]);
+    let tile_counts: Vec<usize> = boards
+        .iter()
+        .map(|board| board.iter().flatten().filter(|n| **n != 0).count())
+        .collect();
+    assert!(
+        tile_counts.windows(2).all(|pair| pair[1] <= pair[0]),
+        "{:?}",
+        tile_counts
+    );
+    // up changes nothing, left makes a 4 next to the 4, the 8 is made by the next up
+    assert_eq!(boards.len(), 4, "{:?}", boards);
+    // the 4 the puzzle starts with counts as merged already
+    assert_eq!(lines[lines.len() - 1], "{\"score\":16}");
+}
+
+#[test]
+fn debug_log_leaves_the_game_alone() {
+    let dir = std::env::temp_dir();
+    let puzzle = dir.join(format!("rust2048-logged-{}.txt", std::process::id()));
+    let log = dir.join(format!("rust2048-logged-{}.log", std::process::id()));
+    std::fs::write(&puzzle, "2 2 0 0\n4 0 0 0\n0 0 0 0\n0 0 0 0\n").unwrap();
+    let args = ["--puzzle", puzzle.to_str().unwrap(), "8"];
+    let plain = run_headless(&args, "U\nL\nU\nR\n");
+    let logged = run_headless(
+        &[&args[..], &["--debug-log", log.to_str().unwrap()]].concat(),
+        "U\nL\nU\nR\n",
+    );
+    std::fs::remove_file(&puzzle).unwrap();
+
+    assert_eq!(logged, plain);
+    // without the feature the flag is only reported and nothing is written
+    if cfg!(feature = "logging") {
+        let records = std::fs::read_to_string(&log).expect("the log is written");
+        std::fs::remove_file(&log).unwrap();
+        assert!(!records.is_empty());
+    } else {
+        assert!(!log.exists());
+    }
+}